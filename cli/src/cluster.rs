@@ -0,0 +1,186 @@
+//! Network profiles: which RPC, Open Lotto deployment and Switchboard queue to use
+//!
+//! Each `--cluster` has built-in defaults that can be overridden per field in
+//! `~/.config/open-lotto/clusters.toml`:
+//!
+//! ```toml
+//! # Cluster used when --cluster is not passed
+//! default = "devnet"
+//!
+//! [devnet]
+//! rpc_url = "https://devnet.helius-rpc.com/?api-key=..."
+//!
+//! [custom]
+//! rpc_url = "https://my-rpc.example.com"
+//! program_id = "..."
+//! switchboard_program_id = "..."
+//! switchboard_queue = "..."
+//! ```
+
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// Switchboard On-Demand program and default queue per cluster
+const SB_ON_DEMAND_PID_MAINNET: &str = "SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv";
+const SB_ON_DEMAND_PID_DEVNET: &str = "Aio4gaXjXzJNVLtzwtNVmSqGKpANtXhybbkhtAC94ji2";
+const SB_QUEUE_MAINNET: &str = "A43DyUGA7s8eXPxqEjJY6EBu1KKbNgfxF8h17VAHn13w";
+const SB_QUEUE_DEVNET: &str = "EYiAmGSdsQTuCw413V5BzaruWuCCSDgTPtBGvLkXHbe7";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Cluster {
+    Mainnet,
+    Devnet,
+    /// Local validator; Switchboard defaults to the devnet deployment (clone it with
+    /// `--clone` or override it in the config file)
+    Localnet,
+    /// Everything comes from the `[custom]` section of the config file
+    Custom,
+}
+
+impl fmt::Display for Cluster {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Cluster::Mainnet => "mainnet",
+            Cluster::Devnet => "devnet",
+            Cluster::Localnet => "localnet",
+            Cluster::Custom => "custom",
+        })
+    }
+}
+
+/// Resolved addresses and endpoint for one cluster
+#[derive(Clone, Debug)]
+pub struct Network {
+    pub cluster: Cluster,
+    pub rpc_url: String,
+    pub program_id: Pubkey,
+    pub switchboard_program_id: Pubkey,
+    pub switchboard_queue: Pubkey,
+}
+
+/// Per-field overrides for one cluster, as written in the config file
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileOverride {
+    rpc_url: Option<String>,
+    program_id: Option<String>,
+    switchboard_program_id: Option<String>,
+    switchboard_queue: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClusterConfig {
+    default: Option<Cluster>,
+    #[serde(default)]
+    mainnet: ProfileOverride,
+    #[serde(default)]
+    devnet: ProfileOverride,
+    #[serde(default)]
+    localnet: ProfileOverride,
+    #[serde(default)]
+    custom: ProfileOverride,
+}
+
+impl ClusterConfig {
+    /// Default config location: ~/.config/open-lotto/clusters.toml
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".config").join("open-lotto").join("clusters.toml"))
+    }
+
+    /// Load the config file; a missing file means "built-in defaults only"
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read cluster config {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse cluster config {}", path.display()))
+    }
+
+    /// Cluster to use when `--cluster` is not passed
+    pub fn default_cluster(&self) -> Option<Cluster> {
+        self.default
+    }
+
+    /// Resolve `cluster`, layering the config file over the built-in defaults
+    ///
+    /// `rpc_url` is used when neither `--rpc-url` nor the profile sets one (the Solana
+    /// CLI config URL).
+    pub fn resolve(&self, cluster: Cluster, fallback_rpc_url: &str) -> Result<Network> {
+        let (defaults, overrides) = match cluster {
+            Cluster::Mainnet => (
+                ProfileOverride::builtin(
+                    "https://api.mainnet-beta.solana.com",
+                    SB_ON_DEMAND_PID_MAINNET,
+                    SB_QUEUE_MAINNET,
+                ),
+                &self.mainnet,
+            ),
+            Cluster::Devnet => (
+                ProfileOverride::builtin(
+                    "https://api.devnet.solana.com",
+                    SB_ON_DEMAND_PID_DEVNET,
+                    SB_QUEUE_DEVNET,
+                ),
+                &self.devnet,
+            ),
+            Cluster::Localnet => (
+                ProfileOverride::builtin("http://127.0.0.1:8899", SB_ON_DEMAND_PID_DEVNET, SB_QUEUE_DEVNET),
+                &self.localnet,
+            ),
+            Cluster::Custom => (
+                ProfileOverride {
+                    rpc_url: Some(fallback_rpc_url.to_string()),
+                    program_id: Some(open_lotto_client::ID.to_string()),
+                    ..ProfileOverride::default()
+                },
+                &self.custom,
+            ),
+        };
+
+        let field = |value: &Option<String>, default: &Option<String>, name: &str| {
+            value.clone().or_else(|| default.clone()).ok_or_else(|| {
+                anyhow!("Cluster '{}' has no {} - set it in the cluster config file", cluster, name)
+            })
+        };
+        let pubkey = |value: String, name: &str| {
+            Pubkey::from_str(&value).map_err(|e| anyhow!("Invalid {} '{}' for cluster '{}': {}", name, value, cluster, e))
+        };
+
+        Ok(Network {
+            cluster,
+            rpc_url: field(&overrides.rpc_url, &defaults.rpc_url, "rpc_url")?,
+            program_id: pubkey(
+                field(&overrides.program_id, &defaults.program_id, "program_id")?,
+                "program_id",
+            )?,
+            switchboard_program_id: pubkey(
+                field(&overrides.switchboard_program_id, &defaults.switchboard_program_id, "switchboard_program_id")?,
+                "switchboard_program_id",
+            )?,
+            switchboard_queue: pubkey(
+                field(&overrides.switchboard_queue, &defaults.switchboard_queue, "switchboard_queue")?,
+                "switchboard_queue",
+            )?,
+        })
+    }
+}
+
+impl ProfileOverride {
+    fn builtin(rpc_url: &str, switchboard_program_id: &str, switchboard_queue: &str) -> Self {
+        Self {
+            rpc_url: Some(rpc_url.to_string()),
+            program_id: Some(open_lotto_client::ID.to_string()),
+            switchboard_program_id: Some(switchboard_program_id.to_string()),
+            switchboard_queue: Some(switchboard_queue.to_string()),
+        }
+    }
+}
@@ -0,0 +1,499 @@
+//! Naming program errors
+//!
+//! A failed instruction only reports `custom program error: 0x1779`. [`explain`] turns
+//! the code into the error's name and message, for Open Lotto's own errors, Anchor's
+//! framework errors and, from the program log, Switchboard's, and suggests what to do
+//! about it. Where the answer depends on on-chain state ("pot still open, ends in
+//! 3m12s") the pot or manager the instruction touched is fetched.
+
+use anyhow::Error;
+use open_lotto_client::{AccountDeserialize, AnchorErrorCode, ErrorCode, Pot, PotManager};
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    nonblocking::rpc_client::RpcClient,
+    rpc_request::{RpcError, RpcResponseErrorData},
+};
+use solana_sdk::{
+    instruction::InstructionError, message::Message, pubkey::Pubkey, transaction::TransactionError,
+};
+use std::fmt;
+use std::future::Future;
+use std::sync::OnceLock;
+
+use crate::unix_now;
+
+/// Anchor framework errors a misconfigured command can run into
+const ANCHOR_ERRORS: &[AnchorErrorCode] = &[
+    AnchorErrorCode::InstructionMissing,
+    AnchorErrorCode::InstructionFallbackNotFound,
+    AnchorErrorCode::InstructionDidNotDeserialize,
+    AnchorErrorCode::ConstraintMut,
+    AnchorErrorCode::ConstraintHasOne,
+    AnchorErrorCode::ConstraintSigner,
+    AnchorErrorCode::ConstraintSeeds,
+    AnchorErrorCode::ConstraintAddress,
+    AnchorErrorCode::ConstraintTokenMint,
+    AnchorErrorCode::ConstraintTokenOwner,
+    AnchorErrorCode::AccountDiscriminatorMismatch,
+    AnchorErrorCode::AccountDidNotDeserialize,
+    AnchorErrorCode::AccountNotEnoughKeys,
+    AnchorErrorCode::AccountOwnedByWrongProgram,
+    AnchorErrorCode::AccountNotSigner,
+    AnchorErrorCode::AccountNotInitialized,
+    AnchorErrorCode::DeclaredProgramIdMismatch,
+];
+
+#[derive(Clone, Copy)]
+struct Programs {
+    open_lotto: Pubkey,
+    switchboard: Pubkey,
+}
+
+static PROGRAMS: OnceLock<Programs> = OnceLock::new();
+
+/// Set the programs whose errors are named; only the first call counts
+pub fn configure(open_lotto: Pubkey, switchboard: Pubkey) {
+    let _ = PROGRAMS.set(Programs { open_lotto, switchboard });
+}
+
+tokio::task_local! {
+    static SCOPED_PROGRAMS: Programs;
+}
+
+/// Run `f` naming the errors of these programs instead of the configured ones
+pub async fn with_programs<F: Future>(open_lotto: Pubkey, switchboard: Pubkey, f: F) -> F::Output {
+    SCOPED_PROGRAMS.scope(Programs { open_lotto, switchboard }, f).await
+}
+
+fn programs() -> Programs {
+    SCOPED_PROGRAMS.try_with(|programs| *programs).unwrap_or_else(|_| {
+        PROGRAMS.get().copied().unwrap_or(Programs {
+            open_lotto: open_lotto_client::ID,
+            switchboard: Pubkey::default(),
+        })
+    })
+}
+
+/// A custom error raised by one of a transaction's instructions
+#[derive(Debug, PartialEq, Eq)]
+pub struct ProgramError {
+    pub program: String,
+    pub code: u32,
+    pub name: String,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+impl fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} error {} ({}): {}", self.program, self.name, self.code, self.message)?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\nHint: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// Where a decoded error's name came from, which decides the hint
+#[derive(Clone, Copy, Debug)]
+enum Source {
+    OpenLotto(ErrorCode),
+    Anchor(AnchorErrorCode),
+    Switchboard,
+    Log,
+}
+
+struct Decoded {
+    error: ProgramError,
+    source: Source,
+    /// Accounts of the failing instruction
+    accounts: Vec<Pubkey>,
+}
+
+/// Wrap a failed send, naming the program error behind it when it can be decoded
+///
+/// The `ClientError` stays the error's source, so callers can still downcast to it.
+pub async fn explain(rpc_client: &RpcClient, message: &Message, error: ClientError) -> Error {
+    let Some(transaction_error) = error.get_transaction_error() else {
+        return Error::new(error);
+    };
+    match describe(rpc_client, message, &transaction_error, &simulation_logs(&error)).await {
+        Some(program_error) => Error::new(error).context(program_error.to_string()),
+        None => Error::new(error),
+    }
+}
+
+/// Name the program error behind `transaction_error`, with a hint, if it is a custom
+/// error this module knows or the program `logs` name it
+pub async fn describe(
+    rpc_client: &RpcClient,
+    message: &Message,
+    transaction_error: &TransactionError,
+    logs: &[String],
+) -> Option<ProgramError> {
+    let decoded = decode(&programs(), message, transaction_error, logs)?;
+    let mut program_error = decoded.error;
+    program_error.hint = hint(rpc_client, decoded.source, &decoded.accounts).await;
+    Some(program_error)
+}
+
+/// Program log of a transaction rejected in preflight simulation
+fn simulation_logs(error: &ClientError) -> Vec<String> {
+    match error.kind() {
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+            ..
+        }) => result.logs.clone().unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+fn decode(
+    programs: &Programs,
+    message: &Message,
+    transaction_error: &TransactionError,
+    logs: &[String],
+) -> Option<Decoded> {
+    let TransactionError::InstructionError(index, InstructionError::Custom(code)) = *transaction_error else {
+        return None;
+    };
+    let instruction = message.instructions.get(index as usize)?;
+    let program_id = *message.account_keys.get(instruction.program_id_index as usize)?;
+    let accounts = instruction
+        .accounts
+        .iter()
+        .filter_map(|i| message.account_keys.get(*i as usize).copied())
+        .collect();
+
+    let program = if program_id == programs.open_lotto {
+        "Open Lotto".to_string()
+    } else if program_id == programs.switchboard {
+        "Switchboard".to_string()
+    } else {
+        program_id.to_string()
+    };
+
+    let open_lotto_error = ErrorCode::try_from_32(code).filter(|_| program_id == programs.open_lotto);
+    let anchor_error = ANCHOR_ERRORS.iter().copied().find(|e| u32::from(*e) == code);
+    let (name, message, source) = if let Some(e) = open_lotto_error {
+        (e.name(), e.to_string(), Source::OpenLotto(e))
+    } else if let Some(e) = anchor_error {
+        (e.name(), e.to_string(), Source::Anchor(e))
+    } else {
+        let (name, message) = logged_error(logs, code)?;
+        let source = if program_id == programs.switchboard { Source::Switchboard } else { Source::Log };
+        (name, message, source)
+    };
+
+    Some(Decoded {
+        error: ProgramError { program, code, name, message, hint: None },
+        source,
+        accounts,
+    })
+}
+
+/// Name and message of error `code` from the `AnchorError ... Error Code: <name>. Error
+/// Number: <code>. Error Message: <message>.` line Anchor programs log
+fn logged_error(logs: &[String], code: u32) -> Option<(String, String)> {
+    let number = format!(". Error Number: {}. ", code);
+    logs.iter().find_map(|line| {
+        let (head, message) = line.split_once(&number)?;
+        let name = head.rsplit_once("Error Code: ")?.1;
+        let message = message.strip_prefix("Error Message: ")?.trim_end_matches('.');
+        Some((name.to_string(), message.to_string()))
+    })
+}
+
+/// Open Lotto accounts an instruction touched, for hints that depend on their state
+#[derive(Default)]
+struct State {
+    pot: Option<(Pubkey, Pot)>,
+    manager: Option<(Pubkey, PotManager)>,
+}
+
+impl State {
+    async fn load(rpc_client: &RpcClient, accounts: &[Pubkey]) -> Self {
+        let mut state = Self::default();
+        let Ok(fetched) = rpc_client.get_multiple_accounts(accounts).await else {
+            return state;
+        };
+        for (address, account) in accounts.iter().zip(fetched) {
+            let Some(account) = account else { continue };
+            if let Ok(pot) = Pot::try_deserialize(&mut account.data.as_slice()) {
+                state.pot.get_or_insert((*address, pot));
+            } else if let Ok(manager) = PotManager::try_deserialize(&mut account.data.as_slice()) {
+                state.manager.get_or_insert((*address, manager));
+            }
+        }
+        state
+    }
+
+    fn pot_address(&self) -> String {
+        self.pot.as_ref().map_or("<POT>".to_string(), |(address, _)| address.to_string())
+    }
+}
+
+async fn hint(rpc_client: &RpcClient, source: Source, accounts: &[Pubkey]) -> Option<String> {
+    match source {
+        Source::OpenLotto(code) => {
+            let state = State::load(rpc_client, accounts).await;
+            open_lotto_hint(code, &state, unix_now().ok()?)
+        }
+        Source::Anchor(code) => anchor_hint(code).map(str::to_string),
+        Source::Switchboard => Some(
+            "`check-randomness --account <RANDOMNESS>` shows where the request stands; a retried commit picks another oracle"
+                .to_string(),
+        ),
+        Source::Log => None,
+    }
+}
+
+fn open_lotto_hint(code: ErrorCode, state: &State, now: u64) -> Option<String> {
+    let pot = state.pot_address();
+    let hint = match code {
+        ErrorCode::EndTimestampPassed => {
+            "the first pot's end is already past on the cluster clock; pass a larger --end-in".to_string()
+        }
+        ErrorCode::PotClosed => match &state.pot {
+            Some((_, p)) => format!(
+                "the pot ended {} ago; buy tickets in the manager's current pot (`show-manager --manager {}`)",
+                format_duration(now.saturating_sub(p.end_timestamp)),
+                p.pot_manager
+            ),
+            None => "the pot has ended; buy tickets in the manager's current pot (`show-manager`)".to_string(),
+        },
+        ErrorCode::RandomnessAlreadyRevealed => format!(
+            "the draw must land right after its randomness commit; `resume --pot {}` commits fresh randomness and draws again",
+            pot
+        ),
+        ErrorCode::NotEnoughFundsToPlay => {
+            "the wallet's token account holds less than the ticket price".to_string()
+        }
+        ErrorCode::InvalidRandomnessAccount => format!(
+            "settle with the randomness account the pot was drawn with (`show-pot --pot {}`)",
+            pot
+        ),
+        ErrorCode::RandomnessNotResolved => format!(
+            "the oracle has not revealed yet; wait a few seconds and run `settle --pot {}` again",
+            pot
+        ),
+        ErrorCode::TicketAccountNotWinning => {
+            format!("the wallet does not hold the winning ticket; `winner --pot {}` shows who does", pot)
+        }
+        ErrorCode::InsufficientWagerBalance => match &state.manager {
+            Some((_, m)) => format!("the manager has {} lamports of wagers escrowed; withdraw at most that", m.wager_balance),
+            None => "withdraw at most the manager's escrowed wagers (`show-manager`)".to_string(),
+        },
+        ErrorCode::WagerEscrowBelowRent => {
+            "withdraw less, leaving the wager escrow its rent-exempt minimum".to_string()
+        }
+        ErrorCode::PotNotEnded => {
+            let end = match (&state.pot, &state.manager) {
+                (Some((_, p)), _) => Some(p.end_timestamp),
+                (None, Some((_, m))) => Some(m.timestamps.0),
+                (None, None) => None,
+            };
+            match end {
+                Some(end) if end >= now => format!(
+                    "pot still open, ends in {}; draw and roll it once it has ended",
+                    format_duration(end + 1 - now)
+                ),
+                Some(_) => "the pot has only just ended on the cluster clock; retry in a few seconds".to_string(),
+                None => "the manager's current pot is still open; draw and roll it after it ends".to_string(),
+            }
+        }
+        ErrorCode::PotAlreadySettled => {
+            format!("nothing left to do; `winner --pot {}` shows the result", pot)
+        }
+        ErrorCode::KeeperLeaseHeld => match &state.pot {
+            Some((_, p)) => format!(
+                "another keeper holds the lease (`show-manager --manager {}`); wait for it to lapse, or take it over with `acquire-lease` as the manager authority",
+                p.pot_manager
+            ),
+            None => "another keeper holds the lease (`show-manager`); wait for it to lapse, or take it over with `acquire-lease` as the manager authority".to_string(),
+        },
+        ErrorCode::InvalidLeaseDuration => format!("lease for 1 to {} slots", open_lotto_client::MAX_LEASE_SLOTS),
+        ErrorCode::PotAlreadyDrawn => format!(
+            "someone else drew the pot first; `settle --pot {}` once its randomness is revealed, or `draw` it again if it is still unsettled {} slots after that draw",
+            pot,
+            open_lotto_client::REDRAW_TIMEOUT_SLOTS
+        ),
+        ErrorCode::PotNotSettled => format!(
+            "the winner has not been picked yet; `settle --pot {}` once its randomness is revealed",
+            pot
+        ),
+        ErrorCode::PrizeAlreadyPaid => {
+            format!("nothing left to do; `winner --pot {}` shows who received it", pot)
+        }
+        ErrorCode::AutoPayoutDisabled => match &state.pot {
+            Some((_, p)) => format!(
+                "the winner claims the prize with `claim`, or the authority runs `set-auto-payout --manager {} --enabled true`",
+                p.pot_manager
+            ),
+            None => "the winner claims the prize with `claim`, or the authority enables `set-auto-payout`".to_string(),
+        },
+        ErrorCode::BountyTooLarge => format!("set a bounty of at most {} token base units", open_lotto_client::MAX_CRANK_BOUNTY),
+        ErrorCode::PotEmpty => "nobody entered this pot, so there is nothing to draw; the manager can roll straight on".to_string(),
+        ErrorCode::RandomnessSeedMismatch => "the randomness account was re-committed after the draw; only a reveal of the original commit can settle this pot".to_string(),
+        ErrorCode::KeeperNotAllowed => {
+            "the manager authority has to allow this keeper first with `set-lease-keepers`".to_string()
+        }
+        ErrorCode::TooManyLeaseKeepers => {
+            format!("allow at most {} keepers besides the authority", open_lotto_client::MAX_LEASE_KEEPERS)
+        }
+        ErrorCode::NoPayoutReserve => {
+            "the pot was drawn before auto-payout was switched on, so its winner claims the prize with `claim`".to_string()
+        }
+    };
+    Some(hint)
+}
+
+fn anchor_hint(code: AnchorErrorCode) -> Option<&'static str> {
+    let hint = match code {
+        AnchorErrorCode::InstructionMissing
+        | AnchorErrorCode::InstructionFallbackNotFound
+        | AnchorErrorCode::InstructionDidNotDeserialize => {
+            "the deployed program does not know this instruction; it may be older than this CLI"
+        }
+        AnchorErrorCode::ConstraintHasOne | AnchorErrorCode::ConstraintAddress => {
+            "an account does not match the one recorded on the pot manager; admin commands must be signed by the manager's authority (`show-manager`)"
+        }
+        AnchorErrorCode::ConstraintSigner | AnchorErrorCode::AccountNotSigner => {
+            "a required signer did not sign; check --keypair"
+        }
+        AnchorErrorCode::ConstraintSeeds | AnchorErrorCode::DeclaredProgramIdMismatch => {
+            "an address is not the one the program derives; check that --program-id matches the deployed program"
+        }
+        AnchorErrorCode::ConstraintTokenMint | AnchorErrorCode::ConstraintTokenOwner => {
+            "the token account belongs to another wallet or mint than the manager's"
+        }
+        AnchorErrorCode::AccountDiscriminatorMismatch
+        | AnchorErrorCode::AccountDidNotDeserialize
+        | AnchorErrorCode::AccountOwnedByWrongProgram => {
+            "an account is not of the expected type, or was created by an older program version (see `force-close`)"
+        }
+        AnchorErrorCode::AccountNotInitialized => {
+            "an account does not exist on this cluster; check --network and the addresses passed"
+        }
+        _ => return None,
+    };
+    Some(hint)
+}
+
+/// `3m12s`, `2h05m`, `4d3h`
+fn format_duration(secs: u64) -> String {
+    let (days, hours, minutes, seconds) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60, secs % 60);
+    if days > 0 {
+        format!("{}d{}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h{:02}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m{:02}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_compute_budget_interface::ComputeBudgetInstruction;
+    use solana_sdk::instruction::{AccountMeta, Instruction};
+
+    const PROGRAMS: Programs = Programs {
+        open_lotto: open_lotto_client::ID,
+        switchboard: Pubkey::new_from_array([7; 32]),
+    };
+
+    /// A budget instruction followed by one instruction of `program` over `accounts`
+    fn message(program: Pubkey, accounts: &[Pubkey]) -> Message {
+        let metas = accounts.iter().map(|a| AccountMeta::new(*a, false)).collect();
+        Message::new(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(200_000),
+                Instruction::new_with_bytes(program, &[], metas),
+            ],
+            Some(&Pubkey::new_unique()),
+        )
+    }
+
+    fn custom(code: u32) -> TransactionError {
+        TransactionError::InstructionError(1, InstructionError::Custom(code))
+    }
+
+    #[test]
+    fn names_open_lotto_and_anchor_errors() {
+        let pot_manager = Pubkey::new_unique();
+        let message = message(PROGRAMS.open_lotto, &[pot_manager]);
+
+        let decoded = decode(&PROGRAMS, &message, &custom(ErrorCode::PotNotEnded.as_u32()), &[]).unwrap();
+        assert!(matches!(decoded.source, Source::OpenLotto(ErrorCode::PotNotEnded)));
+        assert_eq!(decoded.accounts, vec![pot_manager]);
+        assert_eq!(
+            decoded.error.to_string(),
+            "Open Lotto error PotNotEnded (6009): The current pot has not ended yet"
+        );
+
+        let decoded = decode(&PROGRAMS, &message, &custom(2001), &[]).unwrap();
+        assert!(matches!(decoded.source, Source::Anchor(AnchorErrorCode::ConstraintHasOne)));
+        assert_eq!(decoded.error.message, "A has one constraint was violated");
+
+        // Budget instructions and non-custom errors are left alone
+        let budget_failure = TransactionError::InstructionError(0, InstructionError::Custom(6009));
+        assert!(decode(&PROGRAMS, &message, &budget_failure, &[]).is_none());
+        assert!(decode(&PROGRAMS, &message, &TransactionError::AccountNotFound, &[]).is_none());
+    }
+
+    #[test]
+    fn names_other_programs_errors_from_their_log() {
+        let message = message(PROGRAMS.switchboard, &[Pubkey::new_unique()]);
+        let logs = vec![
+            "Program log: Instruction: RandomnessReveal".to_string(),
+            "Program log: AnchorError occurred. Error Code: RandomnessTooOld. Error Number: 6032. Error Message: Randomness is too old.".to_string(),
+        ];
+
+        let decoded = decode(&PROGRAMS, &message, &custom(6032), &logs).unwrap();
+        assert!(matches!(decoded.source, Source::Switchboard));
+        assert_eq!(decoded.error.program, "Switchboard");
+        assert_eq!(decoded.error.name, "RandomnessTooOld");
+        assert_eq!(decoded.error.message, "Randomness is too old");
+
+        // Open Lotto's codes only apply to Open Lotto, and unlogged codes stay unnamed
+        assert!(decode(&PROGRAMS, &message, &custom(6009), &logs).is_none());
+    }
+
+    #[test]
+    fn hints_use_the_accounts_state() {
+        let now = 1_700_000_000;
+        let manager = PotManager {
+            authority: Pubkey::new_unique(),
+            treasury: Pubkey::new_unique(),
+            token_mint: Pubkey::new_unique(),
+            rent: 0,
+            last_random_number: 0,
+            timestamps: (now + 191, now + 86591),
+            bump: 255,
+            wager: 100,
+            wager_balance: 300,
+            crank_bounty: 0,
+            auto_payout: false,
+            name: "default".to_string(),
+        };
+        let state = State { pot: None, manager: Some((Pubkey::new_unique(), manager)) };
+
+        let hint = open_lotto_hint(ErrorCode::PotNotEnded, &state, now).unwrap();
+        assert!(hint.starts_with("pot still open, ends in 3m12s"), "{}", hint);
+        let hint = open_lotto_hint(ErrorCode::InsufficientWagerBalance, &state, now).unwrap();
+        assert!(hint.contains("300 lamports"), "{}", hint);
+        let hint = open_lotto_hint(ErrorCode::PotAlreadySettled, &State::default(), now).unwrap();
+        assert!(hint.contains("winner --pot <POT>"), "{}", hint);
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(45), "45s");
+        assert_eq!(format_duration(192), "3m12s");
+        assert_eq!(format_duration(7500), "2h05m");
+        assert_eq!(format_duration(2 * 86400 + 3 * 3600), "2d3h");
+    }
+}
@@ -0,0 +1,529 @@
+//! Long-running keeper for a pot manager
+//!
+//! Watches `PotManager.timestamps`, and once the current pot ends walks it through
+//! randomness creation, commit, draw, reveal and settle before rolling the manager to
+//! its next pot. Progress is written to a state file after every step so a restarted
+//! keeper resumes mid-cycle instead of orphaning the randomness account.
+//!
+//! With a lease, several keepers can watch the same manager: the one holding the
+//! manager's on-chain keeper lease works the pots, the others stand by and take the
+//! lease over once it lapses.
+//!
+//! [`fleet`] runs one keeper per manager listed in a config file.
+
+pub mod fleet;
+
+use anyhow::{anyhow, Context, Result};
+use open_lotto_client::{accounts, OpenLotto, Pot};
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+
+use crate::cluster::Network;
+use crate::metrics;
+use crate::notify::{Event, Notifier};
+use crate::signer::Wallet;
+use crate::{switchboard, unix_now};
+
+pub struct KeeperConfig {
+    pub manager: Pubkey,
+    pub state_file: PathBuf,
+    pub poll_interval: Duration,
+    pub max_retries: u32,
+    pub reveal_timeout_secs: u64,
+    /// Where to serve `/metrics` and `/healthz`, if anywhere
+    pub metrics_bind: Option<String>,
+    /// How long a pot may stay ended without settlement before `/healthz` fails
+    pub health_threshold: Duration,
+    /// URLs every keeper event is posted to
+    pub webhooks: Vec<String>,
+    /// Webhook deliveries not made yet
+    pub outbox: PathBuf,
+    /// Fee payer balance, in lamports, below which a low-balance event is sent
+    pub low_balance: u64,
+    /// Duration of the keeper lease to hold, if the keeper runs with one
+    pub lease_slots: Option<u64>,
+}
+
+impl KeeperConfig {
+    /// Default state file location: ~/.config/open-lotto/keeper-<manager>.json
+    pub fn default_state_file(manager: &Pubkey) -> Result<PathBuf> {
+        let home = dirs::home_dir().ok_or_else(|| anyhow!("Unable to find home directory"))?;
+        Ok(home
+            .join(".config")
+            .join("open-lotto")
+            .join(format!("keeper-{}.json", manager)))
+    }
+}
+
+/// Where the keeper is in the draw/settle cycle of a pot
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Waiting for the pot to end
+    #[default]
+    Waiting,
+    /// randomnessInit sent for the persisted randomness keypair. State files written
+    /// before commit and draw were bundled may say `committed`; a commit that was never
+    /// drawn is stale by now, so both resume with a fresh commit + draw.
+    #[serde(alias = "committed")]
+    RandomnessCreated,
+    /// randomnessCommit + draw_lottery landed, waiting for the oracle to reveal
+    Drawn,
+    /// settle_lottery landed (or nothing to settle), ready to pay out (auto-payout
+    /// managers) and roll
+    Settled,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PotProgress {
+    #[serde(with = "pubkey_string")]
+    pub pot: Pubkey,
+    pub stage: Stage,
+    /// Randomness keypair bytes, saved before randomnessInit is sent
+    pub randomness_keypair: Option<Vec<u8>>,
+    /// Signature of the commit + draw transaction, reported once the pot is settled
+    #[serde(default)]
+    pub draw_signature: Option<String>,
+}
+
+impl PotProgress {
+    fn new(pot: Pubkey) -> Self {
+        Self {
+            pot,
+            stage: Stage::Waiting,
+            randomness_keypair: None,
+            draw_signature: None,
+        }
+    }
+
+    fn randomness_keypair(&self) -> Result<Keypair> {
+        let bytes = self
+            .randomness_keypair
+            .as_ref()
+            .ok_or_else(|| anyhow!("No randomness keypair recorded for pot {}", self.pot))?;
+        Keypair::try_from(bytes.as_slice()).map_err(|e| anyhow!("Invalid randomness keypair in state: {}", e))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct KeeperState {
+    #[serde(with = "pubkey_string")]
+    pub manager: Pubkey,
+    pub current: Option<PotProgress>,
+}
+
+impl KeeperState {
+    /// Load the state file, or start fresh if it does not exist yet
+    pub fn load(path: &Path, manager: &Pubkey) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self {
+                manager: *manager,
+                current: None,
+            });
+        }
+
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read keeper state {}", path.display()))?;
+        let state: Self = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse keeper state {}", path.display()))?;
+        if state.manager != *manager {
+            return Err(anyhow!(
+                "State file {} belongs to manager {}, not {}",
+                path.display(),
+                state.manager,
+                manager
+            ));
+        }
+        Ok(state)
+    }
+
+    /// Write the state atomically (temp file + rename) so a crash never leaves it half written
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to write keeper state {}", path.display()))
+    }
+
+    /// Progress for `pot`, discarding progress recorded for an older pot
+    fn progress_for(&mut self, pot: Pubkey) -> &mut PotProgress {
+        if self.current.as_ref().map(|p| p.pot) != Some(pot) {
+            self.current = Some(PotProgress::new(pot));
+        }
+        self.current.as_mut().expect("progress was just set")
+    }
+}
+
+/// Run the keeper until the process is stopped
+pub async fn run(
+    rpc_client: &RpcClient,
+    program: &OpenLotto,
+    payer: &Wallet,
+    network: &Network,
+    config: &KeeperConfig,
+) -> Result<()> {
+    let mut state = KeeperState::load(&config.state_file, &config.manager)?;
+    let notifier = Arc::new(Notifier::new(config.webhooks.clone(), config.outbox.clone())?);
+    tokio::spawn({
+        let notifier = notifier.clone();
+        async move { notifier.run().await }
+    });
+
+    eprintln!("Keeper watching manager {}", config.manager);
+    eprintln!("State file: {}", config.state_file.display());
+    if let Some(progress) = &state.current {
+        eprintln!("Resuming pot {} at stage {:?}", progress.pot, progress.stage);
+    }
+    if let Some(bind) = &config.metrics_bind {
+        serve_metrics(bind, config.health_threshold).await?;
+    }
+
+    // Only the first of consecutive failures is reported, not every poll of an outage
+    let mut failing = false;
+    loop {
+        let wait = match step(rpc_client, program, payer, network, config, &notifier, &mut state).await {
+            Ok(wait) => {
+                failing = false;
+                wait
+            }
+            Err(e) => {
+                eprintln!("Keeper step failed, will retry: {:#}", e);
+                if !failing {
+                    notifier.notify(Event::Failed {
+                        pot: state.current.as_ref().map(|progress| progress.pot),
+                        error: format!("{:#}", e),
+                    });
+                    failing = true;
+                }
+                Some(config.poll_interval)
+            }
+        };
+        if let Some(wait) = wait {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Serve `/metrics` and `/healthz` on `bind` in the background
+pub async fn serve_metrics(bind: &str, health_threshold: Duration) -> Result<()> {
+    let listener = TcpListener::bind(bind)
+        .await
+        .with_context(|| format!("Failed to bind metrics server to {}", bind))?;
+    eprintln!("Serving /metrics and /healthz on {}", listener.local_addr()?);
+    tokio::spawn(metrics::serve(listener, health_threshold));
+    Ok(())
+}
+
+/// Advance the current pot by one stage
+///
+/// Returns how long to sleep before the next step, or `None` to continue immediately.
+async fn step(
+    rpc_client: &RpcClient,
+    program: &OpenLotto,
+    payer: &Wallet,
+    network: &Network,
+    config: &KeeperConfig,
+    notifier: &Notifier,
+    state: &mut KeeperState,
+) -> Result<Option<Duration>> {
+    if let Some(slots) = config.lease_slots {
+        if !hold_lease(rpc_client, program, payer, config, slots).await? {
+            return Ok(Some(config.poll_interval));
+        }
+    }
+
+    let manager = with_retry("fetch pot manager", config.max_retries, || async {
+        accounts::fetch_pot_manager(rpc_client, &config.manager).await
+    })
+    .await?;
+
+    let (current_end, _) = manager.timestamps;
+    let pot_address = program.pot_address(&config.manager, current_end);
+    check_balances(rpc_client, program, payer, config, notifier).await;
+
+    let now = unix_now()?;
+    if now <= current_end {
+        metrics::unsettled(&config.manager, None);
+        let until_end = Duration::from_secs(current_end - now + 1);
+        return Ok(Some(until_end.min(config.poll_interval)));
+    }
+
+    let pot: Pot = with_retry("fetch pot", config.max_retries, || async {
+        accounts::fetch_pot(rpc_client, &pot_address).await
+    })
+    .await?;
+
+    let awaiting_settlement = !pot.settled && pot.total_participants > 0;
+    metrics::unsettled(&config.manager, awaiting_settlement.then_some((pot_address, current_end)));
+
+    let progress = state.progress_for(pot_address);
+    match progress.stage {
+        Stage::Waiting => {
+            if pot.settled {
+                progress.stage = Stage::Settled;
+            } else if pot.total_participants == 0 {
+                eprintln!("Pot {} ended without tickets, nothing to draw", pot_address);
+                progress.stage = Stage::Settled;
+            } else if pot.randomness_account != Pubkey::default() && !redraw_open(rpc_client, &pot).await? {
+                eprintln!("Pot {} already drawn by another keeper", pot_address);
+                progress.stage = Stage::Drawn;
+            } else {
+                // Persist the keypair before paying rent for it, so it can never be orphaned
+                let randomness_keypair = Keypair::new();
+                progress.randomness_keypair = Some(randomness_keypair.to_bytes().to_vec());
+                state.save(&config.state_file)?;
+
+                eprintln!("Pot {} ended, creating randomness account {}", pot_address, randomness_keypair.pubkey());
+                with_retry("create randomness", config.max_retries, || async {
+                    if rpc_client.get_account(&randomness_keypair.pubkey()).await.is_ok() {
+                        return Ok(());
+                    }
+                    switchboard::create_randomness(rpc_client, payer, &randomness_keypair, network).await.map(|_| ())
+                })
+                .await?;
+                state.progress_for(pot_address).stage = Stage::RandomnessCreated;
+            }
+        }
+
+        Stage::RandomnessCreated
+            if pot.randomness_account != Pubkey::default() && !redraw_open(rpc_client, &pot).await? =>
+        {
+            // A third party drew the pot for its bounty while ours was being set up
+            eprintln!("Pot {} already drawn by another keeper", pot_address);
+            state.progress_for(pot_address).stage = Stage::Drawn;
+        }
+
+        Stage::RandomnessCreated => {
+            let randomness = progress.randomness_keypair()?.pubkey();
+            let (signature, oracle) = with_retry("commit and draw", config.max_retries, || async {
+                crate::call_commit_and_draw(rpc_client, program, payer, &pot_address, &randomness, network).await
+            })
+            .await?;
+            eprintln!("Drew pot {} with oracle {}: {}", pot_address, oracle, signature);
+            metrics::pot_drawn();
+            notifier.notify(Event::Drawn {
+                pot: pot_address,
+                randomness_account: randomness,
+                oracle,
+                signatures: vec![signature.clone()],
+            });
+            progress.draw_signature = Some(signature);
+            progress.stage = Stage::Drawn;
+        }
+
+        Stage::Drawn => {
+            if pot.settled {
+                progress.stage = Stage::Settled;
+            } else {
+                let randomness = pot.randomness_account;
+                let started = Instant::now();
+                // Reveal and settle share a transaction: the value only reads back in the
+                // reveal's slot, so a retry reveals again
+                let reveal_and_settle = with_retry("reveal and settle", config.max_retries, || async {
+                    crate::call_reveal_and_settle(rpc_client, program, payer, &pot_address, &randomness, network).await
+                });
+                let settled = tokio::time::timeout(Duration::from_secs(config.reveal_timeout_secs), reveal_and_settle)
+                    .await
+                    .map_err(|_| anyhow!("Randomness {} not revealed within {}s", randomness, config.reveal_timeout_secs))
+                    .and_then(|settled| settled);
+                let signature = match settled {
+                    Ok(signature) => signature,
+                    // The drawer never revealed, or the oracle is gone: draw again with fresh
+                    // randomness, committed to another oracle since the failed one is forgotten
+                    Err(e) if redraw_open(rpc_client, &pot).await? => {
+                        eprintln!("Pot {} unsettled past the redraw timeout, drawing it again: {:#}", pot_address, e);
+                        state.progress_for(pot_address).stage = Stage::Waiting;
+                        state.save(&config.state_file)?;
+                        return Ok(None);
+                    }
+                    Err(e) => return Err(e),
+                };
+                metrics::reveal_latency(started.elapsed());
+                eprintln!("Settled pot {}: {}", pot_address, signature);
+                metrics::pot_settled();
+
+                let progress = state.progress_for(pot_address);
+                let signatures = progress.draw_signature.iter().cloned().chain([signature]).collect();
+                progress.stage = Stage::Settled;
+                match accounts::fetch_pot(rpc_client, &pot_address).await {
+                    Ok(settled) => {
+                        let winner = crate::fetch_winner(rpc_client, program, &pot_address, &settled).await;
+                        notifier.notify(Event::Settled {
+                            pot: pot_address,
+                            winner_ticket_index: winner.index,
+                            participant: winner.participant,
+                            prize: settled.prize_amount(),
+                            signatures,
+                        });
+                    }
+                    Err(e) => eprintln!("Settled pot {} but could not read its winner: {:#}", pot_address, e),
+                }
+            }
+        }
+
+        Stage::Settled => {
+            // Pots drawn before auto-payout was switched on have no reserve and are claimed
+            if manager.auto_payout && pot.settled && !pot.prize_paid && pot.payout_reserve > 0 {
+                let paid = with_retry("payout winner", config.max_retries, || async {
+                    crate::call_payout_winner(rpc_client, program, payer, &pot_address, &pot).await
+                })
+                .await;
+                // The winner can still claim, so a failed payout must not hold up the roll
+                match paid {
+                    Ok((signature, winner, _)) => {
+                        eprintln!("Paid out pot {} to {}: {}", pot_address, winner, signature);
+                        metrics::prize_paid_out();
+                        notifier.notify(Event::PaidOut {
+                            pot: pot_address,
+                            winner,
+                            prize: pot.prize_amount(),
+                            signature,
+                        });
+                    }
+                    Err(e) => {
+                        eprintln!("Could not pay out pot {}, leaving it to the winner to claim: {:#}", pot_address, e);
+                        notifier.notify(Event::Failed {
+                            pot: Some(pot_address),
+                            error: format!("{:#}", e),
+                        });
+                    }
+                }
+            }
+
+            let signature = with_retry("roll pot manager", config.max_retries, || async {
+                let instruction = program.roll_pot_manager(&config.manager, &manager, &payer.pubkey());
+                crate::send_instruction(rpc_client, payer, instruction).await
+            })
+            .await?;
+            eprintln!("Rolled manager {} to its next pot: {}", config.manager, signature);
+            metrics::unsettled(&config.manager, None);
+            state.current = None;
+        }
+    }
+
+    state.save(&config.state_file)?;
+    Ok(None)
+}
+
+/// Whether `pot`'s draw went unsettled long enough to be replaced
+async fn redraw_open(rpc_client: &RpcClient, pot: &Pot) -> Result<bool> {
+    Ok(pot.redraw_open(rpc_client.get_slot().await?))
+}
+
+/// Make sure this keeper holds the manager's lease, renewing it once half of it has run
+/// out and taking it over once it lapsed; `false` while another keeper holds it
+async fn hold_lease(
+    rpc_client: &RpcClient,
+    program: &OpenLotto,
+    payer: &Wallet,
+    config: &KeeperConfig,
+    slots: u64,
+) -> Result<bool> {
+    let keeper = payer.pubkey();
+    let lease = accounts::fetch_keeper_lease(rpc_client, &program.keeper_lease_address(&config.manager)).await?;
+    let slot = rpc_client.get_slot().await?;
+    match &lease {
+        Some(lease) if lease.blocks(&keeper, slot) => {
+            eprintln!("Standing by: keeper {} holds the lease until slot {}", lease.keeper, lease.expiry_slot);
+            return Ok(false);
+        }
+        Some(lease) if lease.keeper == keeper && lease.expiry_slot >= slot + slots / 2 => return Ok(true),
+        _ => {}
+    }
+
+    let instruction = program.acquire_keeper_lease(&config.manager, &keeper, &payer.fee_payer().pubkey(), slots);
+    let signature = crate::send_instruction(rpc_client, payer, instruction).await?;
+    match lease {
+        Some(lease) if lease.keeper == keeper => eprintln!("Renewed the lease for {} slots: {}", slots, signature),
+        // A lease the authority allowed keepers on, or revoked, has no holder yet
+        Some(lease) if lease.keeper != Pubkey::default() => {
+            eprintln!("Took over the lease from keeper {}: {}", lease.keeper, signature)
+        }
+        _ => eprintln!("Acquired the lease for {} slots: {}", slots, signature),
+    }
+    Ok(true)
+}
+
+/// Publish the SOL balances of the fee payer and the wager escrow, and report a fee
+/// payer that dropped below `config.low_balance`; a failed lookup leaves the last known
+/// value
+async fn check_balances(
+    rpc_client: &RpcClient,
+    program: &OpenLotto,
+    payer: &Wallet,
+    config: &KeeperConfig,
+    notifier: &Notifier,
+) {
+    let fee_payer = payer.fee_payer().pubkey();
+    let accounts = [("keeper", fee_payer), ("wager_escrow", program.wager_escrow_address())];
+    for (account, address) in accounts {
+        let Ok(lamports) = rpc_client.get_balance(&address).await else { continue };
+        let previous = metrics::balance(&config.manager, account, &address, lamports);
+        // Reported when it crosses the threshold, not on every poll below it
+        let was_low = previous.is_some_and(|previous| previous < config.low_balance);
+        if address == fee_payer && lamports < config.low_balance && !was_low {
+            eprintln!("Fee payer {} is down to {} lamports", address, lamports);
+            notifier.notify(Event::LowBalance {
+                address,
+                lamports,
+                threshold: config.low_balance,
+            });
+        }
+    }
+}
+
+/// Run `f` up to `max_attempts` times with exponential backoff between attempts
+async fn with_retry<T, F, Fut>(what: &str, max_attempts: u32, mut f: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    const BASE_DELAY: Duration = Duration::from_millis(500);
+    const MAX_DELAY: Duration = Duration::from_secs(30);
+
+    let mut attempt = 1;
+    loop {
+        match f().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < max_attempts => {
+                let delay = (BASE_DELAY * 2u32.saturating_pow(attempt - 1)).min(MAX_DELAY);
+                eprintln!(
+                    "{} failed (attempt {}/{}), retrying in {:?}: {:#}",
+                    what, attempt, max_attempts, delay, e
+                );
+                metrics::step_retry(what);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e.context(format!("{} failed after {} attempts", what, attempt))),
+        }
+    }
+}
+
+/// Serialize pubkeys as base58 strings so the state file stays readable
+pub(crate) mod pubkey_string {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use solana_sdk::pubkey::Pubkey;
+    use std::str::FromStr;
+
+    pub fn serialize<S: Serializer>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&pubkey.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Pubkey::from_str(&s).map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,335 @@
+//! Keeping several pot managers from one process
+//!
+//! `keeper --config <FILE>` reads the managers to keep from a TOML file, each named by
+//! its authority and name, with its own cluster profile, priority-fee policy, webhooks
+//! and lease:
+//!
+//! ```toml
+//! [[manager]]
+//! authority = "..."
+//! name = "daily"
+//! cluster = "mainnet"        # profile from the cluster config (default: --cluster)
+//! priority_fee = "auto"      # or micro-lamports per compute unit (default: --priority-fee)
+//! max_priority_fee = 200000
+//! webhooks = ["https://hooks.example.com/daily"]
+//! lease_slots = 1500
+//!
+//! [[manager]]
+//! authority = "..."
+//! name = "hourly"
+//! cluster = "devnet"
+//! rpc_url = "https://my-devnet-rpc.example.com"
+//! priority_fee = 5000
+//! ```
+//!
+//! Every manager gets its own RPC client, state file and webhook outbox and is kept by
+//! its own [`super::run`], all of them concurrently. A keeper that stops (its state file
+//! can't be read, say) is restarted on its own after a pause while the others carry on.
+
+use anyhow::{anyhow, Context, Result};
+use futures_util::future::join_all;
+use open_lotto_client::{OpenLotto, MAX_LEASE_SLOTS};
+use serde::{de::Error as _, Deserialize, Deserializer};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use super::KeeperConfig;
+use crate::cluster::{Cluster, ClusterConfig, Network};
+use crate::notify::Notifier;
+use crate::signer::Wallet;
+use crate::transaction::{self, ComputeBudget, PriorityFee};
+use crate::{errors, expand_tilde};
+
+/// Pause before a stopped keeper is started again
+const RESTART_DELAY: Duration = Duration::from_secs(30);
+
+/// The managers listed in a keeper config file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FleetConfig {
+    #[serde(rename = "manager", default)]
+    managers: Vec<ManagerEntry>,
+}
+
+/// One `[[manager]]` table; everything but `authority` and `name` is optional
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManagerEntry {
+    authority: String,
+    name: String,
+    cluster: Option<Cluster>,
+    rpc_url: Option<String>,
+    #[serde(default, deserialize_with = "priority_fee")]
+    priority_fee: Option<PriorityFee>,
+    max_priority_fee: Option<u64>,
+    compute_unit_limit: Option<u32>,
+    #[serde(default)]
+    webhooks: Vec<String>,
+    lease_slots: Option<u64>,
+    state_file: Option<String>,
+    outbox: Option<String>,
+}
+
+/// `priority_fee` as a number of micro-lamports or a string the way `--priority-fee`
+/// takes it
+fn priority_fee<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<PriorityFee>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Fee {
+        MicroLamports(u64),
+        Text(String),
+    }
+
+    Ok(Some(match Fee::deserialize(deserializer)? {
+        Fee::MicroLamports(fee) => fee.to_string().parse().map_err(D::Error::custom)?,
+        Fee::Text(fee) => fee.parse().map_err(D::Error::custom)?,
+    }))
+}
+
+/// Command line settings every manager shares
+pub struct Shared {
+    pub poll_interval: Duration,
+    pub max_retries: u32,
+    pub reveal_timeout_secs: u64,
+    pub health_threshold: Duration,
+    pub low_balance: u64,
+    /// Compute budget of managers that don't set their own priority-fee policy
+    pub budget: ComputeBudget,
+}
+
+/// Everything one manager's keeper runs with
+pub struct ManagerKeeper {
+    pub name: String,
+    pub network: Network,
+    pub rpc_client: RpcClient,
+    pub program: OpenLotto,
+    pub budget: ComputeBudget,
+    pub config: KeeperConfig,
+}
+
+impl FleetConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read keeper config {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("Failed to parse keeper config {}", path.display()))
+    }
+
+    /// Resolve every manager's cluster, address and settings
+    ///
+    /// Managers without a `cluster` run on `default_network` (`--cluster` and
+    /// `--rpc-url`); `fallback_rpc_url` is the Solana CLI config URL a `custom` profile
+    /// without one falls back to.
+    pub fn resolve(
+        &self,
+        clusters: &ClusterConfig,
+        default_network: &Network,
+        fallback_rpc_url: &str,
+        shared: &Shared,
+    ) -> Result<Vec<ManagerKeeper>> {
+        if self.managers.is_empty() {
+            return Err(anyhow!("The keeper config lists no [[manager]]"));
+        }
+
+        let mut keepers: Vec<ManagerKeeper> = Vec::with_capacity(self.managers.len());
+        for entry in &self.managers {
+            let keeper = entry
+                .resolve(clusters, default_network, fallback_rpc_url, shared)
+                .with_context(|| format!("Invalid [[manager]] '{}'", entry.name))?;
+            if let Some(other) = keepers.iter().find(|other| other.config.manager == keeper.config.manager) {
+                return Err(anyhow!(
+                    "Managers '{}' and '{}' are the same account {}",
+                    other.name,
+                    keeper.name,
+                    keeper.config.manager
+                ));
+            }
+            keepers.push(keeper);
+        }
+        Ok(keepers)
+    }
+}
+
+impl ManagerEntry {
+    fn resolve(
+        &self,
+        clusters: &ClusterConfig,
+        default_network: &Network,
+        fallback_rpc_url: &str,
+        shared: &Shared,
+    ) -> Result<ManagerKeeper> {
+        let authority = Pubkey::from_str(&self.authority).context("Invalid authority public key")?;
+        if self.lease_slots.is_some_and(|slots| slots == 0 || slots > MAX_LEASE_SLOTS) {
+            return Err(anyhow!("lease_slots must be between 1 and {}", MAX_LEASE_SLOTS));
+        }
+
+        let mut network = match self.cluster {
+            Some(cluster) => clusters.resolve(cluster, fallback_rpc_url)?,
+            None => default_network.clone(),
+        };
+        if let Some(rpc_url) = &self.rpc_url {
+            network.rpc_url = rpc_url.clone();
+        }
+
+        let program = OpenLotto::new(network.program_id);
+        let manager = program.pot_manager_address(&authority, &self.name);
+        let budget = ComputeBudget {
+            unit_limit: self.compute_unit_limit.or(shared.budget.unit_limit),
+            priority_fee: self.priority_fee.unwrap_or(shared.budget.priority_fee),
+            max_priority_fee: self.max_priority_fee.or(shared.budget.max_priority_fee),
+        };
+        let state_file = match &self.state_file {
+            Some(path) => PathBuf::from(expand_tilde(path)),
+            None => KeeperConfig::default_state_file(&manager)?,
+        };
+        let outbox = match &self.outbox {
+            Some(path) => PathBuf::from(expand_tilde(path)),
+            None => Notifier::default_path(&manager)?,
+        };
+
+        Ok(ManagerKeeper {
+            name: self.name.clone(),
+            rpc_client: RpcClient::new_with_commitment(network.rpc_url.clone(), CommitmentConfig::confirmed()),
+            network,
+            program,
+            budget,
+            config: KeeperConfig {
+                manager,
+                state_file,
+                poll_interval: shared.poll_interval,
+                max_retries: shared.max_retries,
+                reveal_timeout_secs: shared.reveal_timeout_secs,
+                // Served once for every manager by `run`
+                metrics_bind: None,
+                health_threshold: shared.health_threshold,
+                webhooks: self.webhooks.clone(),
+                outbox,
+                low_balance: shared.low_balance,
+                lease_slots: self.lease_slots,
+            },
+        })
+    }
+}
+
+/// Keep every manager until the process is stopped
+pub async fn run(
+    keepers: &[ManagerKeeper],
+    payer: &Wallet,
+    metrics_bind: Option<&str>,
+    health_threshold: Duration,
+) -> Result<()> {
+    if let Some(bind) = metrics_bind {
+        super::serve_metrics(bind, health_threshold).await?;
+    }
+    for keeper in keepers {
+        eprintln!(
+            "Keeping manager '{}' ({}) on {} via {}, priority fee {:?}",
+            keeper.name, keeper.config.manager, keeper.network.cluster, keeper.network.rpc_url, keeper.budget.priority_fee
+        );
+    }
+
+    join_all(keepers.iter().map(|keeper| supervise(keeper, payer))).await;
+    Ok(())
+}
+
+/// Run one manager's keeper with its own compute budget and programs, restarting it
+/// whenever it stops
+async fn supervise(keeper: &ManagerKeeper, payer: &Wallet) {
+    loop {
+        let run = super::run(&keeper.rpc_client, &keeper.program, payer, &keeper.network, &keeper.config);
+        let run = errors::with_programs(keeper.network.program_id, keeper.network.switchboard_program_id, run);
+        if let Err(e) = transaction::with_budget(keeper.budget, run).await {
+            eprintln!(
+                "Keeper for manager '{}' ({}) stopped, restarting in {:?}: {:#}",
+                keeper.name, keeper.config.manager, RESTART_DELAY, e
+            );
+        }
+        tokio::time::sleep(RESTART_DELAY).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shared() -> Shared {
+        Shared {
+            poll_interval: Duration::from_secs(10),
+            max_retries: 5,
+            reveal_timeout_secs: 60,
+            health_threshold: Duration::from_secs(600),
+            low_balance: 50_000_000,
+            budget: ComputeBudget {
+                unit_limit: Some(200_000),
+                priority_fee: PriorityFee::Fixed(1_000),
+                max_priority_fee: None,
+            },
+        }
+    }
+
+    fn resolve(config: &str) -> Result<Vec<ManagerKeeper>> {
+        let clusters = ClusterConfig::default();
+        let default_network = clusters.resolve(Cluster::Devnet, "http://127.0.0.1:8899")?;
+        let fleet: FleetConfig = toml::from_str(config)?;
+        fleet.resolve(&clusters, &default_network, "http://127.0.0.1:8899", &shared())
+    }
+
+    #[test]
+    fn managers_get_their_own_cluster_and_fee_policy() {
+        let authority = Pubkey::new_unique();
+        let keepers = resolve(&format!(
+            r#"
+            [[manager]]
+            authority = "{authority}"
+            name = "daily"
+            cluster = "mainnet"
+            priority_fee = "auto"
+            max_priority_fee = 200000
+            webhooks = ["https://hooks.example.com/daily"]
+            lease_slots = 1500
+
+            [[manager]]
+            authority = "{authority}"
+            name = "hourly"
+            rpc_url = "https://my-devnet-rpc.example.com"
+            priority_fee = 5000
+            "#
+        ))
+        .unwrap();
+
+        let (daily, hourly) = (&keepers[0], &keepers[1]);
+        assert_eq!(daily.network.cluster, Cluster::Mainnet);
+        assert_eq!(daily.config.manager, daily.program.pot_manager_address(&authority, "daily"));
+        assert_eq!(daily.budget.priority_fee, PriorityFee::Auto);
+        assert_eq!(daily.budget.max_priority_fee, Some(200_000));
+        assert_eq!(daily.budget.unit_limit, Some(200_000));
+        assert_eq!(daily.config.webhooks, ["https://hooks.example.com/daily"]);
+        assert_eq!(daily.config.lease_slots, Some(1500));
+
+        // Without a cluster of its own a manager runs on --cluster, here devnet
+        assert_eq!(hourly.network.cluster, Cluster::Devnet);
+        assert_eq!(hourly.network.rpc_url, "https://my-devnet-rpc.example.com");
+        assert_eq!(hourly.budget.priority_fee, PriorityFee::Fixed(5_000));
+        assert!(hourly.config.webhooks.is_empty());
+        assert_ne!(daily.config.state_file, hourly.config.state_file);
+        assert_ne!(daily.config.outbox, hourly.config.outbox);
+    }
+
+    #[test]
+    fn rejects_conflicting_or_invalid_managers() {
+        let authority = Pubkey::new_unique();
+        let twice = format!(
+            "[[manager]]\nauthority = \"{authority}\"\nname = \"daily\"\n\n[[manager]]\nauthority = \"{authority}\"\nname = \"daily\"\n"
+        );
+        let error = resolve(&twice).err().unwrap();
+        assert!(error.to_string().contains("are the same account"), "{:#}", error);
+
+        let lease = format!("[[manager]]\nauthority = \"{authority}\"\nname = \"daily\"\nlease_slots = 0\n");
+        assert!(resolve(&lease).is_err());
+        let fee = format!("[[manager]]\nauthority = \"{authority}\"\nname = \"daily\"\npriority_fee = \"fast\"\n");
+        assert!(resolve(&fee).is_err());
+        assert!(resolve("").is_err());
+    }
+}
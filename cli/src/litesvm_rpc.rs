@@ -0,0 +1,143 @@
+//! An `RpcClient` backed by LiteSVM, so tests run CLI code against the real program
+//!
+//! Answers the requests sending transactions and reading accounts make; any other
+//! request fails naming the method, so a test that needs one shows which.
+
+use async_trait::async_trait;
+use base64::Engine;
+use litesvm::LiteSVM;
+use serde_json::{json, Value};
+use solana_client::{
+    client_error::Result as ClientResult,
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::RpcClientConfig,
+    rpc_request::{RpcError, RpcRequest, RpcResponseErrorData},
+    rpc_response::RpcSimulateTransactionResult,
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_sdk::{
+    account::Account, clock::Clock, commitment_config::CommitmentConfig, pubkey::Pubkey,
+    signature::Signature, transaction::VersionedTransaction,
+};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+/// `RpcClient` over `svm`, which the test keeps a handle on to set up and inspect accounts
+pub fn rpc_client(svm: Arc<Mutex<LiteSVM>>) -> RpcClient {
+    RpcClient::new_sender(LiteSvmSender { svm }, RpcClientConfig::with_commitment(CommitmentConfig::confirmed()))
+}
+
+struct LiteSvmSender {
+    svm: Arc<Mutex<LiteSVM>>,
+}
+
+#[async_trait]
+impl RpcSender for LiteSvmSender {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        let mut svm = self.svm.lock().unwrap();
+        let slot = svm.get_sysvar::<Clock>().slot;
+        let context = json!({ "slot": slot });
+        Ok(match request {
+            RpcRequest::GetAccountInfo => {
+                let address = pubkey(&params[0]).ok_or_else(|| parse_error(&params))?;
+                json!({ "context": context, "value": svm.get_account(&address).map(ui_account) })
+            }
+            RpcRequest::GetMultipleAccounts => {
+                let accounts = params[0]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|address| Some(svm.get_account(&pubkey(address)?).map(ui_account)))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| parse_error(&params))?;
+                json!({ "context": context, "value": accounts })
+            }
+            RpcRequest::GetLatestBlockhash => json!({
+                "context": context,
+                "value": { "blockhash": svm.latest_blockhash().to_string(), "lastValidBlockHeight": slot + 150 },
+            }),
+            RpcRequest::GetSlot | RpcRequest::GetBlockHeight => json!(slot),
+            RpcRequest::IsBlockhashValid => json!({ "context": context, "value": true }),
+            RpcRequest::SendTransaction => {
+                let encoded = params[0].as_str().unwrap_or_default();
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(encoded)
+                    .map_err(|e| RpcError::ParseError(e.to_string()))?;
+                let transaction: VersionedTransaction =
+                    bincode::deserialize(&bytes).map_err(|e| RpcError::ParseError(e.to_string()))?;
+                let signature = transaction.signatures[0];
+                if let Err(failed) = svm.send_transaction(transaction) {
+                    // Rejected in preflight, as a validator would
+                    return Err(RpcError::RpcResponseError {
+                        code: -32002,
+                        message: format!("Transaction simulation failed: {}", failed.err),
+                        data: RpcResponseErrorData::SendTransactionPreflightFailure(RpcSimulateTransactionResult {
+                            err: Some(failed.err),
+                            logs: Some(failed.meta.logs),
+                            accounts: None,
+                            units_consumed: Some(failed.meta.compute_units_consumed),
+                            loaded_accounts_data_size: None,
+                            return_data: None,
+                            inner_instructions: None,
+                            replacement_blockhash: None,
+                        }),
+                    }
+                    .into());
+                }
+                json!(signature.to_string())
+            }
+            RpcRequest::GetSignatureStatuses => {
+                let statuses: Vec<Value> = params[0]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|signature| {
+                        let signature = Signature::from_str(signature.as_str().unwrap_or_default()).ok()?;
+                        let err = svm.get_transaction(&signature)?.as_ref().err().map(|failed| failed.err.clone());
+                        let status = match &err {
+                            None => json!({ "Ok": null }),
+                            Some(err) => json!({ "Err": err }),
+                        };
+                        Some(json!({
+                            "slot": slot,
+                            "confirmations": null,
+                            "err": err,
+                            "status": status,
+                            "confirmationStatus": "finalized",
+                        }))
+                    })
+                    .map(|status| status.unwrap_or(Value::Null))
+                    .collect();
+                json!({ "context": context, "value": statuses })
+            }
+            request => return Err(RpcError::RpcRequestError(format!("{} is not served by LiteSVM", request)).into()),
+        })
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        "litesvm".to_string()
+    }
+}
+
+fn pubkey(value: &Value) -> Option<Pubkey> {
+    Pubkey::from_str(value.as_str()?).ok()
+}
+
+fn parse_error(params: &Value) -> RpcError {
+    RpcError::ParseError(format!("Expected base58 addresses in {}", params))
+}
+
+fn ui_account(account: Account) -> Value {
+    json!({
+        "lamports": account.lamports,
+        "data": [base64::engine::general_purpose::STANDARD.encode(&account.data), "base64"],
+        "owner": account.owner.to_string(),
+        "executable": account.executable,
+        "rentEpoch": account.rent_epoch,
+        "space": account.data.len(),
+    })
+}
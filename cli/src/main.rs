@@ -1,37 +1,115 @@
 use anyhow::{anyhow, Context, Result};
-use clap::{Parser, Subcommand};
-use solana_cli_config::{Config as SolanaConfig, CONFIG_FILE};
-use solana_client::rpc_client::RpcClient;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use open_lotto_client::{
+    accounts, Discriminator, OpenLotto, Pot, PotManager, PotStatus, Ticket, FEE, MAX_CRANK_BOUNTY, MAX_LEASE_KEEPERS,
+    MAX_LEASE_SLOTS, POT_AMOUNT,
+};
 use solana_sdk::{
     commitment_config::CommitmentConfig,
-    instruction::{AccountMeta, Instruction},
-    message::Message,
+    hash::Hash,
+    instruction::Instruction,
+    program_option::COption,
+    program_pack::Pack,
     pubkey::Pubkey,
-    signature::{read_keypair_file, Keypair, Signer},
-    system_program,
-    sysvar,
-    transaction::Transaction,
+    signature::{Keypair, Signer},
 };
+use spl_associated_token_account::get_associated_token_address;
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use std::path::Path;
 use std::str::FromStr;
 
+use cluster::{Cluster, ClusterConfig, Network};
+use output::{OutputFormat, SettleResult};
+use signer::Wallet;
+use solana_config::SolanaConfig;
+
+mod cluster;
+mod errors;
+mod keeper;
+#[cfg(test)]
+mod litesvm_rpc;
+mod metrics;
+mod notify;
+mod output;
+mod pipeline;
+mod signer;
+mod solana_config;
 mod switchboard;
+mod transaction;
 
-// Open Lotto Program ID
-const OPEN_LOTTO_PID: &str = "FVzki74o5zsTDK1ShhQ6EyR3m2ft7HRgeSkCiEsE8aDf";
+/// enter_ticket instructions packed into one transaction by buy-ticket
+const TICKETS_PER_TRANSACTION: u64 = 5;
 
 /// Open Lotto CLI - Manage lottery and invoke randomness oracle
 #[derive(Parser)]
 #[command(name = "open-lotto")]
 #[command(about = "CLI for Open Lotto lottery program", long_about = None)]
 struct Cli {
-    /// Solana RPC URL (defaults to Solana CLI config)
+    /// Solana RPC URL (overrides the cluster profile)
     #[arg(long, short = 'u')]
     rpc_url: Option<String>,
 
-    /// Path to keypair file (defaults to Solana CLI config)
+    /// Cluster profile selecting RPC, program and Switchboard addresses
+    /// (defaults to `default` in the cluster config, else devnet)
+    #[arg(long, value_enum)]
+    cluster: Option<Cluster>,
+
+    /// Cluster config file (defaults to ~/.config/open-lotto/clusters.toml)
+    #[arg(long)]
+    cluster_config: Option<String>,
+
+    /// Authority signer: keypair file, `usb://ledger`, `prompt://` or `stdin`
+    /// (defaults to the Solana CLI config keypair)
     #[arg(long, short = 'k')]
     keypair: Option<String>,
 
+    /// Signer paying transaction fees, if not the authority (same forms as `--keypair`)
+    #[arg(long, global = true)]
+    fee_payer: Option<String>,
+
+    /// Compute unit limit requested by every transaction (default: runtime default)
+    #[arg(long, global = true, value_parser = transaction::parse_unit_limit)]
+    compute_unit_limit: Option<u32>,
+
+    /// Priority fee for every transaction, in micro-lamports per compute unit, or `auto`
+    /// for the 75th percentile of recent fees paid on the accounts it writes
+    #[arg(long, global = true, default_value = "0")]
+    priority_fee: transaction::PriorityFee,
+
+    /// Cap on the fee chosen by `--priority-fee auto`, in micro-lamports per compute unit
+    #[arg(long, global = true)]
+    max_priority_fee: Option<u64>,
+
+    /// Sign admin transactions with the signers at hand and print them as base64 instead
+    /// of sending (give `--keypair <PUBKEY>` for a signer that signs elsewhere)
+    #[arg(long, global = true, conflicts_with = "dump_transaction")]
+    sign_only: bool,
+
+    /// Print admin transactions unsigned, as base64, instead of sending
+    #[arg(long, global = true)]
+    dump_transaction: bool,
+
+    /// Simulate the transactions of close-pot, force-close, close-escrow or draw and print
+    /// their logs, compute units and balance changes instead of sending
+    #[arg(long, global = true, conflicts_with_all = ["sign_only", "dump_transaction"])]
+    dry_run: bool,
+
+    /// Blockhash for --sign-only/--dump-transaction, so that signers on several machines
+    /// sign the same transaction (defaults to the latest one)
+    #[arg(long, global = true)]
+    blockhash: Option<Hash>,
+
+    /// Durable nonce account to build transactions on instead of a recent blockhash, so
+    /// they stay valid for a long keeper run or an offline signature; its authority must
+    /// be the fee payer
+    #[arg(long, global = true)]
+    nonce: Option<Pubkey>,
+
+    /// Output format for command results (logs always go to stderr)
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -55,6 +133,10 @@ enum Commands {
         /// SPL Token mint address for the lottery
         #[arg(long)]
         token_mint: String,
+
+        /// SOL wager (in lamports) escrowed for the oracle on each draw
+        #[arg(long, default_value = "100")]
+        wager: u64,
     },
 
     /// Create a new randomness account and commit
@@ -67,6 +149,20 @@ enum Commands {
         pot: String,
     },
 
+    /// Finish an interrupted draw from its last completed step
+    Resume {
+        /// Pot account public key
+        #[arg(long)]
+        pot: String,
+    },
+
+    /// Close stale Switchboard randomness accounts and reclaim their rent
+    CloseRandomness {
+        /// Randomness account public key (repeatable)
+        #[arg(long, required = true)]
+        account: Vec<String>,
+    },
+
     /// Reveal randomness and settle lottery
     Settle {
         /// Pot account public key
@@ -74,7 +170,7 @@ enum Commands {
         pot: String,
     },
 
-    /// Full draw and settle in one command (waits for reveal)
+    /// Full draw, reveal and settle in one command
     DrawAndSettle {
         /// Pot account public key
         #[arg(long)]
@@ -109,6 +205,64 @@ enum Commands {
     /// List all program accounts (pots, pot managers, tickets)
     ListAccounts,
 
+    /// Show a pot manager's configuration and its pots
+    ShowManager {
+        /// Pot manager account public key
+        #[arg(long)]
+        manager: String,
+    },
+
+    /// Show a pot's status, participants, prize, winner and randomness account
+    ShowPot {
+        /// Pot account public key
+        #[arg(long)]
+        pot: String,
+    },
+
+    /// List tickets sold in a pot and/or owned by a wallet
+    #[command(group(clap::ArgGroup::new("ticket_filter").required(true).multiple(true).args(["pot", "owner"])))]
+    ListTickets {
+        /// Pot account public key
+        #[arg(long)]
+        pot: Option<String>,
+
+        /// Ticket owner public key
+        #[arg(long)]
+        owner: Option<String>,
+    },
+
+    /// Buy tickets in a pot with the wallet's token account
+    BuyTicket {
+        /// Pot account public key
+        #[arg(long)]
+        pot: String,
+
+        /// Number of tickets to buy
+        #[arg(long, default_value = "1")]
+        count: u64,
+    },
+
+    /// Claim the prize of a settled pot if the wallet holds the winning ticket
+    Claim {
+        /// Pot account public key
+        #[arg(long)]
+        pot: String,
+    },
+
+    /// Push a settled pot's prize to the winner's token account (auto-payout managers)
+    Payout {
+        /// Pot account public key
+        #[arg(long)]
+        pot: String,
+    },
+
+    /// Show the winning ticket of a settled pot
+    Winner {
+        /// Pot account public key
+        #[arg(long)]
+        pot: String,
+    },
+
     /// Force close a program-owned account (for cleaning up legacy accounts)
     ForceClose {
         /// Account public key to close
@@ -118,6 +272,193 @@ enum Commands {
 
     /// Close the escrow token account (self-authority PDA)
     CloseEscrow,
+
+    /// Update the SOL wager escrowed on each draw
+    SetWager {
+        /// Pot manager account public key
+        #[arg(long)]
+        manager: String,
+
+        /// New wager in lamports
+        #[arg(long)]
+        wager: u64,
+    },
+
+    /// Update the token bounty paid to whoever draws or settles a pot
+    SetBounty {
+        /// Pot manager account public key
+        #[arg(long)]
+        manager: String,
+
+        /// New bounty in token base units, paid from the treasury per step
+        #[arg(long)]
+        bounty: u64,
+
+        /// Treasury allowance, in token base units, to delegate to the bounty authority in
+        /// the same transaction; bounties are only paid out of this allowance
+        #[arg(long)]
+        allowance: Option<u64>,
+    },
+
+    /// Switch auto-payout mode, where keepers push settled prizes to winners
+    SetAutoPayout {
+        /// Pot manager account public key
+        #[arg(long)]
+        manager: String,
+
+        /// Whether prizes are pushed to winners instead of waiting for claims
+        #[arg(long, action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+
+    /// Acquire, renew or take over a manager's keeper lease; only the holder may draw
+    AcquireLease {
+        /// Pot manager account public key
+        #[arg(long)]
+        manager: String,
+
+        /// Lease duration in slots (at most about an hour)
+        #[arg(long, default_value = "1500")]
+        slots: u64,
+    },
+
+    /// Replace the keepers allowed to acquire a manager's lease, revoking it from a holder
+    /// that is no longer allowed
+    SetLeaseKeepers {
+        /// Pot manager account public key
+        #[arg(long)]
+        manager: String,
+
+        /// Keeper allowed to acquire the lease besides the authority; repeat for several,
+        /// or leave out to allow none
+        #[arg(long = "keeper")]
+        keepers: Vec<String>,
+    },
+
+    /// Withdraw escrowed oracle wagers (defaults to the manager's full balance)
+    WithdrawWager {
+        /// Pot manager account public key
+        #[arg(long)]
+        manager: String,
+
+        /// Amount in lamports (defaults to everything escrowed by this manager)
+        #[arg(long)]
+        amount: Option<u64>,
+
+        /// Destination wallet, e.g. the oracle operator (defaults to the authority)
+        #[arg(long)]
+        to: Option<String>,
+    },
+
+    /// Run a keeper that draws, settles and rolls every pot of a manager
+    Keeper {
+        /// Pot manager account public key
+        #[arg(long, required_unless_present = "config")]
+        manager: Option<String>,
+
+        /// Keep every manager listed in this TOML file instead of one, each with its own
+        /// cluster, priority fee, webhooks and lease
+        #[arg(long, conflicts_with_all = ["manager", "state_file", "webhook", "outbox", "lease_slots"])]
+        config: Option<String>,
+
+        /// Keeper state file (defaults to ~/.config/open-lotto/keeper-<manager>.json)
+        #[arg(long)]
+        state_file: Option<String>,
+
+        /// Seconds between polls while waiting for a pot to end
+        #[arg(long, default_value = "10")]
+        poll_interval: u64,
+
+        /// Attempts per step before backing off until the next poll
+        #[arg(long, default_value = "5")]
+        max_retries: u32,
+
+        /// Seconds to wait for the oracle to reveal randomness
+        #[arg(long, default_value = "60")]
+        reveal_timeout: u64,
+
+        /// Address to serve Prometheus /metrics and /healthz on, e.g. 0.0.0.0:9100
+        #[arg(long)]
+        metrics_bind: Option<String>,
+
+        /// Seconds a pot may stay ended without settlement before /healthz fails
+        #[arg(long, default_value = "600")]
+        health_threshold: u64,
+
+        /// URL to POST draw, settle, failure and low-balance events to; repeatable
+        #[arg(long)]
+        webhook: Vec<String>,
+
+        /// Webhook outbox file (defaults to ~/.config/open-lotto/outbox-<manager>.json)
+        #[arg(long)]
+        outbox: Option<String>,
+
+        /// Fee payer balance in lamports below which a low-balance event is sent
+        #[arg(long, default_value = "50000000")]
+        low_balance: u64,
+
+        /// Hold the manager's keeper lease for this many slots, renewing it as it runs out;
+        /// a keeper whose lease is held by another stands by and takes over once it lapses
+        #[arg(long)]
+        lease_slots: Option<u64>,
+    },
+
+    /// Broadcast a transaction assembled from --sign-only/--dump-transaction output
+    Submit {
+        /// Base64 transaction; repeat to merge the signatures of partially signed copies
+        #[arg(long, required = true)]
+        transaction: Vec<String>,
+    },
+
+    /// Serve a local oracle gateway that signs reveals with its own key (testing only)
+    MockGateway {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8787")]
+        bind: String,
+
+        /// Hex secp256k1 secret key file, created if missing (a throwaway key if omitted)
+        #[arg(long)]
+        key: Option<String>,
+
+        /// Address of the mock oracle account (a new one if omitted)
+        #[arg(long)]
+        oracle: Option<String>,
+
+        /// Write the matching oracle account for `solana-test-validator --account`
+        #[arg(long)]
+        account_out: Option<String>,
+    },
+}
+
+/// Single-transaction admin commands that can be signed offline, by subcommand name
+const OFFLINE_COMMANDS: &[&str] = &[
+    "close-pot",
+    "force-close",
+    "close-escrow",
+    "set-wager",
+    "set-bounty",
+    "set-auto-payout",
+    "acquire-lease",
+    "set-lease-keepers",
+    "withdraw-wager",
+];
+
+impl Commands {
+    /// Destructive commands whose transactions `--dry-run` can simulate
+    fn supports_dry_run(&self) -> bool {
+        matches!(
+            self,
+            Commands::ClosePot { .. } | Commands::ForceClose { .. } | Commands::CloseEscrow | Commands::Draw { .. }
+        )
+    }
+}
+
+/// What a command does with its transactions instead of sending them
+enum Unsent {
+    /// `--sign-only` or `--dump-transaction`: printed as base64
+    Offline { sign: bool, blockhash: Option<Hash> },
+    /// `--dry-run`: simulated
+    DryRun,
 }
 
 fn expand_tilde(path: &str) -> String {
@@ -129,189 +470,216 @@ fn expand_tilde(path: &str) -> String {
     path.to_string()
 }
 
-fn load_keypair(path: &str) -> Result<Keypair> {
-    let expanded = expand_tilde(path);
-    read_keypair_file(&expanded)
-        .map_err(|e| anyhow!("Failed to read keypair from {}: {}", expanded, e))
-}
-
 #[tokio::main]
 async fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let command_name = matches.subcommand_name().unwrap_or_default();
 
     // Load Solana CLI config for defaults
-    let config_file = CONFIG_FILE.as_ref()
+    let config_file = SolanaConfig::default_path()
         .ok_or_else(|| anyhow!("Unable to get Solana config file path"))?;
-    let solana_config = SolanaConfig::load(config_file)
+    let solana_config = SolanaConfig::load(&config_file)
         .map_err(|e| anyhow!("Failed to load Solana config: {}", e))?;
 
-    let rpc_url = cli.rpc_url.unwrap_or(solana_config.json_rpc_url);
+    let cluster_config = match cli.cluster_config.as_deref() {
+        Some(path) => ClusterConfig::load(Path::new(&expand_tilde(path)))?,
+        None => match ClusterConfig::default_path() {
+            Some(path) => ClusterConfig::load(&path)?,
+            None => ClusterConfig::default(),
+        },
+    };
+    let cluster = cli.cluster
+        .or(cluster_config.default_cluster())
+        .unwrap_or(Cluster::Devnet);
+    let mut network = cluster_config.resolve(cluster, &solana_config.json_rpc_url)?;
+    if let Some(rpc_url) = cli.rpc_url {
+        network.rpc_url = rpc_url;
+    }
     let keypair_path = cli.keypair.unwrap_or(solana_config.keypair_path);
 
-    println!("Using RPC: {}", rpc_url);
+    // The mock gateway only needs the network's Switchboard addresses, not a wallet
+    if let Commands::MockGateway { bind, key, oracle, account_out } = cli.command {
+        return mock_gateway(&network, &bind, key, oracle, account_out).await;
+    }
+
+    eprintln!("Using cluster: {}", network.cluster);
+    eprintln!("Using RPC: {}", network.rpc_url);
 
     let rpc_client = RpcClient::new_with_commitment(
-        rpc_url.clone(),
+        network.rpc_url.clone(),
         CommitmentConfig::confirmed(),
     );
 
-    let payer = load_keypair(&keypair_path)?;
-    println!("Using wallet: {}", payer.pubkey());
+    let offline = cli.sign_only || cli.dump_transaction;
+    if offline && !OFFLINE_COMMANDS.contains(&command_name) {
+        return Err(anyhow!(
+            "--sign-only and --dump-transaction only apply to {}",
+            OFFLINE_COMMANDS.join(", ")
+        ));
+    }
+    if !offline && cli.blockhash.is_some() {
+        return Err(anyhow!("--blockhash only applies with --sign-only or --dump-transaction"));
+    }
+    if cli.dry_run && !cli.command.supports_dry_run() {
+        return Err(anyhow!("--dry-run only applies to close-pot, force-close, close-escrow and draw"));
+    }
+    let unsent = if offline {
+        Some(Unsent::Offline { sign: cli.sign_only, blockhash: cli.blockhash })
+    } else {
+        cli.dry_run.then_some(Unsent::DryRun)
+    };
+
+    // Offline signers and dry runs never sign, so a bare pubkey will do
+    let payer = Wallet::resolve(&keypair_path, cli.fee_payer.as_deref(), unsent.is_some())?;
+    eprintln!("Using wallet: {}", payer.pubkey());
+    if payer.fee_payer().pubkey() != payer.pubkey() {
+        eprintln!("Fee payer: {}", payer.fee_payer().pubkey());
+    }
+
+    let program = OpenLotto::new(network.program_id);
+
+    errors::configure(network.program_id, network.switchboard_program_id);
+    transaction::configure(transaction::ComputeBudget {
+        unit_limit: cli.compute_unit_limit,
+        priority_fee: cli.priority_fee,
+        max_priority_fee: cli.max_priority_fee,
+    });
+    if let Some(nonce) = cli.nonce {
+        transaction::use_nonce(nonce);
+    }
+
+    let output = cli.output;
 
     match cli.command {
-        Commands::Init { name, duration, end_in, token_mint } => {
-            let now = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)?
-                .as_secs();
-            let end_ts = now + end_in;
+        Commands::Init { name, duration, end_in, token_mint, wager } => {
+            let end_ts = unix_now()? + end_in;
 
             let token_mint_pubkey = Pubkey::from_str(&token_mint)
                 .context("Invalid token mint public key")?;
 
             let result = init_pot_manager(
                 &rpc_client,
+                &program,
                 &payer,
                 &name,
                 end_ts,
                 duration,
                 &token_mint_pubkey,
-            )?;
-
-            println!("\n✓ Pot Manager initialized!");
-            println!("Manager: {}", result.pot_manager);
-            println!("Token Mint: {}", token_mint);
-            println!("Treasury Token Account: {}", result.treasury_token_account);
-            println!("Escrow Token Account: {}", result.escrow_token_account);
-            println!("First Pot: {}", result.first_pot);
-            println!("Next Pot: {}", result.next_pot);
-            println!("\nFirst pot ends at: {} (in {} seconds)", end_ts, end_in);
-            println!("\nTo draw the lottery, run:");
-            println!("  open-lotto draw --pot {}", result.first_pot);
+                wager,
+            ).await?;
+            output.print(&result)?;
         }
 
         Commands::CreateRandomness => {
             let randomness_keypair = Keypair::new();
-            println!("Creating new randomness account: {}", randomness_keypair.pubkey());
+            eprintln!("Creating new randomness account: {}", randomness_keypair.pubkey());
 
             let result = switchboard::create_and_commit_randomness(
                 &rpc_client,
                 &payer,
                 &randomness_keypair,
-                &rpc_url,
+                &network,
             ).await?;
-
-            println!("\n✓ Randomness account created and committed!");
-            println!("Randomness account: {}", result.randomness_account);
-            println!("Commit slot: {}", result.commit_slot);
-            println!("\nNext step: Wait a few slots, then reveal and settle");
+            output.print(&result)?;
         }
 
         Commands::Draw { pot } => {
             let pot_pubkey = Pubkey::from_str(&pot)
                 .context("Invalid pot public key")?;
 
-            let randomness_keypair = Keypair::new();
-            println!("Creating randomness account: {}", randomness_keypair.pubkey());
-
-            // 1. Create and commit randomness
-            let commit_result = switchboard::create_and_commit_randomness(
-                &rpc_client,
-                &payer,
-                &randomness_keypair,
-                &rpc_url,
-            ).await?;
-
-            println!("Randomness committed at slot: {}", commit_result.commit_slot);
-
-            // 2. Call draw_lottery on our program
-            let draw_result = call_draw_lottery(
-                &rpc_client,
-                &payer,
-                &pot_pubkey,
-                &randomness_keypair.pubkey(),
-            )?;
+            if unsent.is_some() {
+                let simulated = pipeline::dry_run(&rpc_client, &program, &payer, &pot_pubkey, &network).await?;
+                output.print(&simulated)?;
+                return Ok(());
+            }
 
-            println!("\n✓ Draw completed!");
-            println!("Randomness account: {}", randomness_keypair.pubkey());
-            println!("Transaction: {}", draw_result);
-            println!("\nNext step: Wait for randomness reveal (~5-10 seconds), then run settle");
+            let result = pipeline::draw(&rpc_client, &program, &payer, &pot_pubkey, &network).await?;
+            output.print(&result)?;
         }
 
-        Commands::Settle { pot } => {
+        Commands::Resume { pot } => {
             let pot_pubkey = Pubkey::from_str(&pot)
                 .context("Invalid pot public key")?;
 
-            // Read pot account to get randomness account
-            let pot_data = rpc_client.get_account_data(&pot_pubkey)?;
-            let randomness_account = read_pot_randomness_account(&pot_data)?;
+            let result = pipeline::resume(&rpc_client, &program, &payer, &pot_pubkey, &network).await?;
+            output.print(&result)?;
+        }
 
-            println!("Using randomness account from pot: {}", randomness_account);
+        Commands::CloseRandomness { account } => {
+            let records = pipeline::DrawRecord::load_all()?;
+            let mut closed = Vec::new();
+
+            for account in account {
+                let randomness = Pubkey::from_str(&account)
+                    .context("Invalid randomness account public key")?;
+
+                // Settling needs the randomness account, so never close one an open pot is waiting on
+                let pending = program
+                    .fetch_all::<Pot>(&rpc_client, vec![accounts::pots_by_randomness_filter(&randomness)]).await?
+                    .into_iter()
+                    .find(|(_, pot)| !pot.settled);
+                if let Some((pot_address, _)) = pending {
+                    return Err(anyhow!(
+                        "Randomness account {} is still needed to settle pot {}",
+                        randomness,
+                        pot_address
+                    ));
+                }
 
-            // Wait for reveal if needed
-            println!("Waiting for randomness to be revealed...");
-            switchboard::wait_for_reveal(&rpc_client, &randomness_account, 30).await?;
+                let signature = switchboard::close_randomness(&rpc_client, &payer, &randomness, &network).await?;
+                eprintln!("Closed randomness account {}: {}", randomness, signature);
 
-            // Call settle_lottery
-            let settle_result = call_settle_lottery(
-                &rpc_client,
-                &payer,
-                &pot_pubkey,
-                &randomness_account,
-            )?;
+                // An abandoned draw for this keypair can no longer be resumed
+                for record in &records {
+                    if record.randomness_keypair()?.pubkey() == randomness {
+                        record.remove()?;
+                    }
+                }
 
-            println!("\n✓ Settle completed! Winner has been determined.");
-            println!("Transaction: {}", settle_result);
+                closed.push(output::CloseResult {
+                    account: randomness,
+                    rent_recipient: payer.pubkey(),
+                    signature,
+                });
+            }
+            output.print(&output::CloseRandomnessResult { closed })?;
         }
 
-        Commands::DrawAndSettle { pot } => {
+        Commands::Settle { pot } => {
             let pot_pubkey = Pubkey::from_str(&pot)
                 .context("Invalid pot public key")?;
 
-            let randomness_keypair = Keypair::new();
-            println!("Creating randomness account: {}", randomness_keypair.pubkey());
-
-            // 1. Create and commit randomness
-            let commit_result = switchboard::create_and_commit_randomness(
-                &rpc_client,
-                &payer,
-                &randomness_keypair,
-                &rpc_url,
-            ).await?;
-
-            println!("Randomness committed at slot: {}", commit_result.commit_slot);
-
-            // 2. Call draw_lottery
-            let draw_result = call_draw_lottery(
-                &rpc_client,
-                &payer,
-                &pot_pubkey,
-                &randomness_keypair.pubkey(),
-            )?;
-            println!("Draw transaction: {}", draw_result);
+            // Read pot account to get randomness account
+            let randomness_account = accounts::fetch_pot(&rpc_client, &pot_pubkey).await?.randomness_account;
+            eprintln!("Using randomness account from pot: {}", randomness_account);
 
-            // 3. Wait for reveal
-            println!("\nWaiting for randomness to be revealed...");
-            switchboard::wait_for_reveal(&rpc_client, &randomness_keypair.pubkey(), 30).await?;
+            let result = settle(&rpc_client, &program, &payer, &pot_pubkey, &randomness_account, &network).await?;
+            output.print(&result)?;
+        }
 
-            // 4. Settle
-            let settle_result = call_settle_lottery(
-                &rpc_client,
-                &payer,
-                &pot_pubkey,
-                &randomness_keypair.pubkey(),
-            )?;
+        Commands::DrawAndSettle { pot } => {
+            let pot_pubkey = Pubkey::from_str(&pot)
+                .context("Invalid pot public key")?;
 
-            println!("\n✓ Draw and settle completed! Winner has been determined.");
-            println!("Settle transaction: {}", settle_result);
+            let draw = pipeline::draw(&rpc_client, &program, &payer, &pot_pubkey, &network).await?;
+            let settle = settle(&rpc_client, &program, &payer, &pot_pubkey, &draw.randomness_account, &network).await?;
+            output.print(&output::DrawAndSettleResult { draw, settle })?;
         }
 
         Commands::CheckRandomness { account } => {
             let randomness_pubkey = Pubkey::from_str(&account)
                 .context("Invalid randomness account public key")?;
 
-            let status = switchboard::check_randomness_status(&rpc_client, &randomness_pubkey)?;
-            println!("Randomness account: {}", randomness_pubkey);
-            println!("Status: {}", status);
+            let status = switchboard::check_randomness_status(
+                &rpc_client,
+                &randomness_pubkey,
+                &network.switchboard_program_id,
+            ).await?;
+            output.print(&output::RandomnessStatus {
+                randomness_account: randomness_pubkey,
+                status,
+            })?;
         }
 
         Commands::Reveal { randomness, oracle } => {
@@ -323,463 +691,1000 @@ async fn main() -> Result<()> {
                 Pubkey::from_str(&oracle_str).context("Invalid oracle public key")?
             } else {
                 // Read oracle from randomness account
-                let randomness_data = rpc_client.get_account_data(&randomness_pubkey)?;
+                let randomness_data = rpc_client.get_account_data(&randomness_pubkey).await?;
                 read_oracle_from_randomness(&randomness_data)?
             };
 
-            let queue = switchboard::get_sb_queue(rpc_url.contains("devnet"))?;
+            let queue = network.switchboard_queue;
 
-            println!("Revealing randomness...");
-            println!("  Randomness: {}", randomness_pubkey);
-            println!("  Oracle: {}", oracle_pubkey);
-            println!("  Queue: {}", queue);
+            eprintln!("Revealing randomness...");
+            eprintln!("  Randomness: {}", randomness_pubkey);
+            eprintln!("  Oracle: {}", oracle_pubkey);
+            eprintln!("  Queue: {}", queue);
 
             let signature = switchboard::reveal_randomness(
                 &rpc_client,
                 &payer,
                 &randomness_pubkey,
                 &oracle_pubkey,
-                &queue,
-                &rpc_url,
+                &network,
             ).await?;
 
-            println!("\n✓ Randomness revealed!");
-            println!("Transaction: {}", signature);
+            output.print(&output::RevealResult {
+                randomness_account: randomness_pubkey,
+                oracle: oracle_pubkey,
+                queue,
+                signature,
+            })?;
         }
 
         Commands::ClosePot { pot } => {
             let pot_pubkey = Pubkey::from_str(&pot)
                 .context("Invalid pot public key")?;
 
-            let signature = call_close_pot(&rpc_client, &payer, &pot_pubkey)?;
-            println!("\n✓ Pot account closed!");
-            println!("Transaction: {}", signature);
-            println!("Rent recovered to: {}", payer.pubkey());
+            let instruction = program.close_pot(&pot_pubkey, &payer.pubkey());
+            let Some(signature) = send_admin_instruction(&rpc_client, &payer, instruction, unsent.as_ref(), output).await? else {
+                return Ok(());
+            };
+            output.print(&output::CloseResult {
+                account: pot_pubkey,
+                rent_recipient: payer.pubkey(),
+                signature,
+            })?;
         }
 
         Commands::ListAccounts => {
-            let program_id = Pubkey::from_str(OPEN_LOTTO_PID)?;
+            let program_id = program.program_id;
 
-            println!("Fetching all program accounts...\n");
+            eprintln!("Fetching all program accounts...");
 
-            let accounts = rpc_client.get_program_accounts(&program_id)?;
+            let accounts = rpc_client.get_program_accounts(&program_id).await?;
 
-            if accounts.is_empty() {
-                println!("No accounts found for program {}", program_id);
-            } else {
-                // Categorize accounts by discriminator
-                let mut pots = Vec::new();
-                let mut pot_managers = Vec::new();
-                let mut tickets = Vec::new();
-                let mut unknown = Vec::new();
-
-                for (pubkey, account) in &accounts {
-                    if account.data.len() >= 8 {
-                        let disc = &account.data[0..8];
-                        match disc {
-                            // Pot discriminator
-                            [238, 118, 60, 175, 178, 191, 59, 58] => pots.push((pubkey, account)),
-                            // PotManager discriminator
-                            [184, 109, 148, 80, 4, 87, 136, 85] => pot_managers.push((pubkey, account)),
-                            // Ticket discriminator
-                            [41, 228, 24, 165, 78, 90, 235, 200] => tickets.push((pubkey, account)),
-                            _ => unknown.push((pubkey, account)),
-                        }
+            // Categorize accounts by discriminator
+            let mut listed = output::ProgramAccounts {
+                program_id,
+                pot_managers: Vec::new(),
+                pots: Vec::new(),
+                tickets: Vec::new(),
+                unknown: Vec::new(),
+                total_accounts: accounts.len(),
+                total_lamports: accounts.iter().map(|(_, a)| a.lamports).sum(),
+            };
+
+            for (pubkey, account) in &accounts {
+                if account.data.starts_with(Pot::DISCRIMINATOR) {
+                    let pot = accounts::decode::<Pot>(pubkey, &account.data).ok();
+                    listed.pots.push(output::ListedPot {
+                        address: *pubkey,
+                        total_participants: pot.as_ref().map(|pot| pot.total_participants),
+                        end_timestamp: pot.as_ref().map(|pot| pot.end_timestamp),
+                        lamports: account.lamports,
+                    });
+                } else if account.data.starts_with(PotManager::DISCRIMINATOR) {
+                    listed.pot_managers.push(output::ListedPotManager {
+                        address: *pubkey,
+                        name: accounts::decode::<PotManager>(pubkey, &account.data)
+                            .map(|manager| manager.name)
+                            .ok(),
+                        lamports: account.lamports,
+                    });
+                } else {
+                    let entry = output::ListedAccount {
+                        address: *pubkey,
+                        lamports: account.lamports,
+                        data_len: account.data.len(),
+                    };
+                    if account.data.starts_with(Ticket::DISCRIMINATOR) {
+                        listed.tickets.push(entry);
                     } else {
-                        unknown.push((pubkey, account));
+                        listed.unknown.push(entry);
                     }
                 }
+            }
 
-                println!("=== Pot Managers ({}) ===", pot_managers.len());
-                for (pubkey, account) in &pot_managers {
-                    let lamports = account.lamports;
-                    let name = parse_pot_manager_name(&account.data).unwrap_or_else(|_| "unknown".to_string());
-                    println!("  {} (name: {}, {} lamports)", pubkey, name, lamports);
-                }
+            output.print(&listed)?;
+        }
 
-                println!("\n=== Pots ({}) ===", pots.len());
-                for (pubkey, account) in &pots {
-                    let lamports = account.lamports;
-                    let (participants, end_ts) = parse_pot_info(&account.data).unwrap_or((0, 0));
-                    println!("  {} (participants: {}, end_ts: {}, {} lamports)", pubkey, participants, end_ts, lamports);
-                }
+        Commands::ShowManager { manager } => {
+            let manager_pubkey = Pubkey::from_str(&manager)
+                .context("Invalid pot manager public key")?;
+
+            let pot_manager = accounts::fetch_pot_manager(&rpc_client, &manager_pubkey).await?;
+            let now = unix_now()?;
+            let mut pots: Vec<output::PotSummary> = program
+                .fetch_all::<Pot>(&rpc_client, vec![accounts::pots_by_manager_filter(&manager_pubkey)]).await?
+                .into_iter()
+                .map(|(address, pot)| output::PotSummary {
+                    address,
+                    status: PotStatus::of(&pot, now),
+                    end_timestamp: pot.end_timestamp,
+                    total_participants: pot.total_participants,
+                })
+                .collect();
+            pots.sort_by_key(|pot| pot.end_timestamp);
+
+            let lease = accounts::fetch_keeper_lease(&rpc_client, &program.keeper_lease_address(&manager_pubkey)).await?;
+            let keeper_lease = lease
+                .as_ref()
+                .filter(|lease| lease.keeper != Pubkey::default())
+                .map(|lease| output::LeaseInfo { keeper: lease.keeper, expiry_slot: lease.expiry_slot });
+            let lease_keepers = lease.map(|lease| lease.keepers).unwrap_or_default();
+
+            let (current_end, next_end) = pot_manager.timestamps;
+            output.print(&output::ManagerInfo {
+                address: manager_pubkey,
+                name: pot_manager.name,
+                authority: pot_manager.authority,
+                token_mint: pot_manager.token_mint,
+                treasury: pot_manager.treasury,
+                current_pot: program.pot_address(&manager_pubkey, current_end),
+                next_pot: program.pot_address(&manager_pubkey, next_end),
+                current_pot_end_timestamp: current_end,
+                next_pot_end_timestamp: next_end,
+                wager: pot_manager.wager,
+                wager_balance: pot_manager.wager_balance,
+                crank_bounty: pot_manager.crank_bounty,
+                auto_payout: pot_manager.auto_payout,
+                last_random_number: pot_manager.last_random_number,
+                keeper_lease,
+                lease_keepers,
+                pots,
+            })?;
+        }
 
-                println!("\n=== Tickets ({}) ===", tickets.len());
-                for (pubkey, account) in &tickets {
-                    let lamports = account.lamports;
-                    println!("  {} ({} lamports)", pubkey, lamports);
-                }
+        Commands::ShowPot { pot } => {
+            let pot_pubkey = Pubkey::from_str(&pot)
+                .context("Invalid pot public key")?;
 
-                if !unknown.is_empty() {
-                    println!("\n=== Unknown ({}) ===", unknown.len());
-                    for (pubkey, account) in &unknown {
-                        println!("  {} ({} lamports, {} bytes)", pubkey, account.lamports, account.data.len());
-                    }
-                }
+            let pot = accounts::fetch_pot(&rpc_client, &pot_pubkey).await?;
+            let winner = if pot.settled {
+                Some(fetch_winner(&rpc_client, &program, &pot_pubkey, &pot).await)
+            } else {
+                None
+            };
 
-                println!("\n=== Summary ===");
-                let total_lamports: u64 = accounts.iter().map(|(_, a)| a.lamports).sum();
-                println!("Total accounts: {}", accounts.len());
-                println!("Total lamports: {} ({:.4} SOL)", total_lamports, total_lamports as f64 / 1_000_000_000.0);
-            }
+            output.print(&output::PotInfo {
+                address: pot_pubkey,
+                pot_manager: pot.pot_manager,
+                status: PotStatus::of(&pot, unix_now()?),
+                start_timestamp: pot.start_timestamp,
+                end_timestamp: pot.end_timestamp,
+                total_participants: pot.total_participants,
+                prize: pot.prize_amount(),
+                bounties_paid: pot.bounties_paid,
+                prize_paid: pot.prize_paid,
+                wager: pot.wager,
+                randomness_account: Some(pot.randomness_account)
+                    .filter(|randomness| *randomness != Pubkey::default()),
+                winner,
+            })?;
         }
 
-        Commands::ForceClose { account } => {
-            let account_pubkey = Pubkey::from_str(&account)
-                .context("Invalid account public key")?;
+        Commands::ListTickets { pot, owner } => {
+            let owner_pubkey = owner
+                .map(|owner| Pubkey::from_str(&owner).context("Invalid owner public key"))
+                .transpose()?;
+
+            let tickets: Vec<output::ListedTicket> = match pot {
+                Some(pot) => {
+                    let pot_pubkey = Pubkey::from_str(&pot)
+                        .context("Invalid pot public key")?;
+                    let pot = accounts::fetch_pot(&rpc_client, &pot_pubkey).await?;
+                    program
+                        .fetch_pot_tickets(&rpc_client, &pot_pubkey, &pot).await?
+                        .into_iter()
+                        .filter(|(_, ticket)| owner_pubkey.is_none_or(|owner| ticket.participant == owner))
+                        .map(|(address, ticket)| output::ListedTicket {
+                            address,
+                            pot: Some(pot_pubkey),
+                            index: ticket.index,
+                            participant: ticket.participant,
+                        })
+                        .collect()
+                }
+                None => {
+                    // The arg group guarantees an owner when there is no pot
+                    let owner = owner_pubkey.ok_or_else(|| anyhow!("Pass --pot and/or --owner"))?;
+                    program
+                        .fetch_all::<Ticket>(&rpc_client, vec![accounts::tickets_by_owner_filter(&owner)]).await?
+                        .into_iter()
+                        .map(|(address, ticket)| output::ListedTicket {
+                            address,
+                            pot: None,
+                            index: ticket.index,
+                            participant: ticket.participant,
+                        })
+                        .collect()
+                }
+            };
 
-            let signature = call_force_close_account(&rpc_client, &payer, &account_pubkey)?;
-            println!("\n✓ Account force closed!");
-            println!("Transaction: {}", signature);
-            println!("Rent recovered to: {}", payer.pubkey());
+            output.print(&output::TicketList { tickets })?;
         }
 
-        Commands::CloseEscrow => {
-            let signature = call_close_escrow(&rpc_client, &payer)?;
-            println!("\n✓ Escrow token account closed!");
-            println!("Transaction: {}", signature);
-            println!("Rent recovered to: {}", payer.pubkey());
-        }
-    }
+        Commands::BuyTicket { pot, count } => {
+            let pot_pubkey = Pubkey::from_str(&pot)
+                .context("Invalid pot public key")?;
+            if count == 0 {
+                return Err(anyhow!("--count must be at least 1"));
+            }
 
-    Ok(())
-}
+            let pot = accounts::fetch_pot(&rpc_client, &pot_pubkey).await?;
+            let token_mint = accounts::fetch_pot_manager(&rpc_client, &pot.pot_manager).await?.token_mint;
+            let (token_account, create_token_account) =
+                user_token_account(&rpc_client, &payer, &token_mint).await?;
 
-/// Read the oracle field from a randomness account's data
-fn read_oracle_from_randomness(data: &[u8]) -> Result<Pubkey> {
-    // RandomnessAccountData layout:
-    // - discriminator: 8 bytes
-    // - authority: 32 bytes
-    // - queue: 32 bytes
-    // - seed_slothash: 32 bytes
-    // - seed_slot: 8 bytes
-    // - oracle: 32 bytes (offset: 8 + 32 + 32 + 32 + 8 = 112)
-    const ORACLE_OFFSET: usize = 112;
-
-    if data.len() < ORACLE_OFFSET + 32 {
-        return Err(anyhow!("Randomness account data too short"));
-    }
+            let cost = count
+                .checked_mul(POT_AMOUNT + FEE)
+                .ok_or_else(|| anyhow!("--count {} is too large: the tickets' cost overflows u64", count))?;
+            let balance = if create_token_account.is_some() {
+                0
+            } else {
+                rpc_client
+                    .get_token_account_balance(&token_account).await?
+                    .amount
+                    .parse::<u64>()?
+            };
+            if balance < cost {
+                return Err(anyhow!(
+                    "Token account {} holds {} but {} ticket(s) cost {} (token base units)",
+                    token_account,
+                    balance,
+                    count,
+                    cost
+                ));
+            }
 
-    let pubkey_bytes: [u8; 32] = data[ORACLE_OFFSET..ORACLE_OFFSET + 32].try_into()?;
-    Ok(Pubkey::from(pubkey_bytes))
-}
+            // Ticket PDAs are indexed by total_participants, so refetch the pot before
+            // every batch in case other buyers got in between
+            let mut create_token_account = create_token_account;
+            let mut tickets = Vec::new();
+            let mut signatures = Vec::new();
+            while (tickets.len() as u64) < count {
+                let first_index = accounts::fetch_pot(&rpc_client, &pot_pubkey).await?.total_participants;
+                let batch = (count - tickets.len() as u64).min(TICKETS_PER_TRANSACTION);
+
+                let mut instructions: Vec<Instruction> = create_token_account.take().into_iter().collect();
+                for index in first_index..first_index + batch {
+                    instructions.push(program.enter_ticket(
+                        &payer.pubkey(),
+                        &pot_pubkey,
+                        index,
+                        &token_account,
+                        &token_mint,
+                    ));
+                    tickets.push(output::ListedTicket {
+                        address: program.ticket_address(&pot_pubkey, index),
+                        pot: Some(pot_pubkey),
+                        index,
+                        participant: payer.pubkey(),
+                    });
+                }
 
-/// Read the randomness_account field from a Pot account's data
-fn read_pot_randomness_account(data: &[u8]) -> Result<Pubkey> {
-    // Pot layout (new):
-    // - discriminator: 8 bytes
-    // - pot_manager: 32 bytes
-    // - total_participants: 8 bytes
-    // - start_ts: 8 bytes
-    // - end_ts: 8 bytes
-    // - winning_slot: 8 bytes
-    // - randomness_account: 32 bytes
-    const RANDOMNESS_OFFSET: usize = 8 + 32 + 8 + 8 + 8 + 8;
-    if data.len() < RANDOMNESS_OFFSET + 32 {
-        return Err(anyhow!("Pot account data too short"));
-    }
-    let pubkey_bytes: [u8; 32] = data[RANDOMNESS_OFFSET..RANDOMNESS_OFFSET + 32].try_into()?;
-    Ok(Pubkey::from(pubkey_bytes))
-}
+                let signature = send_instructions(&rpc_client, &payer, &instructions).await?;
+                eprintln!("Bought {} ticket(s): {}", batch, signature);
+                signatures.push(signature);
+            }
 
-/// Call the draw_lottery instruction on the Open Lotto program
-fn call_draw_lottery(
-    rpc_client: &RpcClient,
-    payer: &Keypair,
-    pot: &Pubkey,
-    randomness_account: &Pubkey,
-) -> Result<String> {
-    let program_id = Pubkey::from_str(OPEN_LOTTO_PID)?;
+            output.print(&output::BuyTicketResult {
+                pot: pot_pubkey,
+                token_account,
+                cost,
+                tickets,
+                signatures,
+            })?;
+        }
 
-    // Wager Escrow PDA (for oracle SOL wager)
-    let (wager_escrow, _bump) = Pubkey::find_program_address(
-        &[b"wagerEscrow"],
-        &program_id,
-    );
+        Commands::Claim { pot } => {
+            let pot_pubkey = Pubkey::from_str(&pot)
+                .context("Invalid pot public key")?;
 
-    // Build instruction data: discriminator + randomness_account pubkey
-    // draw_lottery discriminator: sha256("global:draw_lottery")[..8]
-    let discriminator = get_anchor_discriminator("draw_lottery");
-    let mut data = discriminator.to_vec();
-    data.extend_from_slice(&randomness_account.to_bytes());
-
-    let accounts = vec![
-        AccountMeta::new(*pot, false),
-        AccountMeta::new(payer.pubkey(), true),
-        AccountMeta::new_readonly(*randomness_account, false),
-        AccountMeta::new(wager_escrow, false),
-        AccountMeta::new_readonly(system_program::id(), false),
-    ];
+            let pot = accounts::fetch_pot(&rpc_client, &pot_pubkey).await?;
+            if !pot.settled {
+                return Err(anyhow!(
+                    "Pot {} has not been settled yet (status: {})",
+                    pot_pubkey,
+                    PotStatus::of(&pot, unix_now()?)
+                ));
+            }
 
-    let instruction = Instruction::new_with_bytes(program_id, &data, accounts);
+            if pot.prize_paid {
+                return Err(anyhow!("The prize of pot {} has already been paid", pot_pubkey));
+            }
 
-    let recent_blockhash = rpc_client.get_latest_blockhash()?;
-    let message = Message::new(&[instruction], Some(&payer.pubkey()));
-    let transaction = Transaction::new(&[payer], message, recent_blockhash);
+            let winner = fetch_winner(&rpc_client, &program, &pot_pubkey, &pot).await;
+            match winner.participant {
+                Some(participant) if participant == payer.pubkey() => {}
+                Some(participant) => {
+                    return Err(anyhow!(
+                        "Wallet {} does not hold the winning ticket #{} (owner: {})",
+                        payer.pubkey(),
+                        winner.index,
+                        participant
+                    ));
+                }
+                None => {
+                    return Err(anyhow!("Winning ticket {} has been closed", winner.ticket));
+                }
+            }
 
-    let signature = rpc_client.send_and_confirm_transaction(&transaction)?;
-    Ok(signature.to_string())
-}
+            let token_mint = accounts::fetch_pot_manager(&rpc_client, &pot.pot_manager).await?.token_mint;
+            let (token_account, create_token_account) =
+                user_token_account(&rpc_client, &payer, &token_mint).await?;
 
-/// Call the settle_lottery instruction on the Open Lotto program
-fn call_settle_lottery(
-    rpc_client: &RpcClient,
-    payer: &Keypair,
-    pot: &Pubkey,
-    randomness_account: &Pubkey,
-) -> Result<String> {
-    let program_id = Pubkey::from_str(OPEN_LOTTO_PID)?;
+            let mut instructions: Vec<Instruction> = create_token_account.into_iter().collect();
+            instructions.push(program.claim_prize(
+                &winner.ticket,
+                &payer.pubkey(),
+                &pot_pubkey,
+                &token_account,
+                &token_mint,
+            ));
+            let signature = send_instructions(&rpc_client, &payer, &instructions).await?;
+
+            output.print(&output::ClaimResult {
+                pot: pot_pubkey,
+                ticket: winner.ticket,
+                index: winner.index,
+                prize: pot.prize_amount(),
+                token_account,
+                signature,
+            })?;
+        }
 
-    // Build instruction data
-    let discriminator = get_anchor_discriminator("settle_lottery");
-    let data = discriminator.to_vec();
+        Commands::Payout { pot } => {
+            let pot_pubkey = Pubkey::from_str(&pot)
+                .context("Invalid pot public key")?;
 
-    // SettleLottery accounts: pot, randomness_account_data, user (signer)
-    let accounts = vec![
-        AccountMeta::new(*pot, false),
-        AccountMeta::new_readonly(*randomness_account, false),
-        AccountMeta::new_readonly(payer.pubkey(), true),
-    ];
+            let pot = accounts::fetch_pot(&rpc_client, &pot_pubkey).await?;
+            if !pot.settled {
+                return Err(anyhow!(
+                    "Pot {} has not been settled yet (status: {})",
+                    pot_pubkey,
+                    PotStatus::of(&pot, unix_now()?)
+                ));
+            }
+            if pot.prize_paid {
+                return Err(anyhow!("The prize of pot {} has already been paid", pot_pubkey));
+            }
+            if pot.payout_reserve == 0 {
+                return Err(anyhow!(
+                    "Pot {} was drawn before auto-payout was switched on; its winner claims the prize with `claim`",
+                    pot_pubkey
+                ));
+            }
 
-    let instruction = Instruction::new_with_bytes(program_id, &data, accounts);
+            let (signature, winner, token_account) =
+                call_payout_winner(&rpc_client, &program, &payer, &pot_pubkey, &pot).await?;
+            output.print(&output::PayoutResult {
+                pot: pot_pubkey,
+                winner,
+                prize: pot.prize_amount(),
+                token_account,
+                signature,
+            })?;
+        }
 
-    let recent_blockhash = rpc_client.get_latest_blockhash()?;
-    let message = Message::new(&[instruction], Some(&payer.pubkey()));
-    let transaction = Transaction::new(&[payer], message, recent_blockhash);
+        Commands::Winner { pot } => {
+            let pot_pubkey = Pubkey::from_str(&pot)
+                .context("Invalid pot public key")?;
 
-    let signature = rpc_client.send_and_confirm_transaction(&transaction)?;
-    Ok(signature.to_string())
-}
+            let pot = accounts::fetch_pot(&rpc_client, &pot_pubkey).await?;
+            if !pot.settled {
+                return Err(anyhow!(
+                    "Pot {} has not been settled yet (status: {})",
+                    pot_pubkey,
+                    PotStatus::of(&pot, unix_now()?)
+                ));
+            }
 
-pub struct InitResult {
-    pub pot_manager: Pubkey,
-    pub treasury_token_account: Pubkey,
-    pub escrow_token_account: Pubkey,
-    pub first_pot: Pubkey,
-    pub next_pot: Pubkey,
-}
+            output.print(&output::WinnerResult {
+                pot: pot_pubkey,
+                prize: pot.prize_amount(),
+                winner: fetch_winner(&rpc_client, &program, &pot_pubkey, &pot).await,
+            })?;
+        }
 
-/// Initialize a pot manager with two pots
-fn init_pot_manager(
-    rpc_client: &RpcClient,
-    payer: &Keypair,
-    manager_name: &str,
-    end_ts: u64,
-    pot_duration: u64,
-    token_mint: &Pubkey,
-) -> Result<InitResult> {
-    let program_id = Pubkey::from_str(OPEN_LOTTO_PID)?;
+        Commands::ForceClose { account } => {
+            let account_pubkey = Pubkey::from_str(&account)
+                .context("Invalid account public key")?;
 
-    // Derive PDAs
-    let (pot_manager, _) = Pubkey::find_program_address(
-        &[b"manager", payer.pubkey().as_ref(), manager_name.as_bytes()],
-        &program_id,
-    );
+            let instruction = program.force_close_account(&account_pubkey, &payer.pubkey());
+            let Some(signature) = send_admin_instruction(&rpc_client, &payer, instruction, unsent.as_ref(), output).await? else {
+                return Ok(());
+            };
+            output.print(&output::CloseResult {
+                account: account_pubkey,
+                rent_recipient: payer.pubkey(),
+                signature,
+            })?;
+        }
 
-    // Treasury token account PDA
-    let (treasury_token_account, _) = Pubkey::find_program_address(
-        &[b"treasury"],
-        &program_id,
-    );
+        Commands::CloseEscrow => {
+            let instruction = program.close_escrow(&payer.pubkey());
+            let Some(signature) = send_admin_instruction(&rpc_client, &payer, instruction, unsent.as_ref(), output).await? else {
+                return Ok(());
+            };
+            output.print(&output::CloseResult {
+                account: program.escrow_address(),
+                rent_recipient: payer.pubkey(),
+                signature,
+            })?;
+        }
 
-    // Escrow token account PDA
-    let (escrow_token_account, _) = Pubkey::find_program_address(
-        &[b"escrow"],
-        &program_id,
-    );
+        Commands::SetWager { manager, wager } => {
+            let manager_pubkey = Pubkey::from_str(&manager)
+                .context("Invalid pot manager public key")?;
 
-    let (first_pot, _) = Pubkey::find_program_address(
-        &[b"pot", pot_manager.as_ref(), &end_ts.to_le_bytes()],
-        &program_id,
-    );
+            let instruction = program.set_wager(&manager_pubkey, &payer.pubkey(), wager);
+            let Some(signature) = send_admin_instruction(&rpc_client, &payer, instruction, unsent.as_ref(), output).await? else {
+                return Ok(());
+            };
+            output.print(&output::SetWagerResult {
+                pot_manager: manager_pubkey,
+                wager,
+                signature,
+            })?;
+        }
 
-    let next_end_ts = end_ts + pot_duration;
-    let (next_pot, _) = Pubkey::find_program_address(
-        &[b"pot", pot_manager.as_ref(), &next_end_ts.to_le_bytes()],
-        &program_id,
-    );
+        Commands::SetBounty { manager, bounty, allowance } => {
+            let manager_pubkey = Pubkey::from_str(&manager)
+                .context("Invalid pot manager public key")?;
+            if bounty > MAX_CRANK_BOUNTY {
+                return Err(anyhow!("--bounty must be at most {}", MAX_CRANK_BOUNTY));
+            }
 
-    // Build instruction data: discriminator + end_ts + pot_duration + manager_name
-    let discriminator = get_anchor_discriminator("init_pot_manager");
-    let mut data = discriminator.to_vec();
-    data.extend_from_slice(&end_ts.to_le_bytes());
-    data.extend_from_slice(&pot_duration.to_le_bytes());
-    // String is serialized as: length (4 bytes) + bytes
-    data.extend_from_slice(&(manager_name.len() as u32).to_le_bytes());
-    data.extend_from_slice(manager_name.as_bytes());
-
-    // Accounts for InitPotManager with SPL token support
-    let accounts = vec![
-        AccountMeta::new(pot_manager, false),           // pot_manager
-        AccountMeta::new_readonly(*token_mint, false),  // token_mint
-        AccountMeta::new(treasury_token_account, false), // treasury_token_account
-        AccountMeta::new(escrow_token_account, false),  // escrow_token_account
-        AccountMeta::new(first_pot, false),             // first_pot
-        AccountMeta::new(next_pot, false),              // next_pot
-        AccountMeta::new(payer.pubkey(), true),         // authority (signer, payer)
-        AccountMeta::new_readonly(system_program::id(), false), // system_program
-        AccountMeta::new_readonly(spl_token::id(), false),      // token_program
-        AccountMeta::new_readonly(sysvar::rent::id(), false),   // rent
-    ];
+            let treasury = program.treasury_address();
+            let bounty_authority = program.bounty_authority_address();
+            let mut instructions = vec![program.set_crank_bounty(&manager_pubkey, &payer.pubkey(), bounty)];
+            match allowance {
+                // The treasury's owner, the manager authority, delegates the allowance
+                Some(allowance) => instructions.push(spl_token::instruction::approve(
+                    &spl_token::id(),
+                    &treasury,
+                    &bounty_authority,
+                    &payer.pubkey(),
+                    &[],
+                    allowance,
+                )?),
+                // Offline signing may have no cluster to check the treasury against
+                None if bounty > 0 && !matches!(unsent, Some(Unsent::Offline { .. })) => {
+                    let data = rpc_client.get_account_data(&treasury).await
+                        .with_context(|| format!("Failed to fetch treasury {}", treasury))?;
+                    let treasury_account = spl_token::state::Account::unpack(&data)
+                        .with_context(|| format!("Treasury {} is not a token account", treasury))?;
+                    let delegated = treasury_account.delegate == COption::Some(bounty_authority)
+                        && treasury_account.delegated_amount > 0;
+                    if !delegated {
+                        return Err(anyhow!(
+                            "The treasury has no allowance delegated to the bounty authority {}, so no bounty would be paid; pass --allowance <N> to approve one",
+                            bounty_authority
+                        ));
+                    }
+                }
+                None => {}
+            }
+            let Some(signature) = send_admin_instructions(&rpc_client, &payer, instructions, unsent.as_ref(), output).await? else {
+                return Ok(());
+            };
+            output.print(&output::SetBountyResult {
+                pot_manager: manager_pubkey,
+                bounty,
+                allowance,
+                treasury: program.treasury_address(),
+                bounty_authority: program.bounty_authority_address(),
+                signature,
+            })?;
+        }
 
-    let instruction = Instruction::new_with_bytes(program_id, &data, accounts);
+        Commands::SetAutoPayout { manager, enabled } => {
+            let manager_pubkey = Pubkey::from_str(&manager)
+                .context("Invalid pot manager public key")?;
 
-    let recent_blockhash = rpc_client.get_latest_blockhash()?;
-    let message = Message::new(&[instruction], Some(&payer.pubkey()));
-    let transaction = Transaction::new(&[payer], message, recent_blockhash);
+            let instruction = program.set_auto_payout(&manager_pubkey, &payer.pubkey(), enabled);
+            let Some(signature) = send_admin_instruction(&rpc_client, &payer, instruction, unsent.as_ref(), output).await? else {
+                return Ok(());
+            };
+            output.print(&output::SetAutoPayoutResult {
+                pot_manager: manager_pubkey,
+                enabled,
+                signature,
+            })?;
+        }
 
-    let signature = rpc_client.send_and_confirm_transaction(&transaction)?;
-    println!("Transaction: {}", signature);
+        Commands::AcquireLease { manager, slots } => {
+            let manager_pubkey = Pubkey::from_str(&manager)
+                .context("Invalid pot manager public key")?;
+            if slots == 0 || slots > MAX_LEASE_SLOTS {
+                return Err(anyhow!("--slots must be between 1 and {}", MAX_LEASE_SLOTS));
+            }
 
-    Ok(InitResult {
-        pot_manager,
-        treasury_token_account,
-        escrow_token_account,
-        first_pot,
-        next_pot,
-    })
-}
+            let instruction =
+                program.acquire_keeper_lease(&manager_pubkey, &payer.pubkey(), &payer.fee_payer().pubkey(), slots);
+            let Some(signature) = send_admin_instruction(&rpc_client, &payer, instruction, unsent.as_ref(), output).await? else {
+                return Ok(());
+            };
+            let lease_address = program.keeper_lease_address(&manager_pubkey);
+            let lease = accounts::fetch_keeper_lease(&rpc_client, &lease_address).await?
+                .ok_or_else(|| anyhow!("Keeper lease {} not found after acquiring it", lease_address))?;
+            output.print(&output::AcquireLeaseResult {
+                pot_manager: manager_pubkey,
+                lease: output::LeaseInfo { keeper: lease.keeper, expiry_slot: lease.expiry_slot },
+                signature,
+            })?;
+        }
 
-/// Get Anchor instruction discriminator
-fn get_anchor_discriminator(name: &str) -> [u8; 8] {
-    use solana_sdk::hash::hash;
-    let preimage = format!("global:{}", name);
-    let hash_bytes = hash(preimage.as_bytes()).to_bytes();
-    let mut discriminator = [0u8; 8];
-    discriminator.copy_from_slice(&hash_bytes[..8]);
-    discriminator
-}
+        Commands::SetLeaseKeepers { manager, keepers } => {
+            let manager_pubkey = Pubkey::from_str(&manager)
+                .context("Invalid pot manager public key")?;
+            let keepers = keepers
+                .iter()
+                .map(|keeper| Pubkey::from_str(keeper).with_context(|| format!("Invalid keeper public key {}", keeper)))
+                .collect::<Result<Vec<_>>>()?;
+            if keepers.len() > MAX_LEASE_KEEPERS {
+                return Err(anyhow!("at most {} --keeper may be allowed", MAX_LEASE_KEEPERS));
+            }
 
-/// Call the close_pot instruction on the Open Lotto program
-fn call_close_pot(
-    rpc_client: &RpcClient,
-    payer: &Keypair,
-    pot: &Pubkey,
-) -> Result<String> {
-    let program_id = Pubkey::from_str(OPEN_LOTTO_PID)?;
+            let instruction = program.set_lease_keepers(&manager_pubkey, &payer.pubkey(), keepers.clone());
+            let Some(signature) = send_admin_instruction(&rpc_client, &payer, instruction, unsent.as_ref(), output).await? else {
+                return Ok(());
+            };
+            output.print(&output::SetLeaseKeepersResult {
+                pot_manager: manager_pubkey,
+                keepers,
+                signature,
+            })?;
+        }
 
-    let discriminator = get_anchor_discriminator("close_pot");
-    let data = discriminator.to_vec();
+        Commands::WithdrawWager { manager, amount, to } => {
+            let manager_pubkey = Pubkey::from_str(&manager)
+                .context("Invalid pot manager public key")?;
+            let destination = match to {
+                Some(to) => Pubkey::from_str(&to).context("Invalid destination public key")?,
+                None => payer.pubkey(),
+            };
 
-    // ClosePot accounts: pot, authority (signer)
-    let accounts = vec![
-        AccountMeta::new(*pot, false),
-        AccountMeta::new(payer.pubkey(), true),
-    ];
+            let amount = match amount {
+                Some(amount) => amount,
+                None => accounts::fetch_pot_manager(&rpc_client, &manager_pubkey).await?.wager_balance,
+            };
 
-    let instruction = Instruction::new_with_bytes(program_id, &data, accounts);
+            let signature = if amount == 0 {
+                None
+            } else {
+                let instruction = program.withdraw_wager_escrow(
+                    &manager_pubkey,
+                    &payer.pubkey(),
+                    &destination,
+                    amount,
+                );
+                let Some(signature) = send_admin_instruction(&rpc_client, &payer, instruction, unsent.as_ref(), output).await? else {
+                    return Ok(());
+                };
+                Some(signature)
+            };
 
-    let recent_blockhash = rpc_client.get_latest_blockhash()?;
-    let message = Message::new(&[instruction], Some(&payer.pubkey()));
-    let transaction = Transaction::new(&[payer], message, recent_blockhash);
+            output.print(&output::WithdrawWagerResult {
+                pot_manager: manager_pubkey,
+                destination,
+                amount,
+                signature,
+            })?;
+        }
 
-    let signature = rpc_client.send_and_confirm_transaction(&transaction)?;
-    Ok(signature.to_string())
-}
+        Commands::Submit { transaction: encoded } => {
+            let copies = encoded
+                .iter()
+                .map(|encoded| transaction::decode(encoded))
+                .collect::<Result<Vec<_>>>()?;
+            let mut assembled = transaction::merge(copies)?;
+
+            // The local wallet may hold a signature that is still missing, typically the fee payer's
+            transaction::partial_sign(&mut assembled, payer.signers(&[]))?;
+            let missing = transaction::missing_signers(&assembled);
+            if !missing.is_empty() {
+                let missing: Vec<String> = missing.iter().map(Pubkey::to_string).collect();
+                return Err(anyhow!("Transaction still needs signatures from: {}", missing.join(", ")));
+            }
+            assembled.verify().context("Transaction carries an invalid signature")?;
+
+            let landed = transaction::submit(&rpc_client, &assembled).await?;
+            let num_signers = assembled.message.header.num_required_signatures as usize;
+            output.print(&output::SubmitResult {
+                signature: landed.signature.to_string(),
+                signers: assembled.message.account_keys[..num_signers].to_vec(),
+                slot: landed.slot,
+                status: landed.status_name().to_string(),
+                broadcasts: landed.broadcasts,
+            })?;
+        }
 
-/// Parse pot manager name from account data
-fn parse_pot_manager_name(data: &[u8]) -> Result<String> {
-    // PotManager layout:
-    // - discriminator: 8 bytes
-    // - authority: 32 bytes
-    // - treasury: 32 bytes
-    // - token_mint: 32 bytes
-    // - rent: 8 bytes
-    // - last_random_number: 8 bytes
-    // - timestamps: 16 bytes (two u64s)
-    // - bump: 1 byte
-    // - name: 4 bytes length + string
-    const NAME_OFFSET: usize = 8 + 32 + 32 + 32 + 8 + 8 + 16 + 1;
-
-    if data.len() < NAME_OFFSET + 4 {
-        return Err(anyhow!("PotManager data too short"));
-    }
+        Commands::MockGateway { .. } => unreachable!("handled before loading the wallet"),
+
+        Commands::Keeper {
+            config: Some(config_path),
+            poll_interval,
+            max_retries,
+            reveal_timeout,
+            metrics_bind,
+            health_threshold,
+            low_balance,
+            ..
+        } => {
+            if cli.nonce.is_some() {
+                return Err(anyhow!("--nonce can't be shared by the managers of a keeper config"));
+            }
+            let fleet = keeper::fleet::FleetConfig::load(Path::new(&expand_tilde(&config_path)))?;
+            let health_threshold = std::time::Duration::from_secs(health_threshold);
+            let shared = keeper::fleet::Shared {
+                poll_interval: std::time::Duration::from_secs(poll_interval),
+                max_retries: max_retries.max(1),
+                reveal_timeout_secs: reveal_timeout,
+                health_threshold,
+                low_balance,
+                budget: transaction::ComputeBudget {
+                    unit_limit: cli.compute_unit_limit,
+                    priority_fee: cli.priority_fee,
+                    max_priority_fee: cli.max_priority_fee,
+                },
+            };
+            let keepers = fleet.resolve(&cluster_config, &network, &solana_config.json_rpc_url, &shared)?;
+            keeper::fleet::run(&keepers, &payer, metrics_bind.as_deref(), health_threshold).await?;
+        }
+
+        Commands::Keeper {
+            manager,
+            config: None,
+            state_file,
+            poll_interval,
+            max_retries,
+            reveal_timeout,
+            metrics_bind,
+            health_threshold,
+            webhook,
+            outbox,
+            low_balance,
+            lease_slots,
+        } => {
+            if lease_slots.is_some_and(|slots| slots == 0 || slots > MAX_LEASE_SLOTS) {
+                return Err(anyhow!("--lease-slots must be between 1 and {}", MAX_LEASE_SLOTS));
+            }
+            let manager = manager.ok_or_else(|| anyhow!("--manager or --config is required"))?;
+            let manager_pubkey = Pubkey::from_str(&manager)
+                .context("Invalid pot manager public key")?;
+            let state_file = match state_file {
+                Some(path) => expand_tilde(&path).into(),
+                None => keeper::KeeperConfig::default_state_file(&manager_pubkey)?,
+            };
+            let outbox = match outbox {
+                Some(path) => expand_tilde(&path).into(),
+                None => notify::Notifier::default_path(&manager_pubkey)?,
+            };
 
-    let name_len = u32::from_le_bytes(data[NAME_OFFSET..NAME_OFFSET + 4].try_into()?) as usize;
-    if data.len() < NAME_OFFSET + 4 + name_len {
-        return Err(anyhow!("PotManager name data incomplete"));
+            let config = keeper::KeeperConfig {
+                manager: manager_pubkey,
+                state_file,
+                poll_interval: std::time::Duration::from_secs(poll_interval),
+                max_retries: max_retries.max(1),
+                reveal_timeout_secs: reveal_timeout,
+                metrics_bind,
+                health_threshold: std::time::Duration::from_secs(health_threshold),
+                webhooks: webhook,
+                outbox,
+                low_balance,
+                lease_slots,
+            };
+            keeper::run(&rpc_client, &program, &payer, &network, &config).await?;
+        }
     }
 
-    let name_bytes = &data[NAME_OFFSET + 4..NAME_OFFSET + 4 + name_len];
-    String::from_utf8(name_bytes.to_vec()).map_err(|e| anyhow!("Invalid name UTF-8: {}", e))
+    Ok(())
 }
 
-/// Parse pot info (participants, end_ts) from account data
-fn parse_pot_info(data: &[u8]) -> Result<(u64, u64)> {
-    // Pot layout (new):
-    // - discriminator: 8 bytes
-    // - pot_manager: 32 bytes
-    // - total_participants: 8 bytes
-    // - start_timestamp: 8 bytes
-    // - end_timestamp: 8 bytes
-    // - winning_slot: 8 bytes
-    // - randomness_account: 32 bytes
-    const PARTICIPANTS_OFFSET: usize = 8 + 32;
-    const END_TS_OFFSET: usize = 8 + 32 + 8 + 8;
-
-    if data.len() < END_TS_OFFSET + 8 {
-        return Err(anyhow!("Pot data too short"));
+/// Serve a mock oracle gateway on `bind`, optionally writing the matching oracle account
+async fn mock_gateway(
+    network: &Network,
+    bind: &str,
+    key: Option<String>,
+    oracle: Option<String>,
+    account_out: Option<String>,
+) -> Result<()> {
+    use switchboard::mock_gateway::{self, MockOracle};
+
+    let mock_oracle = match key {
+        Some(path) => MockOracle::load_or_create(Path::new(&expand_tilde(&path)))?,
+        None => MockOracle::random(),
+    };
+    let listener = tokio::net::TcpListener::bind(bind)
+        .await
+        .with_context(|| format!("Failed to listen on {}", bind))?;
+    let gateway_url = format!("http://{}", listener.local_addr()?);
+
+    eprintln!("Mock gateway: {}", gateway_url);
+    eprintln!("Oracle secp256k1 authority: {}", hex::encode(mock_oracle.secp_authority()));
+    if let Some(path) = account_out {
+        let oracle = match oracle {
+            Some(oracle) => Pubkey::from_str(&oracle).context("Invalid oracle public key")?,
+            None => Keypair::new().pubkey(),
+        };
+        let data = mock_gateway::oracle_account(
+            &mock_oracle.secp_authority(),
+            &network.switchboard_queue,
+            &gateway_url,
+            i64::MAX,
+        )?;
+        let path = expand_tilde(&path);
+        mock_gateway::write_account_file(Path::new(&path), &oracle, &network.switchboard_program_id, &data)?;
+        eprintln!("Oracle account {} written to {}", oracle, path);
+        eprintln!("Load it with: solana-test-validator --account {} {}", oracle, path);
     }
 
-    let participants = u64::from_le_bytes(data[PARTICIPANTS_OFFSET..PARTICIPANTS_OFFSET + 8].try_into()?);
-    let end_ts = u64::from_le_bytes(data[END_TS_OFFSET..END_TS_OFFSET + 8].try_into()?);
+    mock_gateway::serve(listener, std::sync::Arc::new(mock_oracle)).await
+}
+
+/// Read the oracle field from a randomness account's data
+fn read_oracle_from_randomness(data: &[u8]) -> Result<Pubkey> {
+    let randomness = switchboard::accounts::randomness(data)?;
+    Ok(Pubkey::new_from_array(randomness.oracle.to_bytes()))
+}
 
-    Ok((participants, end_ts))
+/// Sign and send a single instruction with the wallet
+async fn send_instruction(
+    rpc_client: &RpcClient,
+    payer: &Wallet,
+    instruction: Instruction,
+) -> Result<String> {
+    send_instructions(rpc_client, payer, &[instruction]).await
 }
 
-/// Call the force_close_account instruction on the Open Lotto program
-fn call_force_close_account(
+/// Sign and send instructions in one transaction with the wallet
+async fn send_instructions(
     rpc_client: &RpcClient,
-    payer: &Keypair,
-    account: &Pubkey,
+    payer: &Wallet,
+    instructions: &[Instruction],
 ) -> Result<String> {
-    let program_id = Pubkey::from_str(OPEN_LOTTO_PID)?;
+    let signature = transaction::send(rpc_client, payer, &[], instructions).await?;
+    Ok(signature.to_string())
+}
+
+/// Send an admin instruction, or print its transaction under `--sign-only` /
+/// `--dump-transaction`; the signature is returned only when it was sent
+async fn send_admin_instruction(
+    rpc_client: &RpcClient,
+    payer: &Wallet,
+    instruction: Instruction,
+    unsent: Option<&Unsent>,
+    output: OutputFormat,
+) -> Result<Option<String>> {
+    send_admin_instructions(rpc_client, payer, vec![instruction], unsent, output).await
+}
 
-    let discriminator = get_anchor_discriminator("force_close_account");
-    let data = discriminator.to_vec();
+/// `send_admin_instruction` for instructions that go in one transaction
+async fn send_admin_instructions(
+    rpc_client: &RpcClient,
+    payer: &Wallet,
+    instructions: Vec<Instruction>,
+    unsent: Option<&Unsent>,
+    output: OutputFormat,
+) -> Result<Option<String>> {
+    match unsent {
+        None => send_instructions(rpc_client, payer, &instructions).await.map(Some),
+        Some(Unsent::Offline { sign, blockhash }) => {
+            let mut transaction = transaction::build(rpc_client, payer, &instructions, *blockhash).await?;
+            if *sign {
+                transaction::partial_sign(&mut transaction, payer.signers(&[]))?;
+            }
+            output.print(&output::OfflineTransaction::new(&transaction)?)?;
+            Ok(None)
+        }
+        Some(Unsent::DryRun) => {
+            let program = OpenLotto::new(instructions[0].program_id);
+            let watched = dry_run_accounts(&program, payer, &instructions);
+            let simulated = transaction::simulate(rpc_client, payer, "Admin transaction", &instructions, &watched).await?;
+            output.print(&output::DryRun { transactions: vec![simulated] })?;
+            Ok(None)
+        }
+    }
+}
 
-    // ForceCloseAccount accounts: account, authority (signer)
-    let accounts = vec![
-        AccountMeta::new(*account, false),
-        AccountMeta::new(payer.pubkey(), true),
+/// Accounts whose balances a dry run reports: the program's escrows and treasury, the
+/// fee payer and whatever else `instructions` write
+fn dry_run_accounts(program: &OpenLotto, payer: &Wallet, instructions: &[Instruction]) -> Vec<(String, Pubkey)> {
+    let mut watched = vec![
+        ("escrow".to_string(), program.escrow_address()),
+        ("treasury".to_string(), program.treasury_address()),
+        ("wager escrow".to_string(), program.wager_escrow_address()),
+        ("fee payer".to_string(), payer.fee_payer().pubkey()),
     ];
+    for meta in instructions.iter().flat_map(|ix| &ix.accounts) {
+        if meta.is_writable && !watched.iter().any(|(_, address)| *address == meta.pubkey) {
+            watched.push(("written".to_string(), meta.pubkey));
+        }
+    }
+    watched
+}
+
+/// Reveal the pot's randomness and settle it in one transaction, then look up the winner
+async fn settle(
+    rpc_client: &RpcClient,
+    program: &OpenLotto,
+    payer: &Wallet,
+    pot: &Pubkey,
+    randomness_account: &Pubkey,
+    network: &Network,
+) -> Result<SettleResult> {
+    let signature = call_reveal_and_settle(rpc_client, program, payer, pot, randomness_account, network).await?;
+
+    let settled = accounts::fetch_pot(rpc_client, pot).await?;
+    let winner = fetch_winner(rpc_client, program, pot, &settled).await;
+
+    Ok(SettleResult {
+        pot: *pot,
+        randomness_account: *randomness_account,
+        winner_index: winner.index,
+        winning_ticket: winner.ticket,
+        winner: winner.participant,
+        signature,
+    })
+}
 
-    let instruction = Instruction::new_with_bytes(program_id, &data, accounts);
+/// The wallet's associated token account for `token_mint`, plus the instruction
+/// creating it when it doesn't exist yet
+async fn user_token_account(
+    rpc_client: &RpcClient,
+    payer: &Wallet,
+    token_mint: &Pubkey,
+) -> Result<(Pubkey, Option<Instruction>)> {
+    let token_account = get_associated_token_address(&payer.pubkey(), token_mint);
+    let exists = rpc_client
+        .get_account_with_commitment(&token_account, rpc_client.commitment()).await?
+        .value
+        .is_some();
+    if exists {
+        return Ok((token_account, None));
+    }
 
-    let recent_blockhash = rpc_client.get_latest_blockhash()?;
-    let message = Message::new(&[instruction], Some(&payer.pubkey()));
-    let transaction = Transaction::new(&[payer], message, recent_blockhash);
+    eprintln!("Creating associated token account {}", token_account);
+    let create = create_associated_token_account_idempotent(
+        &payer.pubkey(),
+        &payer.pubkey(),
+        token_mint,
+        &spl_token::id(),
+    );
+    Ok((token_account, Some(create)))
+}
 
-    let signature = rpc_client.send_and_confirm_transaction(&transaction)?;
-    Ok(signature.to_string())
+/// Look up the winning ticket of a settled pot
+async fn fetch_winner(
+    rpc_client: &RpcClient,
+    program: &OpenLotto,
+    pot_address: &Pubkey,
+    pot: &Pot,
+) -> output::WinnerInfo {
+    let ticket = program.ticket_address(pot_address, pot.winning_slot);
+    output::WinnerInfo {
+        index: pot.winning_slot,
+        ticket,
+        participant: accounts::fetch_ticket(rpc_client, &ticket).await
+            .map(|ticket| ticket.participant)
+            .ok(),
+    }
+}
+
+fn unix_now() -> Result<u64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs())
+}
+
+/// Commit `randomness_account` and call draw_lottery in one transaction, returning the
+/// signature and the oracle used
+///
+/// draw_lottery requires `seed_slot == clock.slot - 1`, which only holds reliably when
+/// the commit lands in the same slot. Both instructions together touch about a dozen
+/// accounts, well within a legacy transaction without a lookup table.
+async fn call_commit_and_draw(
+    rpc_client: &RpcClient,
+    program: &OpenLotto,
+    payer: &Wallet,
+    pot: &Pubkey,
+    randomness_account: &Pubkey,
+    network: &Network,
+) -> Result<(String, Pubkey)> {
+    // The pot's manager holds the configured wager and crank bounty
+    let pot_manager = accounts::fetch_pot(rpc_client, pot).await?.pot_manager;
+    let (bounty_account, create_bounty_account) = bounty_token_account(rpc_client, payer, &pot_manager).await?;
+
+    let draw = program.draw_lottery(pot, &pot_manager, &payer.pubkey(), randomness_account, &bounty_account);
+    let followed_by: Vec<Instruction> = create_bounty_account.into_iter().chain([draw]).collect();
+    switchboard::commit_randomness_with(rpc_client, payer, randomness_account, network, &followed_by).await
 }
 
-/// Call the close_escrow instruction on the Open Lotto program
-fn call_close_escrow(
+/// Reveal the pot's randomness through its committed oracle and call settle_lottery in
+/// the same transaction, returning the signature
+///
+/// settle_lottery only reads the value in the slot it was revealed in, so every attempt
+/// reveals again rather than settling on an earlier reveal.
+async fn call_reveal_and_settle(
     rpc_client: &RpcClient,
-    payer: &Keypair,
+    program: &OpenLotto,
+    payer: &Wallet,
+    pot: &Pubkey,
+    randomness_account: &Pubkey,
+    network: &Network,
 ) -> Result<String> {
-    let program_id = Pubkey::from_str(OPEN_LOTTO_PID)?;
+    let pot_manager = accounts::fetch_pot(rpc_client, pot).await?.pot_manager;
+    let (bounty_account, create_bounty_account) = bounty_token_account(rpc_client, payer, &pot_manager).await?;
+    let oracle = read_oracle_from_randomness(&rpc_client.get_account_data(randomness_account).await?)?;
 
-    // Derive escrow PDA
-    let (escrow, _bump) = Pubkey::find_program_address(&[b"escrow"], &program_id);
+    let settle = program.settle_lottery(pot, &pot_manager, randomness_account, &payer.pubkey(), &bounty_account);
+    let followed_by: Vec<Instruction> = create_bounty_account.into_iter().chain([settle]).collect();
+    switchboard::reveal_randomness_with(rpc_client, payer, randomness_account, &oracle, network, &followed_by).await
+}
 
-    let discriminator = get_anchor_discriminator("close_escrow");
-    let data = discriminator.to_vec();
+/// Call the payout_winner instruction for settled `pot`, returning the signature, the
+/// winner and the token account the prize went to
+async fn call_payout_winner(
+    rpc_client: &RpcClient,
+    program: &OpenLotto,
+    payer: &Wallet,
+    pot_address: &Pubkey,
+    pot: &Pot,
+) -> Result<(String, Pubkey, Pubkey)> {
+    let ticket = program.ticket_address(pot_address, pot.winning_slot);
+    let winner = accounts::fetch_ticket(rpc_client, &ticket).await
+        .with_context(|| format!("Winning ticket {} not found", ticket))?
+        .participant;
+    let token_mint = accounts::fetch_pot_manager(rpc_client, &pot.pot_manager).await?.token_mint;
+
+    let instruction = program.payout_winner(pot_address, pot, &token_mint, &winner, &payer.pubkey());
+    let signature = send_instruction(rpc_client, payer, instruction).await?;
+    Ok((signature, winner, get_associated_token_address(&winner, &token_mint)))
+}
 
-    // CloseEscrow accounts: escrow_token_account, authority (signer), token_program
-    let accounts = vec![
-        AccountMeta::new(escrow, false),
-        AccountMeta::new(payer.pubkey(), true),
-        AccountMeta::new_readonly(spl_token::id(), false),
-    ];
+/// The wallet's token account receiving `pot_manager`'s crank bounty, plus the
+/// instruction creating it when it doesn't exist yet
+async fn bounty_token_account(
+    rpc_client: &RpcClient,
+    payer: &Wallet,
+    pot_manager: &Pubkey,
+) -> Result<(Pubkey, Option<Instruction>)> {
+    let token_mint = accounts::fetch_pot_manager(rpc_client, pot_manager).await?.token_mint;
+    user_token_account(rpc_client, payer, &token_mint).await
+}
 
-    let instruction = Instruction::new_with_bytes(program_id, &data, accounts);
+/// Initialize a pot manager with two pots
+#[allow(clippy::too_many_arguments)]
+async fn init_pot_manager(
+    rpc_client: &RpcClient,
+    program: &OpenLotto,
+    payer: &Wallet,
+    manager_name: &str,
+    end_ts: u64,
+    pot_duration: u64,
+    token_mint: &Pubkey,
+    wager: u64,
+) -> Result<output::InitResult> {
+    let instruction = program.init_pot_manager(
+        &payer.pubkey(),
+        token_mint,
+        manager_name,
+        end_ts,
+        pot_duration,
+        wager,
+    );
 
-    let recent_blockhash = rpc_client.get_latest_blockhash()?;
-    let message = Message::new(&[instruction], Some(&payer.pubkey()));
-    let transaction = Transaction::new(&[payer], message, recent_blockhash);
+    let signature = send_instruction(rpc_client, payer, instruction).await?;
 
-    let signature = rpc_client.send_and_confirm_transaction(&transaction)?;
-    Ok(signature.to_string())
+    let pot_manager = program.pot_manager_address(&payer.pubkey(), manager_name);
+    Ok(output::InitResult {
+        pot_manager,
+        token_mint: *token_mint,
+        treasury_token_account: program.treasury_address(),
+        escrow_token_account: program.escrow_address(),
+        first_pot: program.pot_address(&pot_manager, end_ts),
+        next_pot: program.pot_address(&pot_manager, end_ts + pot_duration),
+        first_pot_end_timestamp: end_ts,
+        wager,
+        signature,
+    })
 }
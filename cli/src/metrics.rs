@@ -0,0 +1,284 @@
+//! Keeper metrics and health
+//!
+//! The keeper, the transaction sender and the oracle selection record into one
+//! process-wide registry; [`serve`] exposes it on `/metrics` in the Prometheus text
+//! format, next to a `/healthz` that fails once a pot has stayed ended without being
+//! settled for longer than the configured threshold. Balances and unsettled pots are
+//! tracked per manager, so one registry serves a keeper running several.
+
+use anyhow::Result;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::unix_now;
+
+/// Upper bounds of the reveal latency histogram buckets, in seconds
+const REVEAL_BUCKETS: [f64; 8] = [2.0, 5.0, 10.0, 15.0, 30.0, 60.0, 120.0, 300.0];
+
+/// Latest balance (address, lamports) of each account, keyed by manager and account label
+type Balances = BTreeMap<(Pubkey, &'static str), (Pubkey, u64)>;
+
+#[derive(Default)]
+struct Registry {
+    pots_drawn: AtomicU64,
+    pots_settled: AtomicU64,
+    prizes_paid_out: AtomicU64,
+    rebroadcasts: AtomicU64,
+    resigned: AtomicU64,
+    reveal_latency: Mutex<Histogram>,
+    oracle_failures: Mutex<BTreeMap<Pubkey, u64>>,
+    step_retries: Mutex<BTreeMap<String, u64>>,
+    balances: Mutex<Balances>,
+    /// Per manager, the pot the keeper is working on and its end timestamp, while it is
+    /// not settled yet
+    unsettled_since: Mutex<BTreeMap<Pubkey, (Pubkey, u64)>>,
+}
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; REVEAL_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(REVEAL_BUCKETS) {
+            if value <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+}
+
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+
+fn registry() -> &'static Registry {
+    REGISTRY.get_or_init(Registry::default)
+}
+
+pub fn pot_drawn() {
+    registry().pots_drawn.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn pot_settled() {
+    registry().pots_settled.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn prize_paid_out() {
+    registry().prizes_paid_out.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Time the keeper waited for the oracle to reveal a drawn pot's randomness
+pub fn reveal_latency(latency: Duration) {
+    registry().reveal_latency.lock().unwrap().observe(latency.as_secs_f64());
+}
+
+/// `oracle` failed to commit or reveal and was dropped from the healthy set
+pub fn oracle_failure(oracle: &Pubkey) {
+    *registry().oracle_failures.lock().unwrap().entry(*oracle).or_default() += 1;
+}
+
+/// Broadcasts of a transaction beyond its first
+pub fn rebroadcasts(count: usize) {
+    registry().rebroadcasts.fetch_add(count as u64, Ordering::Relaxed);
+}
+
+/// A transaction expired unlanded and was signed again on a fresh blockhash
+pub fn resigned() {
+    registry().resigned.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A keeper step failed and is retried
+pub fn step_retry(step: &str) {
+    *registry().step_retries.lock().unwrap().entry(step.to_string()).or_default() += 1;
+}
+
+/// Latest SOL balance of an account `manager`'s keeper depends on, labelled `account`,
+/// returning the one recorded before
+pub fn balance(manager: &Pubkey, account: &'static str, address: &Pubkey, lamports: u64) -> Option<u64> {
+    registry()
+        .balances
+        .lock()
+        .unwrap()
+        .insert((*manager, account), (*address, lamports))
+        .map(|(_, previous)| previous)
+}
+
+/// Record `manager`'s pot (and its end timestamp) that has ended and is not settled yet,
+/// or with `None` that nothing is waiting for settlement
+pub fn unsettled(manager: &Pubkey, pot: Option<(Pubkey, u64)>) {
+    let mut unsettled = registry().unsettled_since.lock().unwrap();
+    match pot {
+        Some(pot) => unsettled.insert(*manager, pot),
+        None => unsettled.remove(manager),
+    };
+}
+
+/// Prometheus text exposition of every metric
+pub fn render() -> String {
+    let registry = registry();
+    let mut out = String::new();
+
+    counter(&mut out, "open_lotto_pots_drawn_total", "Pots drawn by this keeper", registry.pots_drawn.load(Ordering::Relaxed));
+    counter(&mut out, "open_lotto_pots_settled_total", "Pots settled by this keeper", registry.pots_settled.load(Ordering::Relaxed));
+    counter(
+        &mut out,
+        "open_lotto_prizes_paid_out_total",
+        "Prizes pushed to winners by this keeper",
+        registry.prizes_paid_out.load(Ordering::Relaxed),
+    );
+    counter(
+        &mut out,
+        "open_lotto_transaction_rebroadcasts_total",
+        "Broadcasts of a transaction beyond its first",
+        registry.rebroadcasts.load(Ordering::Relaxed),
+    );
+    counter(
+        &mut out,
+        "open_lotto_transaction_resigned_total",
+        "Transactions signed again after their blockhash expired",
+        registry.resigned.load(Ordering::Relaxed),
+    );
+
+    let _ = writeln!(out, "# HELP open_lotto_keeper_step_retries_total Keeper steps retried after a failure");
+    let _ = writeln!(out, "# TYPE open_lotto_keeper_step_retries_total counter");
+    for (step, count) in registry.step_retries.lock().unwrap().iter() {
+        let _ = writeln!(out, "open_lotto_keeper_step_retries_total{{step=\"{}\"}} {}", step, count);
+    }
+
+    let _ = writeln!(out, "# HELP open_lotto_oracle_failures_total Commits or reveals an oracle failed");
+    let _ = writeln!(out, "# TYPE open_lotto_oracle_failures_total counter");
+    for (oracle, count) in registry.oracle_failures.lock().unwrap().iter() {
+        let _ = writeln!(out, "open_lotto_oracle_failures_total{{oracle=\"{}\"}} {}", oracle, count);
+    }
+
+    let histogram = registry.reveal_latency.lock().unwrap();
+    let _ = writeln!(out, "# HELP open_lotto_reveal_latency_seconds Time waited for the oracle to reveal randomness");
+    let _ = writeln!(out, "# TYPE open_lotto_reveal_latency_seconds histogram");
+    for (bound, count) in REVEAL_BUCKETS.iter().zip(histogram.buckets) {
+        let _ = writeln!(out, "open_lotto_reveal_latency_seconds_bucket{{le=\"{}\"}} {}", bound, count);
+    }
+    let _ = writeln!(out, "open_lotto_reveal_latency_seconds_bucket{{le=\"+Inf\"}} {}", histogram.count);
+    let _ = writeln!(out, "open_lotto_reveal_latency_seconds_sum {}", histogram.sum);
+    let _ = writeln!(out, "open_lotto_reveal_latency_seconds_count {}", histogram.count);
+
+    let _ = writeln!(out, "# HELP open_lotto_balance_lamports SOL balance of the keeper and the wager escrow");
+    let _ = writeln!(out, "# TYPE open_lotto_balance_lamports gauge");
+    for ((manager, account), (address, lamports)) in registry.balances.lock().unwrap().iter() {
+        let _ = writeln!(
+            out,
+            "open_lotto_balance_lamports{{manager=\"{}\",account=\"{}\",address=\"{}\"}} {}",
+            manager, account, address, lamports
+        );
+    }
+    out
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+/// Why the keeper is unhealthy: a pot of one of its managers ended more than
+/// `threshold` ago and is still not settled
+pub fn unhealthy(threshold: Duration, now: u64) -> Option<String> {
+    let unsettled = registry().unsettled_since.lock().unwrap();
+    let (pot, ended) = unsettled.values().min_by_key(|(_, ended)| *ended)?;
+    let overdue = now.saturating_sub(*ended);
+    (overdue > threshold.as_secs()).then(|| format!("pot {} ended {}s ago and is not settled", pot, overdue))
+}
+
+/// Serve `/metrics` and `/healthz` on `listener` until the process is stopped
+pub async fn serve(listener: TcpListener, health_threshold: Duration) -> Result<()> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, health_threshold).await {
+                eprintln!("Metrics request failed: {:#}", e);
+            }
+        });
+    }
+}
+
+/// Answer one HTTP/1.1 request and close the connection
+async fn handle_connection(mut stream: TcpStream, health_threshold: Duration) -> Result<()> {
+    let (read, mut write) = stream.split();
+    let mut reader = BufReader::new(read);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 || header.trim_end().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", render()),
+        (Some("GET"), Some("/healthz")) => match unhealthy(health_threshold, unix_now()?) {
+            None => ("200 OK", "ok\n".to_string()),
+            Some(reason) => ("503 Service Unavailable", format!("{}\n", reason)),
+        },
+        _ => ("404 Not Found", String::new()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    write.write_all(response.as_bytes()).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn serves_metrics_and_health() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, Duration::from_secs(600)));
+
+        let oracle = Pubkey::new_unique();
+        oracle_failure(&oracle);
+        reveal_latency(Duration::from_secs(7));
+        let metrics = reqwest::get(format!("{}/metrics", url)).await.unwrap().text().await.unwrap();
+        assert!(metrics.contains(&format!("open_lotto_oracle_failures_total{{oracle=\"{}\"}} 1", oracle)));
+        assert!(metrics.contains("open_lotto_reveal_latency_seconds_bucket{le=\"10\"}"));
+
+        // One manager's pot ended ten minutes and a second ago, the other's just now
+        let now = unix_now().unwrap();
+        let (overdue, fresh) = (Pubkey::new_unique(), Pubkey::new_unique());
+        unsettled(&overdue, Some((Pubkey::new_unique(), now - 601)));
+        unsettled(&fresh, Some((Pubkey::new_unique(), now)));
+        let response = reqwest::get(format!("{}/healthz", url)).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        unsettled(&overdue, None);
+        let response = reqwest::get(format!("{}/healthz", url)).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        unsettled(&fresh, None);
+    }
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let mut histogram = Histogram::default();
+        histogram.observe(3.0);
+        histogram.observe(40.0);
+        assert_eq!(histogram.buckets, [0, 1, 1, 1, 1, 2, 2, 2]);
+        assert_eq!(histogram.count, 2);
+    }
+}
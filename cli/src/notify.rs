@@ -0,0 +1,358 @@
+//! Keeper webhooks
+//!
+//! The keeper reports draws, settlements, failed steps and a low fee payer balance by
+//! POSTing a JSON [`Event`] to every `--webhook`. Deliveries go through an outbox file
+//! first, so a webhook that is down, or a keeper that restarts, only delays them: pending
+//! deliveries are retried with exponential backoff until they succeed or run out of
+//! attempts.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+use crate::output::{display, display_option};
+use crate::unix_now;
+
+/// Deliveries dropped after this many failed attempts
+const MAX_ATTEMPTS: u32 = 12;
+/// Longest wait between two attempts of one delivery
+const MAX_BACKOFF: Duration = Duration::from_secs(3600);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Something the keeper did or ran into
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// randomnessCommit + draw_lottery landed
+    Drawn {
+        #[serde(serialize_with = "display")]
+        pot: Pubkey,
+        #[serde(serialize_with = "display")]
+        randomness_account: Pubkey,
+        #[serde(serialize_with = "display")]
+        oracle: Pubkey,
+        signatures: Vec<String>,
+    },
+    /// settle_lottery picked a winner
+    Settled {
+        #[serde(serialize_with = "display")]
+        pot: Pubkey,
+        winner_ticket_index: u64,
+        /// `None` if the winning ticket account has been closed
+        #[serde(serialize_with = "display_option")]
+        participant: Option<Pubkey>,
+        /// Prize in token base units
+        prize: u64,
+        /// Draw (when this keeper drew the pot) and settle signatures
+        signatures: Vec<String>,
+    },
+    /// payout_winner pushed the prize to the winner (auto-payout managers)
+    PaidOut {
+        #[serde(serialize_with = "display")]
+        pot: Pubkey,
+        #[serde(serialize_with = "display")]
+        winner: Pubkey,
+        /// Prize in token base units
+        prize: u64,
+        signature: String,
+    },
+    /// A keeper step failed after all its retries
+    Failed {
+        #[serde(serialize_with = "display_option")]
+        pot: Option<Pubkey>,
+        error: String,
+    },
+    /// The fee payer dropped below `--low-balance`
+    LowBalance {
+        #[serde(serialize_with = "display")]
+        address: Pubkey,
+        lamports: u64,
+        threshold: u64,
+    },
+}
+
+/// One payload waiting to be posted to one webhook
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Delivery {
+    id: u64,
+    url: String,
+    payload: serde_json::Value,
+    attempts: u32,
+    /// Unix timestamp of the next attempt
+    next_attempt: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Outbox {
+    next_id: u64,
+    deliveries: Vec<Delivery>,
+}
+
+impl Outbox {
+    fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read webhook outbox {}", path.display()))?;
+        serde_json::from_str(&contents).with_context(|| format!("Failed to parse webhook outbox {}", path.display()))
+    }
+
+    /// Write the outbox atomically (temp file + rename), like the keeper state
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&tmp, path).with_context(|| format!("Failed to write webhook outbox {}", path.display()))
+    }
+}
+
+/// Posts keeper events to webhooks through an on-disk outbox
+pub struct Notifier {
+    webhooks: Vec<String>,
+    path: PathBuf,
+    outbox: Mutex<Outbox>,
+    wake: Notify,
+    client: reqwest::Client,
+    /// Wait after a delivery's first failed attempt, doubled on each further one
+    retry_base: Duration,
+}
+
+impl Notifier {
+    /// Notifier posting to `webhooks`, picking up deliveries a previous run left in the
+    /// outbox at `path`
+    pub fn new(webhooks: Vec<String>, path: PathBuf) -> Result<Self> {
+        let outbox = Outbox::load(&path)?;
+        if !outbox.deliveries.is_empty() {
+            eprintln!("{} webhook deliveries pending in {}", outbox.deliveries.len(), path.display());
+        }
+        Ok(Self {
+            webhooks,
+            path,
+            outbox: Mutex::new(outbox),
+            wake: Notify::new(),
+            client: reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build()?,
+            retry_base: Duration::from_secs(5),
+        })
+    }
+
+    /// Default outbox location: ~/.config/open-lotto/outbox-<manager>.json
+    pub fn default_path(manager: &Pubkey) -> Result<PathBuf> {
+        let home = dirs::home_dir().ok_or_else(|| anyhow!("Unable to find home directory"))?;
+        Ok(home
+            .join(".config")
+            .join("open-lotto")
+            .join(format!("outbox-{}.json", manager)))
+    }
+
+    /// Queue `event` for every webhook; it is on disk once this returns
+    pub fn notify(&self, event: Event) {
+        if self.webhooks.is_empty() {
+            return;
+        }
+        if let Err(e) = self.enqueue(&event) {
+            eprintln!("Failed to queue {:?} webhook: {:#}", event, e);
+        }
+        self.wake.notify_one();
+    }
+
+    fn enqueue(&self, event: &Event) -> Result<()> {
+        let mut payload = serde_json::to_value(event)?;
+        payload["timestamp"] = unix_now()?.into();
+
+        let mut outbox = self.outbox.lock().unwrap();
+        for url in &self.webhooks {
+            let id = outbox.next_id;
+            outbox.next_id += 1;
+            outbox.deliveries.push(Delivery {
+                id,
+                url: url.clone(),
+                payload: payload.clone(),
+                attempts: 0,
+                next_attempt: 0,
+            });
+        }
+        outbox.save(&self.path)
+    }
+
+    /// Deliver queued events until the process is stopped
+    pub async fn run(&self) {
+        loop {
+            let wait = match self.deliver_due().await {
+                Ok(wait) => wait,
+                Err(e) => {
+                    eprintln!("Webhook outbox failed: {:#}", e);
+                    self.retry_base
+                }
+            };
+            // A new event cuts the wait short
+            let _ = tokio::time::timeout(wait, self.wake.notified()).await;
+        }
+    }
+
+    /// Attempt every delivery that is due, returning how long until the next one is
+    async fn deliver_due(&self) -> Result<Duration> {
+        let now = unix_now()?;
+        let due: Vec<Delivery> = self
+            .outbox
+            .lock()
+            .unwrap()
+            .deliveries
+            .iter()
+            .filter(|delivery| delivery.next_attempt <= now)
+            .cloned()
+            .collect();
+
+        for delivery in due {
+            let result = self.post(&delivery).await;
+            let mut outbox = self.outbox.lock().unwrap();
+            let Some(index) = outbox.deliveries.iter().position(|d| d.id == delivery.id) else { continue };
+            match result {
+                Ok(()) => {
+                    outbox.deliveries.remove(index);
+                }
+                Err(e) if delivery.attempts + 1 >= MAX_ATTEMPTS => {
+                    eprintln!("Dropping webhook delivery to {} after {} attempts: {:#}", delivery.url, MAX_ATTEMPTS, e);
+                    outbox.deliveries.remove(index);
+                }
+                Err(e) => {
+                    let pending = &mut outbox.deliveries[index];
+                    let backoff = (self.retry_base * 2u32.saturating_pow(pending.attempts)).min(MAX_BACKOFF);
+                    pending.attempts += 1;
+                    pending.next_attempt = unix_now()? + backoff.as_secs().max(1);
+                    eprintln!(
+                        "Webhook delivery to {} failed (attempt {}/{}), retrying in {:?}: {:#}",
+                        pending.url, pending.attempts, MAX_ATTEMPTS, backoff, e
+                    );
+                }
+            }
+            outbox.save(&self.path)?;
+        }
+
+        let outbox = self.outbox.lock().unwrap();
+        let now = unix_now()?;
+        Ok(match outbox.deliveries.iter().map(|d| d.next_attempt).min() {
+            Some(next) => Duration::from_secs(next.saturating_sub(now)),
+            None => MAX_BACKOFF,
+        })
+    }
+
+    async fn post(&self, delivery: &Delivery) -> Result<()> {
+        let response = self.client.post(&delivery.url).json(&delivery.payload).send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("webhook answered {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    /// Webhook stand-in failing its first `failures` requests, forwarding every body it
+    /// accepts to the returned channel
+    async fn webhook(failures: usize) -> (String, mpsc::UnboundedReceiver<serde_json::Value>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            for request in 0.. {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let (read, mut write) = stream.split();
+                let mut reader = BufReader::new(read);
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).await.unwrap() == 0 || line.trim_end().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).await.unwrap();
+
+                let status = if request < failures {
+                    "500 Internal Server Error"
+                } else {
+                    sender.send(serde_json::from_slice(&body).unwrap()).unwrap();
+                    "204 No Content"
+                };
+                let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+                write.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, receiver)
+    }
+
+    fn outbox_path() -> PathBuf {
+        std::env::temp_dir().join(format!("open-lotto-outbox-{}.json", Pubkey::new_unique()))
+    }
+
+    fn settled(pot: Pubkey) -> Event {
+        Event::Settled {
+            pot,
+            winner_ticket_index: 3,
+            participant: Some(Pubkey::new_unique()),
+            prize: 900,
+            signatures: vec!["draw".into(), "settle".into()],
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_until_the_webhook_accepts() {
+        let (url, mut received) = webhook(2).await;
+        let path = outbox_path();
+        let mut notifier = Notifier::new(vec![url], path.clone()).unwrap();
+        notifier.retry_base = Duration::from_millis(1);
+        let notifier = Arc::new(notifier);
+        tokio::spawn({
+            let notifier = notifier.clone();
+            async move { notifier.run().await }
+        });
+
+        let pot = Pubkey::new_unique();
+        notifier.notify(settled(pot));
+        let payload = tokio::time::timeout(Duration::from_secs(10), received.recv()).await.unwrap().unwrap();
+        assert_eq!(payload["event"], "settled");
+        assert_eq!(payload["pot"], pot.to_string());
+        assert_eq!(payload["winner_ticket_index"], 3);
+        assert_eq!(payload["prize"], 900);
+
+        // Delivered, so the outbox is empty again
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(Outbox::load(&path).unwrap().deliveries.is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn pending_deliveries_survive_a_restart() {
+        let path = outbox_path();
+        let (url, mut received) = webhook(0).await;
+
+        // Queued but never delivered: the keeper stopped before its delivery task ran
+        let pot = Pubkey::new_unique();
+        Notifier::new(vec![url], path.clone()).unwrap().notify(settled(pot));
+
+        let restarted = Notifier::new(Vec::new(), path.clone()).unwrap();
+        restarted.deliver_due().await.unwrap();
+        let payload = received.recv().await.unwrap();
+        assert_eq!(payload["pot"], pot.to_string());
+        assert!(Outbox::load(&path).unwrap().deliveries.is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
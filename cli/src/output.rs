@@ -0,0 +1,860 @@
+//! Command results and how they are printed
+//!
+//! Every command returns one of these structs. With `--output text` it is rendered
+//! through its `Display` impl; with `--output json` it is serialized as-is. Either way
+//! the result is the only thing written to stdout - progress logs go to stderr.
+
+use anyhow::Result;
+use clap::ValueEnum;
+use open_lotto_client::PotStatus;
+use serde::{Serialize, Serializer};
+use solana_sdk::{hash::Hash, pubkey::Pubkey, transaction::Transaction};
+use std::fmt;
+
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable text
+    #[default]
+    Text,
+    /// A single pretty-printed JSON document
+    Json,
+}
+
+impl OutputFormat {
+    /// Print a command result to stdout
+    pub fn print<T: Serialize + fmt::Display>(&self, result: &T) -> Result<()> {
+        match self {
+            OutputFormat::Text => println!("{}", result),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(result)?),
+        }
+        Ok(())
+    }
+}
+
+/// Serialize pubkeys (and anything else `Display`) as strings rather than byte arrays
+pub fn display<T: fmt::Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+pub fn display_option<T: fmt::Display, S: Serializer>(
+    value: &Option<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serializer.collect_str(value),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub fn display_vec<T: fmt::Display, S: Serializer>(values: &[T], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(values.iter().map(ToString::to_string))
+}
+
+#[derive(Serialize)]
+pub struct InitResult {
+    #[serde(serialize_with = "display")]
+    pub pot_manager: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub token_mint: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub treasury_token_account: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub escrow_token_account: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub first_pot: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub next_pot: Pubkey,
+    pub first_pot_end_timestamp: u64,
+    pub wager: u64,
+    pub signature: String,
+}
+
+impl fmt::Display for InitResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "✓ Pot Manager initialized!")?;
+        writeln!(f, "Manager: {}", self.pot_manager)?;
+        writeln!(f, "Token Mint: {}", self.token_mint)?;
+        writeln!(f, "Treasury Token Account: {}", self.treasury_token_account)?;
+        writeln!(f, "Escrow Token Account: {}", self.escrow_token_account)?;
+        writeln!(f, "First Pot: {}", self.first_pot)?;
+        writeln!(f, "Next Pot: {}", self.next_pot)?;
+        writeln!(f, "Wager per draw: {} lamports", self.wager)?;
+        writeln!(f, "Transaction: {}", self.signature)?;
+        writeln!(f, "\nFirst pot ends at: {}", self.first_pot_end_timestamp)?;
+        writeln!(f, "\nTo draw the lottery, run:")?;
+        write!(f, "  open-lotto draw --pot {}", self.first_pot)
+    }
+}
+
+#[derive(Serialize)]
+pub struct DrawResult {
+    #[serde(serialize_with = "display")]
+    pub pot: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub randomness_account: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub oracle: Pubkey,
+    pub commit_slot: u64,
+    /// Commit and draw_lottery land in the same transaction
+    pub signature: String,
+}
+
+impl fmt::Display for DrawResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "✓ Draw completed!")?;
+        writeln!(f, "Pot: {}", self.pot)?;
+        writeln!(f, "Randomness account: {}", self.randomness_account)?;
+        writeln!(f, "Oracle: {}", self.oracle)?;
+        writeln!(f, "Commit slot: {}", self.commit_slot)?;
+        write!(f, "Transaction: {}", self.signature)
+    }
+}
+
+#[derive(Serialize)]
+pub struct SettleResult {
+    #[serde(serialize_with = "display")]
+    pub pot: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub randomness_account: Pubkey,
+    pub winner_index: u64,
+    #[serde(serialize_with = "display")]
+    pub winning_ticket: Pubkey,
+    #[serde(serialize_with = "display_option")]
+    pub winner: Option<Pubkey>,
+    pub signature: String,
+}
+
+impl fmt::Display for SettleResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "✓ Settle completed! Winner has been determined.")?;
+        writeln!(f, "Pot: {}", self.pot)?;
+        writeln!(f, "Winning ticket: #{} ({})", self.winner_index, self.winning_ticket)?;
+        if let Some(winner) = &self.winner {
+            writeln!(f, "Winner: {}", winner)?;
+        }
+        write!(f, "Transaction: {}", self.signature)
+    }
+}
+
+#[derive(Serialize)]
+pub struct DrawAndSettleResult {
+    pub draw: DrawResult,
+    pub settle: SettleResult,
+}
+
+impl fmt::Display for DrawAndSettleResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.draw)?;
+        writeln!(f)?;
+        write!(f, "{}", self.settle)
+    }
+}
+
+#[derive(Serialize)]
+pub struct RandomnessStatus {
+    #[serde(serialize_with = "display")]
+    pub randomness_account: Pubkey,
+    pub status: String,
+}
+
+impl fmt::Display for RandomnessStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Randomness account: {}", self.randomness_account)?;
+        write!(f, "Status: {}", self.status)
+    }
+}
+
+#[derive(Serialize)]
+pub struct RevealResult {
+    #[serde(serialize_with = "display")]
+    pub randomness_account: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub oracle: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub queue: Pubkey,
+    pub signature: String,
+}
+
+impl fmt::Display for RevealResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "✓ Randomness revealed!")?;
+        writeln!(f, "Randomness account: {}", self.randomness_account)?;
+        writeln!(f, "Oracle: {}", self.oracle)?;
+        write!(f, "Transaction: {}", self.signature)
+    }
+}
+
+/// An account closed and its rent recovered
+#[derive(Serialize)]
+pub struct CloseResult {
+    #[serde(serialize_with = "display")]
+    pub account: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub rent_recipient: Pubkey,
+    pub signature: String,
+}
+
+impl fmt::Display for CloseResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "✓ Account {} closed!", self.account)?;
+        writeln!(f, "Transaction: {}", self.signature)?;
+        write!(f, "Rent recovered to: {}", self.rent_recipient)
+    }
+}
+
+/// A transaction printed instead of sent by `--sign-only` / `--dump-transaction`
+#[derive(Serialize)]
+pub struct OfflineTransaction {
+    /// Bincode-serialized transaction, base64 encoded
+    pub transaction: String,
+    #[serde(serialize_with = "display")]
+    pub blockhash: Hash,
+    pub signers: Vec<SignerStatus>,
+}
+
+#[derive(Serialize)]
+pub struct SignerStatus {
+    #[serde(serialize_with = "display")]
+    pub pubkey: Pubkey,
+    pub signed: bool,
+}
+
+impl OfflineTransaction {
+    pub fn new(transaction: &Transaction) -> Result<Self> {
+        let missing = crate::transaction::missing_signers(transaction);
+        let num_signers = transaction.message.header.num_required_signatures as usize;
+        Ok(Self {
+            transaction: crate::transaction::encode(transaction)?,
+            blockhash: transaction.message.recent_blockhash,
+            signers: transaction.message.account_keys[..num_signers]
+                .iter()
+                .map(|pubkey| SignerStatus {
+                    pubkey: *pubkey,
+                    signed: !missing.contains(pubkey),
+                })
+                .collect(),
+        })
+    }
+}
+
+impl fmt::Display for OfflineTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Transaction (base64):\n{}\n", self.transaction)?;
+        writeln!(f, "Blockhash: {}", self.blockhash)?;
+        writeln!(f, "Signers:")?;
+        for signer in &self.signers {
+            writeln!(f, "  {} {}", signer.pubkey, if signer.signed { "signed" } else { "missing" })?;
+        }
+        write!(f, "\nBroadcast with: open-lotto submit --transaction <BASE64> [--transaction <BASE64>...]")
+    }
+}
+
+/// A transaction assembled from offline signatures and broadcast by `submit`
+#[derive(Serialize)]
+pub struct SubmitResult {
+    pub signature: String,
+    #[serde(serialize_with = "display_vec")]
+    pub signers: Vec<Pubkey>,
+    pub slot: u64,
+    /// Confirmation status reached: processed, confirmed or finalized
+    pub status: String,
+    pub broadcasts: usize,
+}
+
+impl fmt::Display for SubmitResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "✓ Transaction submitted!")?;
+        writeln!(f, "Transaction: {}", self.signature)?;
+        writeln!(f, "Status: {} in slot {} ({} broadcast(s))", self.status, self.slot, self.broadcasts)?;
+        let signers: Vec<String> = self.signers.iter().map(Pubkey::to_string).collect();
+        write!(f, "Signed by: {}", signers.join(", "))
+    }
+}
+
+/// Transactions `--dry-run` simulated instead of sending
+#[derive(Serialize)]
+pub struct DryRun {
+    pub transactions: Vec<SimulatedTransaction>,
+}
+
+impl fmt::Display for DryRun {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Dry run, nothing was sent")?;
+        for (i, transaction) in self.transactions.iter().enumerate() {
+            write!(f, "\n\n[{}] {}", i + 1, transaction)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+pub struct SimulatedTransaction {
+    pub label: String,
+    /// Why the transaction would fail; `None` if it would succeed
+    pub error: Option<String>,
+    pub units_consumed: Option<u64>,
+    pub balances: Vec<BalanceChange>,
+    pub logs: Vec<String>,
+    /// Why the transaction could not be simulated
+    pub skipped: Option<String>,
+}
+
+impl fmt::Display for SimulatedTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.label)?;
+        if let Some(reason) = &self.skipped {
+            return write!(f, "\n  Not simulated: {}", reason);
+        }
+        match &self.error {
+            Some(error) => write!(f, "\n  Would fail: {}", error.replace('\n', "\n  "))?,
+            None => write!(f, "\n  Would succeed")?,
+        }
+        if let Some(units) = self.units_consumed {
+            write!(f, "\n  Compute units: {}", units)?;
+        }
+        if !self.balances.is_empty() {
+            write!(f, "\n  Balances:")?;
+            for balance in &self.balances {
+                write!(f, "\n    {}", balance)?;
+            }
+        }
+        if !self.logs.is_empty() {
+            write!(f, "\n  Logs:")?;
+            for line in &self.logs {
+                write!(f, "\n    {}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Balance of an account before and after a simulated transaction
+#[derive(Serialize)]
+pub struct BalanceChange {
+    pub label: String,
+    #[serde(serialize_with = "display")]
+    pub address: Pubkey,
+    pub lamports_before: u64,
+    pub lamports_after: u64,
+    /// Set for SPL token accounts
+    pub tokens_before: Option<u64>,
+    pub tokens_after: Option<u64>,
+}
+
+impl fmt::Display for BalanceChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:<12} {:<44} {} -> {} lamports",
+            self.label, self.address, self.lamports_before, self.lamports_after
+        )?;
+        if self.tokens_before.is_some() || self.tokens_after.is_some() {
+            let tokens = |amount: Option<u64>| amount.map_or("-".to_string(), |amount| amount.to_string());
+            write!(f, ", {} -> {} tokens", tokens(self.tokens_before), tokens(self.tokens_after))?;
+        }
+        Ok(())
+    }
+}
+
+/// Randomness accounts closed by `close-randomness`
+#[derive(Serialize)]
+pub struct CloseRandomnessResult {
+    pub closed: Vec<CloseResult>,
+}
+
+impl fmt::Display for CloseRandomnessResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, closed) in self.closed.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", closed)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+pub struct SetWagerResult {
+    #[serde(serialize_with = "display")]
+    pub pot_manager: Pubkey,
+    pub wager: u64,
+    pub signature: String,
+}
+
+impl fmt::Display for SetWagerResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "✓ Wager updated to {} lamports!", self.wager)?;
+        write!(f, "Transaction: {}", self.signature)
+    }
+}
+
+#[derive(Serialize)]
+pub struct SetBountyResult {
+    #[serde(serialize_with = "display")]
+    pub pot_manager: Pubkey,
+    /// Bounty in token base units
+    pub bounty: u64,
+    /// Allowance approved in the same transaction, if any
+    pub allowance: Option<u64>,
+    #[serde(serialize_with = "display")]
+    pub treasury: Pubkey,
+    /// PDA the treasury must delegate an allowance to for bounties to be paid
+    #[serde(serialize_with = "display")]
+    pub bounty_authority: Pubkey,
+    pub signature: String,
+}
+
+impl fmt::Display for SetBountyResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "✓ Crank bounty updated to {} (token base units)!", self.bounty)?;
+        if let Some(allowance) = self.allowance {
+            writeln!(f, "Treasury {} allowance for {}: {}", self.treasury, self.bounty_authority, allowance)?;
+        } else {
+            writeln!(
+                f,
+                "Paid from the treasury up to the allowance approved with `set-bounty --allowance <N>`"
+            )?;
+        }
+        write!(f, "Transaction: {}", self.signature)
+    }
+}
+
+#[derive(Serialize)]
+pub struct SetAutoPayoutResult {
+    #[serde(serialize_with = "display")]
+    pub pot_manager: Pubkey,
+    pub enabled: bool,
+    pub signature: String,
+}
+
+impl fmt::Display for SetAutoPayoutResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.enabled {
+            writeln!(f, "✓ Auto-payout enabled!")?;
+        } else {
+            writeln!(f, "✓ Auto-payout disabled!")?;
+        }
+        write!(f, "Transaction: {}", self.signature)
+    }
+}
+
+/// Holder of a manager's keeper lease
+#[derive(Serialize)]
+pub struct LeaseInfo {
+    #[serde(serialize_with = "display")]
+    pub keeper: Pubkey,
+    /// Last slot the lease holds
+    pub expiry_slot: u64,
+}
+
+impl fmt::Display for LeaseInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} until slot {}", self.keeper, self.expiry_slot)
+    }
+}
+
+#[derive(Serialize)]
+pub struct AcquireLeaseResult {
+    #[serde(serialize_with = "display")]
+    pub pot_manager: Pubkey,
+    pub lease: LeaseInfo,
+    pub signature: String,
+}
+
+impl fmt::Display for AcquireLeaseResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "✓ Keeper lease held by {}", self.lease)?;
+        write!(f, "Transaction: {}", self.signature)
+    }
+}
+
+#[derive(Serialize)]
+pub struct SetLeaseKeepersResult {
+    #[serde(serialize_with = "display")]
+    pub pot_manager: Pubkey,
+    /// Keepers besides the authority allowed to acquire the lease
+    #[serde(serialize_with = "display_vec")]
+    pub keepers: Vec<Pubkey>,
+    pub signature: String,
+}
+
+impl fmt::Display for SetLeaseKeepersResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.keepers.is_empty() {
+            writeln!(f, "✓ Only the manager authority may acquire the keeper lease")?;
+        } else {
+            let keepers: Vec<String> = self.keepers.iter().map(Pubkey::to_string).collect();
+            writeln!(f, "✓ Keepers allowed to acquire the lease: {}", keepers.join(", "))?;
+        }
+        write!(f, "Transaction: {}", self.signature)
+    }
+}
+
+#[derive(Serialize)]
+pub struct WithdrawWagerResult {
+    #[serde(serialize_with = "display")]
+    pub pot_manager: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub destination: Pubkey,
+    pub amount: u64,
+    /// `None` when there was nothing to withdraw
+    pub signature: Option<String>,
+}
+
+impl fmt::Display for WithdrawWagerResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.signature {
+            None => write!(f, "Nothing to withdraw: no wagers escrowed by this manager"),
+            Some(signature) => {
+                writeln!(f, "✓ Withdrew {} lamports from the wager escrow!", self.amount)?;
+                writeln!(f, "Transaction: {}", signature)?;
+                write!(f, "Sent to: {}", self.destination)
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ListedPotManager {
+    #[serde(serialize_with = "display")]
+    pub address: Pubkey,
+    pub name: Option<String>,
+    pub lamports: u64,
+}
+
+#[derive(Serialize)]
+pub struct ListedPot {
+    #[serde(serialize_with = "display")]
+    pub address: Pubkey,
+    pub total_participants: Option<u64>,
+    pub end_timestamp: Option<u64>,
+    pub lamports: u64,
+}
+
+#[derive(Serialize)]
+pub struct ListedAccount {
+    #[serde(serialize_with = "display")]
+    pub address: Pubkey,
+    pub lamports: u64,
+    pub data_len: usize,
+}
+
+#[derive(Serialize)]
+pub struct ProgramAccounts {
+    #[serde(serialize_with = "display")]
+    pub program_id: Pubkey,
+    pub pot_managers: Vec<ListedPotManager>,
+    pub pots: Vec<ListedPot>,
+    pub tickets: Vec<ListedAccount>,
+    pub unknown: Vec<ListedAccount>,
+    pub total_accounts: usize,
+    pub total_lamports: u64,
+}
+
+impl fmt::Display for ProgramAccounts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.total_accounts == 0 {
+            return write!(f, "No accounts found for program {}", self.program_id);
+        }
+
+        writeln!(f, "=== Pot Managers ({}) ===", self.pot_managers.len())?;
+        for manager in &self.pot_managers {
+            let name = manager.name.as_deref().unwrap_or("unknown");
+            writeln!(f, "  {} (name: {}, {} lamports)", manager.address, name, manager.lamports)?;
+        }
+
+        writeln!(f, "\n=== Pots ({}) ===", self.pots.len())?;
+        for pot in &self.pots {
+            writeln!(
+                f,
+                "  {} (participants: {}, end_ts: {}, {} lamports)",
+                pot.address,
+                pot.total_participants.unwrap_or(0),
+                pot.end_timestamp.unwrap_or(0),
+                pot.lamports
+            )?;
+        }
+
+        writeln!(f, "\n=== Tickets ({}) ===", self.tickets.len())?;
+        for ticket in &self.tickets {
+            writeln!(f, "  {} ({} lamports)", ticket.address, ticket.lamports)?;
+        }
+
+        if !self.unknown.is_empty() {
+            writeln!(f, "\n=== Unknown ({}) ===", self.unknown.len())?;
+            for account in &self.unknown {
+                writeln!(f, "  {} ({} lamports, {} bytes)", account.address, account.lamports, account.data_len)?;
+            }
+        }
+
+        writeln!(f, "\n=== Summary ===")?;
+        writeln!(f, "Total accounts: {}", self.total_accounts)?;
+        write!(
+            f,
+            "Total lamports: {} ({:.4} SOL)",
+            self.total_lamports,
+            self.total_lamports as f64 / LAMPORTS_PER_SOL
+        )
+    }
+}
+
+#[derive(Serialize)]
+pub struct PotSummary {
+    #[serde(serialize_with = "display")]
+    pub address: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub status: PotStatus,
+    pub end_timestamp: u64,
+    pub total_participants: u64,
+}
+
+#[derive(Serialize)]
+pub struct ManagerInfo {
+    #[serde(serialize_with = "display")]
+    pub address: Pubkey,
+    pub name: String,
+    #[serde(serialize_with = "display")]
+    pub authority: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub token_mint: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub treasury: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub current_pot: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub next_pot: Pubkey,
+    pub current_pot_end_timestamp: u64,
+    pub next_pot_end_timestamp: u64,
+    pub wager: u64,
+    pub wager_balance: u64,
+    /// Bounty in token base units paid per draw and per settle
+    pub crank_bounty: u64,
+    /// Whether keepers push settled prizes to winners
+    pub auto_payout: bool,
+    pub last_random_number: u64,
+    /// `None` if no keeper holds the lease
+    pub keeper_lease: Option<LeaseInfo>,
+    /// Keepers besides the authority allowed to acquire the lease
+    #[serde(serialize_with = "display_vec")]
+    pub lease_keepers: Vec<Pubkey>,
+    pub pots: Vec<PotSummary>,
+}
+
+impl fmt::Display for ManagerInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Pot Manager: {} ({})", self.address, self.name)?;
+        writeln!(f, "Authority: {}", self.authority)?;
+        writeln!(f, "Token Mint: {}", self.token_mint)?;
+        writeln!(f, "Treasury: {}", self.treasury)?;
+        writeln!(f, "Current Pot: {} (ends at {})", self.current_pot, self.current_pot_end_timestamp)?;
+        writeln!(f, "Next Pot: {} (ends at {})", self.next_pot, self.next_pot_end_timestamp)?;
+        writeln!(f, "Wager per draw: {} lamports", self.wager)?;
+        writeln!(f, "Escrowed wagers: {} lamports", self.wager_balance)?;
+        writeln!(f, "Crank bounty: {} (token base units)", self.crank_bounty)?;
+        writeln!(f, "Auto-payout: {}", if self.auto_payout { "on" } else { "off" })?;
+        writeln!(f, "Last random number: {}", self.last_random_number)?;
+        match &self.keeper_lease {
+            Some(lease) => writeln!(f, "Keeper lease: {}", lease)?,
+            None => writeln!(f, "Keeper lease: none")?,
+        }
+        if !self.lease_keepers.is_empty() {
+            let keepers: Vec<String> = self.lease_keepers.iter().map(Pubkey::to_string).collect();
+            writeln!(f, "Lease keepers: {}", keepers.join(", "))?;
+        }
+        write!(f, "\n=== Pots ({}) ===", self.pots.len())?;
+        for pot in &self.pots {
+            write!(
+                f,
+                "\n  {} ({}, participants: {}, end_ts: {})",
+                pot.address, pot.status, pot.total_participants, pot.end_timestamp
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+pub struct WinnerInfo {
+    pub index: u64,
+    #[serde(serialize_with = "display")]
+    pub ticket: Pubkey,
+    /// `None` if the winning ticket account has been closed
+    #[serde(serialize_with = "display_option")]
+    pub participant: Option<Pubkey>,
+}
+
+impl fmt::Display for WinnerInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{} (ticket {}", self.index, self.ticket)?;
+        match &self.participant {
+            Some(participant) => write!(f, ", owner {})", participant),
+            None => write!(f, ", closed)"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PotInfo {
+    #[serde(serialize_with = "display")]
+    pub address: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub pot_manager: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub status: PotStatus,
+    pub start_timestamp: u64,
+    pub end_timestamp: u64,
+    pub total_participants: u64,
+    /// Prize in token base units
+    pub prize: u64,
+    /// Crank bounties paid out of the prize pool, in token base units
+    pub bounties_paid: u64,
+    /// Whether the prize has been claimed or paid out
+    pub prize_paid: bool,
+    pub wager: u64,
+    #[serde(serialize_with = "display_option")]
+    pub randomness_account: Option<Pubkey>,
+    pub winner: Option<WinnerInfo>,
+}
+
+impl fmt::Display for PotInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Pot: {}", self.address)?;
+        writeln!(f, "Manager: {}", self.pot_manager)?;
+        writeln!(f, "Status: {}", self.status)?;
+        writeln!(f, "Start: {}", self.start_timestamp)?;
+        writeln!(f, "End: {}", self.end_timestamp)?;
+        writeln!(f, "Participants: {}", self.total_participants)?;
+        writeln!(f, "Prize: {} (token base units)", self.prize)?;
+        writeln!(f, "Crank bounties paid: {} (token base units)", self.bounties_paid)?;
+        writeln!(f, "Prize paid: {}", if self.prize_paid { "yes" } else { "no" })?;
+        writeln!(f, "Oracle wager: {} lamports", self.wager)?;
+        match &self.randomness_account {
+            Some(randomness) => writeln!(f, "Randomness account: {}", randomness)?,
+            None => writeln!(f, "Randomness account: none")?,
+        }
+        match &self.winner {
+            Some(winner) => write!(f, "Winner: {}", winner),
+            None => write!(f, "Winner: not drawn yet"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ListedTicket {
+    #[serde(serialize_with = "display")]
+    pub address: Pubkey,
+    /// Known only when listing by pot, since tickets don't record their pot
+    #[serde(serialize_with = "display_option")]
+    pub pot: Option<Pubkey>,
+    pub index: u64,
+    #[serde(serialize_with = "display")]
+    pub participant: Pubkey,
+}
+
+#[derive(Serialize)]
+pub struct TicketList {
+    pub tickets: Vec<ListedTicket>,
+}
+
+impl fmt::Display for TicketList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "=== Tickets ({}) ===", self.tickets.len())?;
+        for ticket in &self.tickets {
+            write!(f, "\n  #{} {} (owner: {}", ticket.index, ticket.address, ticket.participant)?;
+            if let Some(pot) = &ticket.pot {
+                write!(f, ", pot: {}", pot)?;
+            }
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+pub struct WinnerResult {
+    #[serde(serialize_with = "display")]
+    pub pot: Pubkey,
+    pub prize: u64,
+    #[serde(flatten)]
+    pub winner: WinnerInfo,
+}
+
+impl fmt::Display for WinnerResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Pot: {}", self.pot)?;
+        writeln!(f, "Winner: {}", self.winner)?;
+        write!(f, "Prize: {} (token base units)", self.prize)
+    }
+}
+
+#[derive(Serialize)]
+pub struct BuyTicketResult {
+    #[serde(serialize_with = "display")]
+    pub pot: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub token_account: Pubkey,
+    /// Total paid in token base units (prize pool share + fee)
+    pub cost: u64,
+    pub tickets: Vec<ListedTicket>,
+    pub signatures: Vec<String>,
+}
+
+impl fmt::Display for BuyTicketResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "✓ Bought {} ticket(s)!", self.tickets.len())?;
+        writeln!(f, "Pot: {}", self.pot)?;
+        writeln!(f, "Paid: {} (token base units) from {}", self.cost, self.token_account)?;
+        for ticket in &self.tickets {
+            writeln!(f, "  #{} {}", ticket.index, ticket.address)?;
+        }
+        write!(f, "Transactions: {}", self.signatures.join(", "))
+    }
+}
+
+#[derive(Serialize)]
+pub struct ClaimResult {
+    #[serde(serialize_with = "display")]
+    pub pot: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub ticket: Pubkey,
+    pub index: u64,
+    /// Prize in token base units
+    pub prize: u64,
+    #[serde(serialize_with = "display")]
+    pub token_account: Pubkey,
+    pub signature: String,
+}
+
+impl fmt::Display for ClaimResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "✓ Prize claimed!")?;
+        writeln!(f, "Winning ticket: #{} ({})", self.index, self.ticket)?;
+        writeln!(f, "Prize: {} (token base units) sent to {}", self.prize, self.token_account)?;
+        write!(f, "Transaction: {}", self.signature)
+    }
+}
+
+#[derive(Serialize)]
+pub struct PayoutResult {
+    #[serde(serialize_with = "display")]
+    pub pot: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub winner: Pubkey,
+    /// Prize in token base units
+    pub prize: u64,
+    #[serde(serialize_with = "display")]
+    pub token_account: Pubkey,
+    pub signature: String,
+}
+
+impl fmt::Display for PayoutResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "✓ Prize paid out!")?;
+        writeln!(f, "Winner: {}", self.winner)?;
+        writeln!(f, "Prize: {} (token base units) sent to {}", self.prize, self.token_account)?;
+        write!(f, "Transaction: {}", self.signature)
+    }
+}
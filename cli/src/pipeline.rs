@@ -0,0 +1,247 @@
+//! Resumable draw pipeline
+//!
+//! `draw` walks a pot through randomnessInit and then randomnessCommit + draw_lottery,
+//! which share one transaction. The randomness keypair and the last completed step are
+//! written to `~/.config/open-lotto/draws/<pot>.json` before every transaction, so a draw
+//! that dies half way can be finished with `resume --pot` instead of orphaning the
+//! rent-paying randomness account. The record is removed once draw_lottery lands.
+//!
+//! A `--dry-run` draw saves nothing and sends nothing; it simulates randomnessInit only,
+//! as the commit needs the account that transaction would create.
+
+use anyhow::{anyhow, Context, Result};
+use open_lotto_client::{accounts, OpenLotto};
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use std::path::PathBuf;
+
+use crate::cluster::Network;
+use crate::keeper::pubkey_string;
+use crate::output::{DrawResult, DryRun, SimulatedTransaction};
+use crate::signer::Wallet;
+use crate::switchboard;
+use crate::transaction;
+
+/// Last completed step of a draw
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DrawStage {
+    /// Keypair saved, randomnessInit may or may not have landed
+    KeypairSaved,
+    /// randomnessInit landed, commit + draw_lottery still to send
+    RandomnessCreated,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DrawRecord {
+    #[serde(with = "pubkey_string")]
+    pub pot: Pubkey,
+    pub stage: DrawStage,
+    pub randomness_keypair: Vec<u8>,
+}
+
+impl DrawRecord {
+    fn new(pot: Pubkey, randomness_keypair: &Keypair) -> Self {
+        Self {
+            pot,
+            stage: DrawStage::KeypairSaved,
+            randomness_keypair: randomness_keypair.to_bytes().to_vec(),
+        }
+    }
+
+    /// Directory holding one record per in-flight draw: ~/.config/open-lotto/draws
+    fn dir() -> Result<PathBuf> {
+        let home = dirs::home_dir().ok_or_else(|| anyhow!("Unable to find home directory"))?;
+        Ok(home.join(".config").join("open-lotto").join("draws"))
+    }
+
+    fn path(pot: &Pubkey) -> Result<PathBuf> {
+        Ok(Self::dir()?.join(format!("{}.json", pot)))
+    }
+
+    /// The saved draw for `pot`, if one was interrupted
+    pub fn load(pot: &Pubkey) -> Result<Option<Self>> {
+        let path = Self::path(pot)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read draw record {}", path.display()))?;
+        let record = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse draw record {}", path.display()))?;
+        Ok(Some(record))
+    }
+
+    /// Every saved draw, in no particular order
+    pub fn load_all() -> Result<Vec<Self>> {
+        let dir = Self::dir()?;
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut records = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let contents = std::fs::read_to_string(&path)?;
+            records.push(
+                serde_json::from_str(&contents)
+                    .with_context(|| format!("Failed to parse draw record {}", path.display()))?,
+            );
+        }
+        Ok(records)
+    }
+
+    /// Write the record atomically (temp file + rename)
+    fn save(&self) -> Result<()> {
+        let path = Self::path(&self.pot)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&tmp, &path)
+            .with_context(|| format!("Failed to write draw record {}", path.display()))
+    }
+
+    pub fn remove(&self) -> Result<()> {
+        let path = Self::path(&self.pot)?;
+        std::fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove draw record {}", path.display()))
+    }
+
+    pub fn randomness_keypair(&self) -> Result<Keypair> {
+        Keypair::try_from(self.randomness_keypair.as_slice())
+            .map_err(|e| anyhow!("Invalid randomness keypair in draw record for pot {}: {}", self.pot, e))
+    }
+}
+
+/// Start a new draw for `pot`, refusing if an interrupted one is still on disk
+pub async fn draw(
+    rpc_client: &RpcClient,
+    program: &OpenLotto,
+    payer: &Wallet,
+    pot: &Pubkey,
+    network: &Network,
+) -> Result<DrawResult> {
+    if DrawRecord::load(pot)?.is_some() {
+        return Err(anyhow!(
+            "An interrupted draw is saved for pot {}; finish it with `open-lotto resume --pot {}`",
+            pot,
+            pot
+        ));
+    }
+
+    // Persist the keypair before paying rent for it, so it can never be orphaned
+    let randomness_keypair = Keypair::new();
+    let record = DrawRecord::new(*pot, &randomness_keypair);
+    record.save()?;
+    eprintln!("Creating randomness account: {}", randomness_keypair.pubkey());
+
+    advance(rpc_client, program, payer, network, record).await
+}
+
+/// Finish the draw saved for `pot` from its last completed step
+pub async fn resume(
+    rpc_client: &RpcClient,
+    program: &OpenLotto,
+    payer: &Wallet,
+    pot: &Pubkey,
+    network: &Network,
+) -> Result<DrawResult> {
+    let record = DrawRecord::load(pot)?.ok_or_else(|| anyhow!("No interrupted draw saved for pot {}", pot))?;
+    eprintln!("Resuming draw for pot {} after stage {:?}", pot, record.stage);
+    advance(rpc_client, program, payer, network, record).await
+}
+
+/// Simulate the transactions `draw` would send for `pot`
+pub async fn dry_run(
+    rpc_client: &RpcClient,
+    program: &OpenLotto,
+    payer: &Wallet,
+    pot: &Pubkey,
+    network: &Network,
+) -> Result<DryRun> {
+    let drawn = accounts::fetch_pot(rpc_client, pot).await?;
+    if drawn.randomness_account != Pubkey::default() {
+        return Err(anyhow!("Pot {} was already drawn with {}", pot, drawn.randomness_account));
+    }
+    let manager = accounts::fetch_pot_manager(rpc_client, &drawn.pot_manager).await?;
+
+    // A throwaway keypair: nothing is created, so there is nothing to save
+    let randomness = Keypair::new().pubkey();
+    let (init_ix, _) = switchboard::randomness_init_instruction(rpc_client, payer, &randomness, network).await?;
+    let instructions = [init_ix];
+    let watched = crate::dry_run_accounts(program, payer, &instructions);
+    let init = transaction::simulate(rpc_client, payer, "randomnessInit", &instructions, &watched).await?;
+
+    let commit_and_draw = SimulatedTransaction {
+        label: "randomnessCommit + draw_lottery".to_string(),
+        error: None,
+        units_consumed: None,
+        balances: Vec::new(),
+        logs: Vec::new(),
+        skipped: Some(format!(
+            "the commit needs the randomness account randomnessInit creates; draw_lottery would move the manager's {} lamport wager into the wager escrow",
+            manager.wager
+        )),
+    };
+
+    Ok(DryRun {
+        transactions: vec![init, commit_and_draw],
+    })
+}
+
+async fn advance(
+    rpc_client: &RpcClient,
+    program: &OpenLotto,
+    payer: &Wallet,
+    network: &Network,
+    mut record: DrawRecord,
+) -> Result<DrawResult> {
+    let randomness_keypair = record.randomness_keypair()?;
+    let randomness = randomness_keypair.pubkey();
+
+    if record.stage == DrawStage::KeypairSaved {
+        if rpc_client.get_account(&randomness).await.is_err() {
+            switchboard::create_randomness(rpc_client, payer, &randomness_keypair, network).await?;
+        }
+        record.stage = DrawStage::RandomnessCreated;
+        record.save()?;
+    }
+
+    let pot = accounts::fetch_pot(rpc_client, &record.pot).await?;
+    let signature = if pot.randomness_account == randomness {
+        // The commit + draw transaction landed before the record was removed
+        eprintln!("draw_lottery already landed for pot {}", record.pot);
+        String::new()
+    } else if pot.randomness_account != Pubkey::default() {
+        return Err(anyhow!(
+            "Pot {} was drawn with another randomness account ({}); reclaim {} with `open-lotto close-randomness --account {}`",
+            record.pot,
+            pot.randomness_account,
+            randomness,
+            randomness
+        ));
+    } else {
+        let (signature, _) =
+            crate::call_commit_and_draw(rpc_client, program, payer, &record.pot, &randomness, network).await?;
+        eprintln!("Commit + draw transaction: {}", signature);
+        signature
+    };
+    record.remove()?;
+
+    let committed = switchboard::accounts::randomness(&rpc_client.get_account_data(&randomness).await?)?;
+    Ok(DrawResult {
+        pot: record.pot,
+        randomness_account: randomness,
+        oracle: Pubkey::new_from_array(committed.oracle.to_bytes()),
+        commit_slot: committed.seed_slot,
+        signature,
+    })
+}
@@ -0,0 +1,196 @@
+//! Signer resolution
+//!
+//! `--keypair` and `--fee-payer` take the same signer URIs as the Solana CLI: a keypair
+//! file, `usb://ledger[?key=0/0]`, `prompt://` for a seed phrase, or `stdin`. The
+//! authority signs the lottery instructions; the fee payer, when given, pays transaction
+//! fees instead, so a keeper host only needs a hot fee wallet.
+//!
+//! With `--sign-only` or `--dump-transaction` either may also be a bare pubkey, standing
+//! for a signer whose signature is collected elsewhere.
+//!
+//! `usb://` wallets need the `ledger` feature, which resolves every URI through the Solana
+//! CLI's own code but needs the system libudev to build.
+
+use anyhow::{anyhow, Result};
+#[cfg(feature = "ledger")]
+use solana_clap_utils::keypair::{signer_from_path_with_config, SignerFromPathConfig};
+#[cfg(feature = "ledger")]
+use solana_remote_wallet::remote_wallet::RemoteWalletManager;
+#[cfg(not(feature = "ledger"))]
+use solana_sdk::signature::{keypair_from_seed_phrase_and_passphrase, read_keypair, read_keypair_file, NullSigner};
+use solana_sdk::{pubkey::Pubkey, signature::Signer};
+#[cfg(feature = "ledger")]
+use std::rc::Rc;
+#[cfg(not(feature = "ledger"))]
+use std::str::FromStr;
+
+use crate::expand_tilde;
+
+/// The signers a command acts with
+pub struct Wallet {
+    authority: Box<dyn Signer>,
+    fee_payer: Option<Box<dyn Signer>>,
+}
+
+impl Wallet {
+    /// Resolve the authority and optional fee payer from signer URIs; `offline` accepts
+    /// bare pubkeys for signers that will sign elsewhere
+    pub fn resolve(authority: &str, fee_payer: Option<&str>, offline: bool) -> Result<Self> {
+        let mut resolver = Resolver::new(offline);
+        let authority = resolver.resolve(authority, "keypair")?;
+        let fee_payer = fee_payer
+            .map(|source| resolver.resolve(source, "fee-payer"))
+            .transpose()?;
+        Ok(Self::new(authority, fee_payer))
+    }
+
+    pub fn new(authority: Box<dyn Signer>, fee_payer: Option<Box<dyn Signer>>) -> Self {
+        // A fee payer that is the authority adds nothing
+        let fee_payer = fee_payer.filter(|payer| payer.pubkey() != authority.pubkey());
+        Self { authority, fee_payer }
+    }
+
+    /// The authority's address, used wherever an instruction names the wallet
+    pub fn pubkey(&self) -> Pubkey {
+        self.authority.pubkey()
+    }
+
+    pub fn authority(&self) -> &dyn Signer {
+        self.authority.as_ref()
+    }
+
+    /// Signer paying transaction fees: `--fee-payer`, else the authority
+    pub fn fee_payer(&self) -> &dyn Signer {
+        self.fee_payer.as_deref().unwrap_or(self.authority.as_ref())
+    }
+
+    /// Fee payer, authority and `extra`, without duplicates
+    pub fn signers<'a>(&'a self, extra: &[&'a dyn Signer]) -> Vec<&'a dyn Signer> {
+        let mut signers: Vec<&dyn Signer> = vec![self.fee_payer()];
+        for signer in std::iter::once(self.authority()).chain(extra.iter().copied()) {
+            if !signers.iter().any(|s| s.pubkey() == signer.pubkey()) {
+                signers.push(signer);
+            }
+        }
+        signers
+    }
+}
+
+/// Turns signer URIs into signers, sharing one USB connection between them
+struct Resolver {
+    offline: bool,
+    #[cfg(feature = "ledger")]
+    wallet_manager: Option<Rc<RemoteWalletManager>>,
+}
+
+impl Resolver {
+    fn new(offline: bool) -> Self {
+        Self {
+            offline,
+            #[cfg(feature = "ledger")]
+            wallet_manager: None,
+        }
+    }
+
+    /// Resolve one signer URI; `name` is used in prompts and errors
+    #[cfg(feature = "ledger")]
+    fn resolve(&mut self, source: &str, name: &str) -> Result<Box<dyn Signer>> {
+        let source = expand_tilde(source);
+        let config = SignerFromPathConfig {
+            allow_null_signer: self.offline,
+        };
+        signer_from_path_with_config(&clap2::ArgMatches::default(), &source, name, &mut self.wallet_manager, &config)
+            .map_err(|e| anyhow!("Failed to load {} signer from {}: {}", name, source, e))
+    }
+
+    /// Resolve one signer URI; `name` is used in prompts and errors
+    #[cfg(not(feature = "ledger"))]
+    fn resolve(&mut self, source: &str, name: &str) -> Result<Box<dyn Signer>> {
+        let source = expand_tilde(source);
+        if self.offline {
+            if let Ok(pubkey) = Pubkey::from_str(&source) {
+                return Ok(Box::new(NullSigner::new(&pubkey)));
+            }
+        }
+
+        let signer: Box<dyn Signer> = match source.as_str() {
+            "stdin" | "stdin:" | "stdin://" | "-" => Box::new(
+                read_keypair(&mut std::io::stdin())
+                    .map_err(|e| anyhow!("Failed to read {} keypair from stdin: {}", name, e))?,
+            ),
+            "prompt:" | "prompt://" => Box::new(prompt_keypair(name)?),
+            uri if uri.starts_with("usb:") => {
+                return Err(anyhow!(
+                    "{} is a hardware wallet; build the CLI with `--features ledger` to use it",
+                    uri
+                ));
+            }
+            uri if uri.contains("://") && !uri.starts_with("file://") => {
+                return Err(anyhow!("Unsupported {} signer URI {}", name, uri));
+            }
+            path => {
+                let path = path.strip_prefix("file://").unwrap_or(path);
+                Box::new(
+                    read_keypair_file(path)
+                        .map_err(|e| anyhow!("Failed to load {} signer from {}: {}", name, path, e))?,
+                )
+            }
+        };
+        Ok(signer)
+    }
+}
+
+/// Derive a keypair from a seed phrase and optional passphrase typed at the terminal
+#[cfg(not(feature = "ledger"))]
+fn prompt_keypair(name: &str) -> Result<solana_sdk::signature::Keypair> {
+    let seed_phrase = rpassword::prompt_password(format!("[{}] seed phrase: ", name))?;
+    let passphrase = rpassword::prompt_password(
+        "If this seed phrase has an associated passphrase, enter it now. Otherwise, press ENTER to continue: ",
+    )?;
+    keypair_from_seed_phrase_and_passphrase(seed_phrase.trim(), &passphrase)
+        .map_err(|e| anyhow!("Invalid {} seed phrase: {}", name, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::Keypair;
+
+    #[test]
+    fn fee_payer_signs_first_and_defaults_to_authority() {
+        let authority = Keypair::new();
+        let authority_pubkey = authority.pubkey();
+        let wallet = Wallet::new(Box::new(authority), None);
+        assert_eq!(wallet.fee_payer().pubkey(), authority_pubkey);
+
+        let fee_payer = Keypair::new();
+        let fee_payer_pubkey = fee_payer.pubkey();
+        let wallet = Wallet::new(Box::new(Keypair::new()), Some(Box::new(fee_payer)));
+        assert_eq!(wallet.fee_payer().pubkey(), fee_payer_pubkey);
+        assert_ne!(wallet.pubkey(), fee_payer_pubkey);
+
+        let extra = Keypair::new();
+        let signers: Vec<Pubkey> = wallet.signers(&[&extra, wallet.authority()]).iter().map(|s| s.pubkey()).collect();
+        assert_eq!(signers, vec![fee_payer_pubkey, wallet.pubkey(), extra.pubkey()]);
+    }
+
+    #[test]
+    fn resolves_keypair_files() {
+        let keypair = Keypair::new();
+        let path = std::env::temp_dir().join(format!("open-lotto-signer-{}.json", keypair.pubkey()));
+        solana_sdk::signature::write_keypair_file(&keypair, &path).unwrap();
+
+        let wallet = Wallet::resolve(path.to_str().unwrap(), None, false).unwrap();
+        assert_eq!(wallet.pubkey(), keypair.pubkey());
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(Wallet::resolve("/nonexistent/keypair.json", None, false).is_err());
+    }
+
+    #[test]
+    fn bare_pubkeys_only_sign_offline() {
+        let pubkey = Pubkey::new_unique().to_string();
+        assert!(Wallet::resolve(&pubkey, None, false).is_err());
+        assert_eq!(Wallet::resolve(&pubkey, None, true).unwrap().pubkey().to_string(), pubkey);
+    }
+}
@@ -0,0 +1,47 @@
+//! The Solana CLI config file, read for the default RPC URL and keypair
+//!
+//! Parsed here rather than through `solana-cli-config`, which pulls in hardware-wallet
+//! support (and with it libudev) whether or not the `ledger` feature is on.
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Deserialize, Debug)]
+pub struct SolanaConfig {
+    pub json_rpc_url: String,
+    pub keypair_path: String,
+}
+
+impl SolanaConfig {
+    /// `~/.config/solana/cli/config.yml`, where `solana config set` writes
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".config").join("solana").join("cli").join("config.yml"))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        serde_yaml::from_str(&contents).map_err(|e| anyhow!("Failed to parse {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_solana_cli_config() {
+        let path = std::env::temp_dir().join(format!("open-lotto-solana-config-{}.yml", std::process::id()));
+        std::fs::write(
+            &path,
+            "---\njson_rpc_url: https://api.devnet.solana.com\nwebsocket_url: ''\nkeypair_path: /home/me/.config/solana/id.json\naddress_labels:\n  '11111111111111111111111111111111': System Program\ncommitment: confirmed\n",
+        )
+        .unwrap();
+
+        let config = SolanaConfig::load(&path).unwrap();
+        assert_eq!(config.json_rpc_url, "https://api.devnet.solana.com");
+        assert_eq!(config.keypair_path, "/home/me/.config/solana/id.json");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
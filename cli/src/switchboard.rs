@@ -5,30 +5,33 @@
 
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
-use solana_client::rpc_client::RpcClient;
+use solana_client::{
+    client_error::ClientError,
+    nonblocking::rpc_client::RpcClient,
+};
+use solana_address_lookup_table_interface as address_lookup_table;
 use solana_sdk::{
-    address_lookup_table,
     instruction::{AccountMeta, Instruction},
-    message::Message,
     pubkey::Pubkey,
     signature::{Keypair, Signer},
-    system_program,
     sysvar,
-    transaction::Transaction,
+    transaction::TransactionError,
 };
+use solana_system_interface::program as system_program;
 use spl_associated_token_account::get_associated_token_address;
+use std::fmt;
 use std::str::FromStr;
 use std::time::Duration;
 
-// Switchboard On-Demand Program ID
-// Mainnet: SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv
-// Devnet: Aio4gaXjXzJNVLtzwtNVmSqGKpANtXhybbkhtAC94ji2
-const SB_ON_DEMAND_PID_DEVNET: &str = "Aio4gaXjXzJNVLtzwtNVmSqGKpANtXhybbkhtAC94ji2";
-const SB_ON_DEMAND_PID_MAINNET: &str = "SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv";
+use crate::cluster::Network;
+use crate::signer::Wallet;
+use crate::transaction;
+
+pub mod accounts;
+pub mod mock_gateway;
+mod oracles;
 
-// Default queue addresses
-const SB_QUEUE_DEVNET: &str = "EYiAmGSdsQTuCw413V5BzaruWuCCSDgTPtBGvLkXHbe7";
-const SB_QUEUE_MAINNET: &str = "A43DyUGA7s8eXPxqEjJY6EBu1KKbNgfxF8h17VAHn13w";
+use accounts::RandomnessState;
 
 // Wrapped SOL mint (same on mainnet and devnet)
 const WRAPPED_SOL_MINT: &str = "So11111111111111111111111111111111111111112";
@@ -37,26 +40,40 @@ const WRAPPED_SOL_MINT: &str = "So11111111111111111111111111111111111111112";
 const SPL_TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
 const SPL_ASSOCIATED_TOKEN_PROGRAM: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
 
-// Switchboard randomness account size
-const RANDOMNESS_ACCOUNT_SIZE: u64 = 512;
-
+#[derive(Serialize)]
 pub struct CommitResult {
+    #[serde(serialize_with = "crate::output::display")]
     pub randomness_account: Pubkey,
     pub commit_slot: u64,
     pub signature: String,
+    #[serde(serialize_with = "crate::output::display")]
     pub oracle: Pubkey,
 }
 
+impl fmt::Display for CommitResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "✓ Randomness account created and committed!")?;
+        writeln!(f, "Randomness account: {}", self.randomness_account)?;
+        writeln!(f, "Oracle: {}", self.oracle)?;
+        writeln!(f, "Commit slot: {}", self.commit_slot)?;
+        writeln!(f, "Transaction: {}", self.signature)?;
+        write!(f, "\nNext step: Wait a few slots, then reveal and settle")
+    }
+}
+
 /// Response from the Switchboard Gateway API for randomness reveal
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct GatewayRevealResponse {
     pub signature: String,      // Base64 encoded signature
     pub recovery_id: u8,        // Recovery ID for secp256k1
     pub value: [u8; 32],        // The random value
 }
 
+/// Gateway API route serving randomness reveals
+const REVEAL_PATH: &str = "/gateway/api/v1/randomness_reveal";
+
 /// Request body for the Gateway API randomness reveal
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 struct GatewayRevealRequest {
     slothash: Vec<u8>,          // Slot hash as byte array
     randomness_key: String,     // Randomness account pubkey as hex
@@ -64,228 +81,147 @@ struct GatewayRevealRequest {
     rpc: String,                // RPC URL
 }
 
-/// Get Switchboard program ID based on network
-pub fn get_sb_program_id(is_devnet: bool) -> Result<Pubkey> {
-    let pid_str = if is_devnet {
-        SB_ON_DEMAND_PID_DEVNET
-    } else {
-        SB_ON_DEMAND_PID_MAINNET
-    };
-    Pubkey::from_str(pid_str).map_err(|e| anyhow!("Invalid program ID: {}", e))
-}
-
-/// Get Switchboard queue based on network
-pub fn get_sb_queue(is_devnet: bool) -> Result<Pubkey> {
-    let queue_str = if is_devnet {
-        SB_QUEUE_DEVNET
-    } else {
-        SB_QUEUE_MAINNET
-    };
-    Pubkey::from_str(queue_str).map_err(|e| anyhow!("Invalid queue address: {}", e))
-}
-
-/// Detect if the RPC URL points to devnet
-fn is_devnet_url(rpc_url: &str) -> bool {
-    rpc_url.contains("devnet")
-}
-
 /// Create a new randomness account and commit to randomness
 pub async fn create_and_commit_randomness(
     rpc_client: &RpcClient,
-    payer: &Keypair,
+    payer: &Wallet,
     randomness_keypair: &Keypair,
-    rpc_url: &str,
+    network: &Network,
 ) -> Result<CommitResult> {
-    // Determine network from RPC URL
-    let is_devnet = is_devnet_url(rpc_url);
+    let recent_slot = create_randomness(rpc_client, payer, randomness_keypair, network).await?;
+    let (signature, oracle) =
+        commit_randomness(rpc_client, payer, &randomness_keypair.pubkey(), network).await?;
+
+    Ok(CommitResult {
+        randomness_account: randomness_keypair.pubkey(),
+        commit_slot: recent_slot,
+        signature,
+        oracle,
+    })
+}
+
+/// Create the randomness account with randomnessInit, returning the slot used for its LUT
+pub async fn create_randomness(
+    rpc_client: &RpcClient,
+    payer: &Wallet,
+    randomness_keypair: &Keypair,
+    network: &Network,
+) -> Result<u64> {
+    let sb_program_id = network.switchboard_program_id;
+    let queue = network.switchboard_queue;
+
+    eprintln!("Network: {}", network.cluster);
+
+    eprintln!("Using Switchboard program: {}", sb_program_id);
+    eprintln!("Using queue: {}", queue);
 
-    let sb_program_id = get_sb_program_id(is_devnet)?;
-    let queue = get_sb_queue(is_devnet)?;
+    let (init_ix, recent_slot) =
+        randomness_init_instruction(rpc_client, payer, &randomness_keypair.pubkey(), network).await?;
 
-    println!("Network: {}", if is_devnet { "devnet" } else { "mainnet" });
+    // Build and send init transaction
+    eprintln!("Sending randomnessInit transaction...");
+    let init_signature = transaction::send(rpc_client, payer, &[randomness_keypair], &[init_ix]).await?;
+    eprintln!("Init transaction: {}", init_signature);
 
-    println!("Using Switchboard program: {}", sb_program_id);
-    println!("Using queue: {}", queue);
+    Ok(recent_slot)
+}
 
+/// randomnessInit for `randomness_account` and the slot used for its LUT
+pub async fn randomness_init_instruction(
+    rpc_client: &RpcClient,
+    payer: &Wallet,
+    randomness_account: &Pubkey,
+    network: &Network,
+) -> Result<(Instruction, u64)> {
     // Get a recent finalized slot for the LUT derivation
-    let recent_slot = rpc_client.get_slot()?;
-    println!("Recent slot: {}", recent_slot);
+    let recent_slot = rpc_client.get_slot().await?;
+    eprintln!("Recent slot: {}", recent_slot);
 
-    // Step 1: Create the randomness account with randomnessInit
     // The Anchor program handles account creation via init constraint
     let init_ix = build_randomness_init_instruction(
-        &sb_program_id,
-        &randomness_keypair.pubkey(),
-        &queue,
+        &network.switchboard_program_id,
+        randomness_account,
+        &network.switchboard_queue,
         &payer.pubkey(),
         recent_slot,
     )?;
+    Ok((init_ix, recent_slot))
+}
 
-    // Build and send init transaction
-    let recent_blockhash = rpc_client.get_latest_blockhash()?;
-    let message = Message::new(&[init_ix], Some(&payer.pubkey()));
-    let transaction = Transaction::new(&[payer, randomness_keypair], message, recent_blockhash);
-
-    println!("Sending randomnessInit transaction...");
-    let init_signature = rpc_client.send_and_confirm_transaction(&transaction)?;
-    println!("Init transaction: {}", init_signature);
-
-    // Step 2: Commit to randomness
-    // Get all oracles and try each one until one succeeds
-    let oracles = get_oracles_from_queue(rpc_client, &queue)?;
-    println!("Found {} oracles in queue, trying each...", oracles.len());
+/// Commit an existing randomness account, returning the signature and the oracle used
+pub async fn commit_randomness(
+    rpc_client: &RpcClient,
+    payer: &Wallet,
+    randomness_account: &Pubkey,
+    network: &Network,
+) -> Result<(String, Pubkey)> {
+    commit_randomness_with(rpc_client, payer, randomness_account, network, &[]).await
+}
 
-    let mut commit_result: Option<(String, Pubkey)> = None;
-    for (idx, oracle) in oracles.iter().enumerate() {
-        println!("Trying oracle {}/{}: {}", idx + 1, oracles.len(), oracle);
+/// Commit an existing randomness account with `followed_by` appended to the same
+/// transaction, returning the signature and the oracle used
+///
+/// Instructions that read the commit (draw_lottery checks `seed_slot == clock.slot - 1`)
+/// only see a fresh seed slot when they execute in the commit's own transaction.
+pub async fn commit_randomness_with(
+    rpc_client: &RpcClient,
+    payer: &Wallet,
+    randomness_account: &Pubkey,
+    network: &Network,
+    followed_by: &[Instruction],
+) -> Result<(String, Pubkey)> {
+    let sb_program_id = network.switchboard_program_id;
+    let queue = network.switchboard_queue;
+
+    // Only oracles with a live gateway and an unexpired key, in weighted random order
+    let oracles = oracles::healthy_oracles(rpc_client, network).await?;
+    let oracles = oracles::weighted_order(oracles, &mut rand::thread_rng());
+
+    for (idx, health) in oracles.iter().enumerate() {
+        let oracle = &health.oracle;
+        eprintln!(
+            "Trying oracle {}/{}: {} ({} ms)",
+            idx + 1,
+            oracles.len(),
+            oracle,
+            health.latency_ms
+        );
 
         let commit_ix = build_randomness_commit_instruction(
             &sb_program_id,
-            &randomness_keypair.pubkey(),
+            randomness_account,
             &queue,
             oracle,
             &payer.pubkey(),
         )?;
 
-        let recent_blockhash = rpc_client.get_latest_blockhash()?;
-        let message = Message::new(&[commit_ix], Some(&payer.pubkey()));
-        let transaction = Transaction::new(&[payer], message, recent_blockhash);
+        let mut instructions = vec![commit_ix];
+        instructions.extend_from_slice(followed_by);
 
-        match rpc_client.send_and_confirm_transaction(&transaction) {
+        match transaction::send(rpc_client, payer, &[], &instructions).await {
             Ok(sig) => {
-                println!(
+                eprintln!(
                     "Commit transaction succeeded with oracle {}: {}",
                     oracle, sig
                 );
-                commit_result = Some((sig.to_string(), *oracle));
-                break;
+                return Ok((sig.to_string(), *oracle));
             }
             Err(e) => {
-                let err_str = e.to_string();
-                if err_str.contains("RandomnessOracleKeyExpired") {
-                    println!("Oracle {} key expired, trying next...", oracle);
-                    continue;
-                } else {
-                    // Other error, might want to fail immediately
-                    println!("Oracle {} failed with error: {}", oracle, err_str);
-                    continue;
+                // Another oracle cannot fix a failure in the instructions after the commit
+                let transaction_error = e.downcast_ref::<ClientError>().and_then(|e| e.get_transaction_error());
+                if let Some(TransactionError::InstructionError(index, _)) = transaction_error {
+                    if index as usize > transaction::leading_instruction_count() {
+                        return Err(anyhow!("Instruction after randomnessCommit failed: {}", e));
+                    }
                 }
-            }
-        }
-    }
-
-    let (signature, oracle) =
-        commit_result.ok_or_else(|| anyhow!("All oracles failed to commit randomness"))?;
-
-    Ok(CommitResult {
-        randomness_account: randomness_keypair.pubkey(),
-        commit_slot: recent_slot,
-        signature,
-        oracle,
-    })
-}
-
-/// Get all oracles from the queue account
-fn get_oracles_from_queue(rpc_client: &RpcClient, queue: &Pubkey) -> Result<Vec<Pubkey>> {
-    // Read queue account data to find an oracle
-    let queue_data = rpc_client.get_account_data(queue)?;
-
-    // QueueAccountData layout (from IDL, bytemuck/repr(C)):
-    // - discriminator: 8 bytes
-    // - authority: 32 bytes
-    // - mr_enclaves: 32 * 32 = 1024 bytes
-    // - oracle_keys: 78 * 32 = 2496 bytes (starting at offset 8 + 32 + 1024 = 1064)
-    // - reserved1: 40 bytes
-    // - secp_oracle_signing_keys: 30 * 20 = 600 bytes
-    // - ed25519_oracle_signing_keys: 30 * 32 = 960 bytes
-    // - max_quote_verification_age: 8 bytes
-    // - last_heartbeat: 8 bytes
-    // - node_timeout: 8 bytes
-    // - oracle_min_stake: 8 bytes
-    // - allow_authority_override_after: 8 bytes
-    // - mr_enclaves_len: 4 bytes
-    // - oracle_keys_len: 4 bytes (at offset ~5236)
-
-    // The actual offsets based on IDL field sizes:
-    // discriminator: 8
-    // authority: 32 -> offset 8, end 40
-    // mr_enclaves: 32*32=1024 -> offset 40, end 1064
-    // oracle_keys: 78*32=2496 -> offset 1064, end 3560
-    // reserved1: 40 -> offset 3560, end 3600
-    // secp_oracle_signing_keys: 30*20=600 -> offset 3600, end 4200
-    // ed25519_oracle_signing_keys: 30*32=960 -> offset 4200, end 5160
-    // max_quote_verification_age: 8 -> offset 5160, end 5168
-    // last_heartbeat: 8 -> offset 5168, end 5176
-    // node_timeout: 8 -> offset 5176, end 5184
-    // oracle_min_stake: 8 -> offset 5184, end 5192
-    // allow_authority_override_after: 8 -> offset 5192, end 5200
-    // mr_enclaves_len: 4 -> offset 5200, end 5204
-    // oracle_keys_len: 4 -> offset 5204, end 5208
-
-    const ORACLE_KEYS_OFFSET: usize = 1064; // 8 + 32 + 1024
-    const ORACLE_KEYS_LEN_OFFSET: usize = 5204;
-
-    if queue_data.len() < ORACLE_KEYS_LEN_OFFSET + 4 {
-        return Err(anyhow!(
-            "Queue account data too short: {} bytes",
-            queue_data.len()
-        ));
-    }
-
-    // Read oracle_keys_len (u32)
-    let oracle_keys_len = u32::from_le_bytes(
-        queue_data[ORACLE_KEYS_LEN_OFFSET..ORACLE_KEYS_LEN_OFFSET + 4]
-            .try_into()
-            .map_err(|_| anyhow!("Failed to read oracle_keys_len"))?,
-    ) as usize;
-
-    println!("Queue has {} active oracles", oracle_keys_len);
-
-    if oracle_keys_len == 0 {
-        return Err(anyhow!("Queue has no active oracles"));
-    }
-
-    // Collect all valid oracles
-    let sb_program_id = get_sb_program_id(true)?;
-    let mut oracles = Vec::new();
-
-    for oracle_idx in 0..oracle_keys_len.min(20) {
-        let oracle_offset = ORACLE_KEYS_OFFSET + (oracle_idx * 32);
-
-        if queue_data.len() < oracle_offset + 32 {
-            continue;
-        }
-
-        let oracle_bytes: [u8; 32] = match queue_data[oracle_offset..oracle_offset + 32].try_into()
-        {
-            Ok(b) => b,
-            Err(_) => continue,
-        };
-
-        let oracle = Pubkey::from(oracle_bytes);
-
-        // Skip zero pubkey
-        if oracle == Pubkey::default() {
-            continue;
-        }
 
-        // Verify oracle exists on-chain and is owned by Switchboard
-        match rpc_client.get_account(&oracle) {
-            Ok(account) => {
-                if account.owner != sb_program_id {
-                    continue;
-                }
-                oracles.push(oracle);
+                eprintln!("Oracle {} failed, trying next: {}", oracle, e);
+                oracles::forget(&queue, oracle);
             }
-            Err(_) => continue,
         }
     }
 
-    if oracles.is_empty() {
-        return Err(anyhow!("Could not find any valid oracles in the queue"));
-    }
-
-    Ok(oracles)
+    Err(anyhow!("All oracles failed to commit randomness"))
 }
 
 /// Build the Switchboard randomnessInit instruction
@@ -315,7 +251,7 @@ fn build_randomness_init_instruction(
     // Seeds are: [authority (lutSigner), recent_slot as 8 bytes little endian]
     let (lut, _) = Pubkey::find_program_address(
         &[lut_signer.as_ref(), &recent_slot.to_le_bytes()],
-        &solana_sdk::address_lookup_table::program::id(),
+        &address_lookup_table::program::id(),
     );
 
     // Discriminator for randomnessInit (Anchor style)
@@ -340,27 +276,27 @@ fn build_randomness_init_instruction(
     // 12. lut (writable)
     // 13. address_lookup_table_program
 
-    println!("Building randomnessInit with accounts:");
-    println!("  0. randomness: {}", randomness_account);
-    println!("  1. reward_escrow: {}", reward_escrow);
-    println!("  2. authority: {}", payer);
-    println!("  3. queue: {}", queue);
-    println!("  4. payer: {}", payer);
-    println!("  5. system_program: {}", system_program::id());
-    println!("  6. token_program: {}", token_program);
-    println!(
+    eprintln!("Building randomnessInit with accounts:");
+    eprintln!("  0. randomness: {}", randomness_account);
+    eprintln!("  1. reward_escrow: {}", reward_escrow);
+    eprintln!("  2. authority: {}", payer);
+    eprintln!("  3. queue: {}", queue);
+    eprintln!("  4. payer: {}", payer);
+    eprintln!("  5. system_program: {}", system_program::id());
+    eprintln!("  6. token_program: {}", token_program);
+    eprintln!(
         "  7. associated_token_program: {}",
         associated_token_program
     );
-    println!("  8. wrapped_sol_mint: {}", wrapped_sol_mint);
-    println!("  9. program_state: {}", program_state);
-    println!(" 10. lut_signer: {}", lut_signer);
-    println!(" 11. lut: {}", lut);
-    println!(
+    eprintln!("  8. wrapped_sol_mint: {}", wrapped_sol_mint);
+    eprintln!("  9. program_state: {}", program_state);
+    eprintln!(" 10. lut_signer: {}", lut_signer);
+    eprintln!(" 11. lut: {}", lut);
+    eprintln!(
         " 12. address_lookup_table_program: {}",
         address_lookup_table::program::id()
     );
-    println!("  Instruction data (hex): {}", hex::encode(&data));
+    eprintln!("  Instruction data (hex): {}", hex::encode(&data));
 
     let accounts = vec![
         AccountMeta::new(*randomness_account, true), // 0. randomness (signer, writable)
@@ -402,12 +338,12 @@ fn build_randomness_commit_instruction(
     // RandomnessCommitParams is an empty struct, so no additional data needed
     let data = discriminator;
 
-    println!("Building randomnessCommit with accounts:");
-    println!("  0. randomness: {}", randomness_account);
-    println!("  1. queue: {}", queue);
-    println!("  2. oracle: {}", oracle);
-    println!("  3. recent_slothashes: {}", sysvar::slot_hashes::id());
-    println!("  4. authority: {}", authority);
+    eprintln!("Building randomnessCommit with accounts:");
+    eprintln!("  0. randomness: {}", randomness_account);
+    eprintln!("  1. queue: {}", queue);
+    eprintln!("  2. oracle: {}", oracle);
+    eprintln!("  3. recent_slothashes: {}", sysvar::slot_hashes::id());
+    eprintln!("  4. authority: {}", authority);
 
     let accounts = vec![
         AccountMeta::new(*randomness_account, false), // 0. randomness (writable)
@@ -420,9 +356,51 @@ fn build_randomness_commit_instruction(
     Ok(Instruction::new_with_bytes(*program_id, &data, accounts))
 }
 
+/// Build the Switchboard randomnessClose instruction
+fn build_randomness_close_instruction(
+    program_id: &Pubkey,
+    randomness_account: &Pubkey,
+    queue: &Pubkey,
+    authority: &Pubkey,
+    lut_slot: u64,
+) -> Result<Instruction> {
+    let wrapped_sol_mint = Pubkey::from_str(WRAPPED_SOL_MINT)?;
+    let token_program = Pubkey::from_str(SPL_TOKEN_PROGRAM)?;
+
+    let (program_state, _) = Pubkey::find_program_address(&[b"STATE"], program_id);
+    let (lut_signer, _) =
+        Pubkey::find_program_address(&[b"LutSigner", randomness_account.as_ref()], program_id);
+    let reward_escrow = get_associated_token_address(randomness_account, &wrapped_sol_mint);
+    let (lut, _) = Pubkey::find_program_address(
+        &[lut_signer.as_ref(), &lut_slot.to_le_bytes()],
+        &address_lookup_table::program::id(),
+    );
+
+    // RandomnessCloseParams is an empty struct, so the discriminator is the whole payload
+    let data = get_anchor_discriminator("randomness_close");
+
+    let accounts = vec![
+        AccountMeta::new(*randomness_account, false), // 0. randomness (writable)
+        AccountMeta::new(reward_escrow, false),       // 1. reward_escrow (writable)
+        AccountMeta::new_readonly(*authority, true),  // 2. authority (signer)
+        AccountMeta::new(*queue, false),              // 3. queue (writable)
+        AccountMeta::new(*authority, true),           // 4. payer (signer, writable)
+        AccountMeta::new(*authority, false),          // 5. sol_dest (writable)
+        AccountMeta::new_readonly(system_program::id(), false), // 6. system_program
+        AccountMeta::new_readonly(token_program, false), // 7. token_program
+        AccountMeta::new_readonly(wrapped_sol_mint, false), // 8. wrapped_sol_mint
+        AccountMeta::new_readonly(lut_signer, false), // 9. lut_signer
+        AccountMeta::new(lut, false),                 // 10. lut (writable)
+        AccountMeta::new_readonly(address_lookup_table::program::id(), false), // 11. address_lookup_table_program
+        AccountMeta::new_readonly(program_state, false), // 12. program_state
+    ];
+
+    Ok(Instruction::new_with_bytes(*program_id, &data, accounts))
+}
+
 /// Get Anchor instruction discriminator using SHA256
 fn get_anchor_discriminator(name: &str) -> Vec<u8> {
-    use solana_sdk::hash::{hashv, Hash};
+    use solana_sdk::hash::hashv;
     // Anchor uses sha256 hash of "global:<instruction_name>"
     // Note: Anchor uses Sha256, solana_sdk::hash::hash uses Sha256 internally
     let preimage = format!("global:{}", name);
@@ -430,89 +408,49 @@ fn get_anchor_discriminator(name: &str) -> Vec<u8> {
     hash_bytes[..8].to_vec()
 }
 
-/// Wait for randomness to be revealed
-pub async fn wait_for_reveal(
+/// Close a randomness account owned by `payer`, returning its rent (and the reward
+/// escrow's) to the payer
+pub async fn close_randomness(
     rpc_client: &RpcClient,
+    payer: &Wallet,
     randomness_account: &Pubkey,
-    timeout_secs: u64,
-) -> Result<()> {
-    let start = std::time::Instant::now();
-    let timeout = Duration::from_secs(timeout_secs);
-
-    loop {
-        if start.elapsed() > timeout {
-            return Err(anyhow!("Timeout waiting for randomness reveal"));
-        }
-
-        match check_if_revealed(rpc_client, randomness_account) {
-            Ok(true) => {
-                println!("Randomness revealed!");
-                return Ok(());
-            }
-            Ok(false) => {
-                print!(".");
-                tokio::time::sleep(Duration::from_secs(1)).await;
-            }
-            Err(e) => {
-                return Err(anyhow!("Error checking randomness: {}", e));
-            }
-        }
+    network: &Network,
+) -> Result<String> {
+    let account = rpc_client.get_account(randomness_account).await?;
+    if account.owner != network.switchboard_program_id {
+        return Err(anyhow!(
+            "{} is not owned by the Switchboard program (owner: {})",
+            randomness_account,
+            account.owner
+        ));
     }
-}
-
-/// Check if randomness has been revealed
-fn check_if_revealed(rpc_client: &RpcClient, randomness_account: &Pubkey) -> Result<bool> {
-    let account = rpc_client.get_account(randomness_account)?;
-
-    // Parse the RandomnessAccountData structure
-    // The revealed value is stored at a specific offset
-    // If the reveal slot is non-zero, randomness has been revealed
 
-    if account.data.len() < 100 {
-        return Err(anyhow!("Account data too short"));
+    let randomness = accounts::randomness(&account.data)?;
+    let authority = Pubkey::new_from_array(randomness.authority.to_bytes());
+    if authority != payer.pubkey() {
+        return Err(anyhow!(
+            "Randomness account {} belongs to {}, not this wallet",
+            randomness_account,
+            authority
+        ));
     }
 
-    // Check if reveal_slot is set (offset may vary based on Switchboard version)
-    // For now, we check if there's meaningful data after the initial fields
-    // The RandomnessAccountData has: seed_slot, seed_value, revealed_value, etc.
-
-    // Simplified check: look for non-zero bytes in the revealed value area
-    // This is a heuristic - the actual check should parse the full structure
-    let reveal_offset = 40; // Approximate offset to revealed random value
-    let reveal_check = &account.data[reveal_offset..reveal_offset + 32];
-
-    // If the revealed value section has non-zero bytes, it's likely revealed
-    let is_revealed = reveal_check.iter().any(|&b| b != 0);
+    let close_ix = build_randomness_close_instruction(
+        &network.switchboard_program_id,
+        randomness_account,
+        &Pubkey::new_from_array(randomness.queue.to_bytes()),
+        &payer.pubkey(),
+        accounts::randomness_lut_slot(&account.data)?,
+    )?;
 
-    Ok(is_revealed)
+    Ok(transaction::send(rpc_client, payer, &[], &[close_ix]).await?.to_string())
 }
 
 /// Get the gateway URL from an oracle account
-fn get_oracle_gateway_url(rpc_client: &RpcClient, oracle: &Pubkey) -> Result<String> {
-    let oracle_data = rpc_client.get_account_data(oracle)?;
-
-    // The gateway_uri is stored as a fixed-size field. We search for "https://" prefix
-    // and extract the URL up to the first null byte or end of field.
-    let https_prefix = b"https://";
-
-    // Find the position of "https://"
-    let url_start = oracle_data
-        .windows(https_prefix.len())
-        .position(|window| window == https_prefix)
-        .ok_or_else(|| anyhow!("Could not find gateway URL in oracle account"))?;
-
-    // Find the end of the URL (first null byte or max 256 chars)
-    let max_len = 256.min(oracle_data.len() - url_start);
-    let url_end = url_start
-        + oracle_data[url_start..url_start + max_len]
-            .iter()
-            .position(|&b| b == 0)
-            .unwrap_or(max_len);
-
-    let gateway_uri = String::from_utf8(oracle_data[url_start..url_end].to_vec())
-        .map_err(|e| anyhow!("Failed to parse gateway_uri: {}", e))?;
-
-    Ok(gateway_uri)
+async fn get_oracle_gateway_url(rpc_client: &RpcClient, oracle: &Pubkey) -> Result<String> {
+    accounts::oracle(&rpc_client.get_account_data(oracle).await?)?
+        .gateway_uri()
+        .ok_or_else(|| anyhow!("Oracle {} has no gateway URL", oracle))
 }
 
 /// Fetch randomness reveal from the Gateway API
@@ -523,23 +461,11 @@ pub async fn fetch_randomness_reveal(
     rpc_client: &RpcClient,
 ) -> Result<GatewayRevealResponse> {
     // Get the slot and slothash from the randomness account
-    let randomness_data = rpc_client.get_account_data(randomness_account)?;
-
-    // Parse seed_slot from randomness account (offset: 8 + 32 + 32 + 32 = 104)
-    let seed_slot_offset = 104;
-    let seed_slot = u64::from_le_bytes(
-        randomness_data[seed_slot_offset..seed_slot_offset + 8]
-            .try_into()
-            .map_err(|_| anyhow!("Failed to read seed_slot"))?,
-    );
-
-    // Parse seed_slothash from randomness account (offset: 8 + 32 + 32 = 72)
-    let slothash_offset = 72;
-    let slothash: [u8; 32] = randomness_data[slothash_offset..slothash_offset + 32]
-        .try_into()
-        .map_err(|_| anyhow!("Failed to read seed_slothash"))?;
+    let randomness = accounts::randomness(&rpc_client.get_account_data(randomness_account).await?)?;
+    let seed_slot = randomness.seed_slot;
+    let slothash = randomness.seed_slothash;
 
-    println!("Requesting reveal for slot {} from {}", seed_slot, gateway_url);
+    eprintln!("Requesting reveal for slot {} from {}", seed_slot, gateway_url);
 
     // Build request
     let request = GatewayRevealRequest {
@@ -549,7 +475,7 @@ pub async fn fetch_randomness_reveal(
         rpc: rpc_url.to_string(),
     };
 
-    let url = format!("{}/gateway/api/v1/randomness_reveal", gateway_url.trim_end_matches('/'));
+    let url = format!("{}{}", gateway_url.trim_end_matches('/'), REVEAL_PATH);
 
     let client = reqwest::Client::new();
     let response = client
@@ -572,31 +498,49 @@ pub async fn fetch_randomness_reveal(
         .await
         .map_err(|e| anyhow!("Failed to parse gateway response: {}", e))?;
 
-    println!("Gateway reveal response received");
-    println!("  Recovery ID: {}", reveal_response.recovery_id);
-    println!("  Value: 0x{}", hex::encode(&reveal_response.value));
+    eprintln!("Gateway reveal response received");
+    eprintln!("  Recovery ID: {}", reveal_response.recovery_id);
+    eprintln!("  Value: 0x{}", hex::encode(reveal_response.value));
 
     Ok(reveal_response)
 }
 
-/// Build and send the randomnessReveal instruction
+/// Reveal randomness through the committed oracle's gateway, returning the signature
+///
+/// Only that oracle can sign the reveal, and the account can't be re-committed to another
+/// one: the pot is bound to the committed seed slot, so settle_lottery would reject it.
 pub async fn reveal_randomness(
     rpc_client: &RpcClient,
-    payer: &Keypair,
+    payer: &Wallet,
     randomness_account: &Pubkey,
     oracle: &Pubkey,
-    queue: &Pubkey,
-    rpc_url: &str,
+    network: &Network,
 ) -> Result<String> {
-    let is_devnet = rpc_url.contains("devnet");
-    let sb_program_id = get_sb_program_id(is_devnet)?;
+    reveal_randomness_with(rpc_client, payer, randomness_account, oracle, network, &[]).await
+}
 
-    // Get the oracle's gateway URL
-    let gateway_url = get_oracle_gateway_url(rpc_client, oracle)?;
-    println!("Oracle gateway URL: {}", gateway_url);
+/// Reveal randomness through the committed oracle's gateway with `followed_by` appended
+/// to the same transaction, returning the signature
+///
+/// A revealed value only reads back in the slot it was revealed in, so settle_lottery has
+/// to execute in the reveal's own transaction.
+pub async fn reveal_randomness_with(
+    rpc_client: &RpcClient,
+    payer: &Wallet,
+    randomness_account: &Pubkey,
+    oracle: &Pubkey,
+    network: &Network,
+    followed_by: &[Instruction],
+) -> Result<String> {
+    let sb_program_id = network.switchboard_program_id;
+    let queue = &network.switchboard_queue;
 
-    // Fetch reveal data from gateway
-    let reveal_data = fetch_randomness_reveal(&gateway_url, randomness_account, rpc_url, rpc_client).await?;
+    let reveal_data = reveal_from_oracle(rpc_client, randomness_account, oracle, network)
+        .await
+        .map_err(|e| {
+            oracles::forget(queue, oracle);
+            anyhow!("Gateway of oracle {} could not reveal {}: {:#}", oracle, randomness_account, e)
+        })?;
 
     // Build the reveal instruction
     let reveal_ix = build_randomness_reveal_instruction(
@@ -608,20 +552,44 @@ pub async fn reveal_randomness(
         &reveal_data,
     )?;
 
-    // Send transaction
-    let recent_blockhash = rpc_client.get_latest_blockhash()?;
-    let message = Message::new(&[reveal_ix], Some(&payer.pubkey()));
-    let transaction = Transaction::new(&[payer], message, recent_blockhash);
+    let mut instructions = vec![reveal_ix];
+    instructions.extend_from_slice(followed_by);
 
-    println!("Sending randomnessReveal transaction...");
-    let signature = rpc_client
-        .send_and_confirm_transaction(&transaction)
+    eprintln!("Sending randomnessReveal transaction...");
+    let signature = transaction::send(rpc_client, payer, &[], &instructions).await
         .map_err(|e| anyhow!("Failed to send reveal transaction: {}", e))?;
 
-    println!("Reveal transaction: {}", signature);
+    eprintln!("Reveal transaction: {}", signature);
     Ok(signature.to_string())
 }
 
+/// Fetch the reveal from `oracle`'s gateway, retrying briefly in case it is still
+/// catching up with the commit slot
+async fn reveal_from_oracle(
+    rpc_client: &RpcClient,
+    randomness_account: &Pubkey,
+    oracle: &Pubkey,
+    network: &Network,
+) -> Result<GatewayRevealResponse> {
+    const ATTEMPTS: u32 = 3;
+
+    let gateway_url = get_oracle_gateway_url(rpc_client, oracle).await?;
+    eprintln!("Oracle gateway URL: {}", gateway_url);
+
+    let mut attempt = 1;
+    loop {
+        match fetch_randomness_reveal(&gateway_url, randomness_account, &network.rpc_url, rpc_client).await {
+            Ok(reveal_data) => return Ok(reveal_data),
+            Err(e) if attempt < ATTEMPTS => {
+                eprintln!("Gateway reveal attempt {}/{} failed: {:#}", attempt, ATTEMPTS, e);
+                tokio::time::sleep(Duration::from_secs(attempt as u64)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
 /// Build the Switchboard randomnessReveal instruction
 fn build_randomness_reveal_instruction(
     program_id: &Pubkey,
@@ -663,7 +631,7 @@ fn build_randomness_reveal_instruction(
     data.push(reveal_data.recovery_id);        // 1 byte recovery_id
     data.extend_from_slice(&reveal_data.value); // 32 bytes value
 
-    println!("Reveal instruction data size: {} bytes", data.len());
+    eprintln!("Reveal instruction data size: {} bytes", data.len());
 
     // Account order from IDL for randomness_reveal (12 accounts total):
     // 0. randomness (writable)
@@ -679,19 +647,19 @@ fn build_randomness_reveal_instruction(
     // 10. wrapped_sol_mint
     // 11. program_state
 
-    println!("Building randomnessReveal with accounts:");
-    println!("  0. randomness: {}", randomness_account);
-    println!("  1. oracle: {}", oracle);
-    println!("  2. queue: {}", queue);
-    println!("  3. stats: {}", oracle_stats);
-    println!("  4. authority: {}", payer);
-    println!("  5. payer: {}", payer);
-    println!("  6. recent_slothashes: {}", sysvar::slot_hashes::id());
-    println!("  7. system_program: {}", system_program::id());
-    println!("  8. reward_escrow: {}", reward_escrow);
-    println!("  9. token_program: {}", token_program);
-    println!(" 10. wrapped_sol_mint: {}", wrapped_sol_mint);
-    println!(" 11. program_state: {}", program_state);
+    eprintln!("Building randomnessReveal with accounts:");
+    eprintln!("  0. randomness: {}", randomness_account);
+    eprintln!("  1. oracle: {}", oracle);
+    eprintln!("  2. queue: {}", queue);
+    eprintln!("  3. stats: {}", oracle_stats);
+    eprintln!("  4. authority: {}", payer);
+    eprintln!("  5. payer: {}", payer);
+    eprintln!("  6. recent_slothashes: {}", sysvar::slot_hashes::id());
+    eprintln!("  7. system_program: {}", system_program::id());
+    eprintln!("  8. reward_escrow: {}", reward_escrow);
+    eprintln!("  9. token_program: {}", token_program);
+    eprintln!(" 10. wrapped_sol_mint: {}", wrapped_sol_mint);
+    eprintln!(" 11. program_state: {}", program_state);
 
     let accounts = vec![
         AccountMeta::new(*randomness_account, false),           // 0. randomness (writable)
@@ -712,74 +680,36 @@ fn build_randomness_reveal_instruction(
 }
 
 /// Check the status of a randomness account
-pub fn check_randomness_status(
+pub async fn check_randomness_status(
     rpc_client: &RpcClient,
     randomness_account: &Pubkey,
+    sb_program_id: &Pubkey,
 ) -> Result<String> {
-    let account = match rpc_client.get_account(randomness_account) {
+    let account = match rpc_client.get_account(randomness_account).await {
         Ok(acc) => acc,
         Err(_) => return Ok("Account not found".to_string()),
     };
 
-    // Check both devnet and mainnet program IDs
-    let sb_devnet = get_sb_program_id(true)?;
-    let sb_mainnet = get_sb_program_id(false)?;
-
-    if account.owner != sb_devnet && account.owner != sb_mainnet {
+    if account.owner != *sb_program_id {
         return Ok(format!(
             "Account is not owned by Switchboard program (owner: {})",
             account.owner
         ));
     }
 
-    // Parse basic info from the account
-    // RandomnessAccountData layout:
-    // - discriminator: 8 bytes [10, 66, 229, 135, 220, 239, 217, 114]
-    // - authority: 32 bytes
-    // - queue: 32 bytes
-    // - seed_slothash: 32 bytes
-    // - seed_slot: 8 bytes
-    // - oracle: 32 bytes
-    // - reveal_slot: 8 bytes
-    // - value: 32 bytes
-
-    if account.data.len() < 160 {
-        return Ok("Account data too short to be a valid randomness account".to_string());
-    }
-
-    // Check discriminator
-    let expected_discriminator = [10u8, 66, 229, 135, 220, 239, 217, 114];
-    if account.data[..8] != expected_discriminator {
-        return Ok("Invalid randomness account discriminator".to_string());
-    }
-
-    // Parse reveal_slot (offset: 8 + 32 + 32 + 32 + 8 + 32 = 144)
-    let reveal_slot_offset = 144;
-    let reveal_slot = u64::from_le_bytes(
-        account.data[reveal_slot_offset..reveal_slot_offset + 8]
-            .try_into()
-            .unwrap_or([0u8; 8]),
-    );
-
-    // Parse seed_slot (offset: 8 + 32 + 32 + 32 = 104)
-    let seed_slot_offset = 104;
-    let seed_slot = u64::from_le_bytes(
-        account.data[seed_slot_offset..seed_slot_offset + 8]
-            .try_into()
-            .unwrap_or([0u8; 8]),
-    );
+    let randomness = match accounts::randomness(&account.data) {
+        Ok(randomness) => randomness,
+        Err(e) => return Ok(format!("Not a valid randomness account: {}", e)),
+    };
 
-    if reveal_slot > 0 {
-        Ok(format!(
+    Ok(match RandomnessState::of(&randomness) {
+        RandomnessState::Revealed { seed_slot, reveal_slot } => format!(
             "Revealed at slot {} (seed slot: {}) - randomness value is available",
             reveal_slot, seed_slot
-        ))
-    } else if seed_slot > 0 {
-        Ok(format!(
-            "Committed at slot {} - waiting for oracle to reveal",
-            seed_slot
-        ))
-    } else {
-        Ok("Initialized - not yet committed".to_string())
-    }
+        ),
+        RandomnessState::Committed { seed_slot } => {
+            format!("Committed at slot {} - waiting for oracle to reveal", seed_slot)
+        }
+        RandomnessState::Initialized => "Initialized - not yet committed".to_string(),
+    })
 }
@@ -0,0 +1,193 @@
+//! Switchboard On-Demand account layouts
+//!
+//! The single place that knows how randomness, oracle and queue accounts are laid out.
+//! Decoding goes through the `switchboard_on_demand` zero-copy types after checking the
+//! discriminator and length. RPC buffers carry no alignment guarantee, so the account
+//! body is copied out with an unaligned read instead of being cast in place.
+
+use anyhow::{anyhow, Result};
+use bytemuck::Pod;
+use switchboard_on_demand::Discriminator;
+
+pub use switchboard_on_demand::{OracleAccountData, QueueAccountData, RandomnessAccountData};
+
+/// Where a randomness account is in its commit/reveal cycle
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RandomnessState {
+    /// randomnessInit landed, nothing committed yet
+    Initialized,
+    /// Committed to `seed_slot`, waiting for the oracle to reveal
+    Committed { seed_slot: u64 },
+    /// The value for the current commit has been revealed
+    Revealed { seed_slot: u64, reveal_slot: u64 },
+}
+
+impl RandomnessState {
+    pub fn of(randomness: &RandomnessAccountData) -> Self {
+        let (seed_slot, reveal_slot) = (randomness.seed_slot, randomness.reveal_slot);
+        if seed_slot == 0 {
+            RandomnessState::Initialized
+        } else if reveal_slot >= seed_slot {
+            RandomnessState::Revealed { seed_slot, reveal_slot }
+        } else {
+            // A reveal from an earlier commit leaves reveal_slot behind the new seed_slot
+            RandomnessState::Committed { seed_slot }
+        }
+    }
+}
+
+/// Decode a randomness account
+pub fn randomness(data: &[u8]) -> Result<RandomnessAccountData> {
+    decode(data, RandomnessAccountData::DISCRIMINATOR, "randomness")
+}
+
+/// Slot the randomness account's lookup table was derived from (needed to close it)
+///
+/// Stored in the first reserved bytes after `value`, which the crate keeps private.
+pub fn randomness_lut_slot(data: &[u8]) -> Result<u64> {
+    const LUT_SLOT: usize = 184;
+
+    randomness(data)?;
+    let bytes = data[LUT_SLOT..LUT_SLOT + 8].try_into().expect("length checked by decode");
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Decode an oracle account
+pub fn oracle(data: &[u8]) -> Result<OracleAccountData> {
+    decode(data, OracleAccountData::DISCRIMINATOR, "oracle")
+}
+
+/// Decode a queue account
+pub fn queue(data: &[u8]) -> Result<QueueAccountData> {
+    decode(data, QueueAccountData::DISCRIMINATOR, "queue")
+}
+
+fn decode<T: Pod>(data: &[u8], discriminator: &[u8], name: &str) -> Result<T> {
+    if data.len() < discriminator.len() || &data[..discriminator.len()] != discriminator {
+        return Err(anyhow!("Not a Switchboard {} account (discriminator mismatch)", name));
+    }
+    let body = &data[discriminator.len()..];
+    let size = std::mem::size_of::<T>();
+    if body.len() < size {
+        return Err(anyhow!(
+            "Switchboard {} account data too short: {} bytes, expected {}",
+            name,
+            data.len(),
+            discriminator.len() + size
+        ));
+    }
+    Ok(bytemuck::pod_read_unaligned(&body[..size]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey::Pubkey;
+
+    // Offsets into the raw account data (discriminator included), written out by hand
+    // so a layout change in switchboard-on-demand shows up as a failing test
+    const RANDOMNESS_SEED_SLOTHASH: usize = 72;
+    const RANDOMNESS_SEED_SLOT: usize = 104;
+    const RANDOMNESS_ORACLE: usize = 112;
+    const RANDOMNESS_REVEAL_SLOT: usize = 144;
+    const RANDOMNESS_VALUE: usize = 152;
+    const RANDOMNESS_LUT_SLOT: usize = 184;
+    const ORACLE_VALID_UNTIL: usize = 88;
+    const ORACLE_GATEWAY_URI: usize = 3584;
+    const QUEUE_ORACLE_KEYS: usize = 1064;
+    const QUEUE_ORACLE_KEYS_LEN: usize = 5204;
+
+    /// Account data as returned by getAccountInfo: discriminator, zeroed body, plus the
+    /// trailing slack some accounts carry
+    fn account_data(discriminator: &[u8], size: usize) -> Vec<u8> {
+        let mut data = discriminator.to_vec();
+        data.resize(discriminator.len() + size + 16, 0);
+        data
+    }
+
+    fn put(data: &mut [u8], offset: usize, bytes: &[u8]) {
+        data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    fn randomness_fixture(seed_slot: u64, reveal_slot: u64) -> Vec<u8> {
+        let mut data = account_data(RandomnessAccountData::DISCRIMINATOR, std::mem::size_of::<RandomnessAccountData>());
+        put(&mut data, RANDOMNESS_SEED_SLOTHASH, &[7; 32]);
+        put(&mut data, RANDOMNESS_SEED_SLOT, &seed_slot.to_le_bytes());
+        put(&mut data, RANDOMNESS_ORACLE, Pubkey::new_from_array([9; 32]).as_ref());
+        put(&mut data, RANDOMNESS_REVEAL_SLOT, &reveal_slot.to_le_bytes());
+        put(&mut data, RANDOMNESS_VALUE, &[42; 32]);
+        put(&mut data, RANDOMNESS_LUT_SLOT, &990u64.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn decodes_randomness_fields() {
+        let randomness = randomness(&randomness_fixture(1_000, 1_002)).unwrap();
+        assert_eq!(randomness.seed_slothash, [7; 32]);
+        assert_eq!(randomness.seed_slot, 1_000);
+        assert_eq!(randomness.oracle.to_bytes(), [9; 32]);
+        assert_eq!(randomness.reveal_slot, 1_002);
+        assert_eq!(randomness.value, [42; 32]);
+        assert_eq!(randomness_lut_slot(&randomness_fixture(1_000, 1_002)).unwrap(), 990);
+    }
+
+    #[test]
+    fn decodes_unaligned_buffers() {
+        let data = randomness_fixture(1_000, 0);
+        let mut shifted = vec![0u8];
+        shifted.extend_from_slice(&data);
+        assert_eq!(randomness(&shifted[1..]).unwrap().seed_slot, 1_000);
+    }
+
+    #[test]
+    fn randomness_state_follows_commit_and_reveal() {
+        let state = |seed, reveal| RandomnessState::of(&randomness(&randomness_fixture(seed, reveal)).unwrap());
+        assert_eq!(state(0, 0), RandomnessState::Initialized);
+        assert_eq!(state(1_000, 0), RandomnessState::Committed { seed_slot: 1_000 });
+        assert_eq!(
+            state(1_000, 1_002),
+            RandomnessState::Revealed { seed_slot: 1_000, reveal_slot: 1_002 }
+        );
+        // Re-committed after an earlier reveal
+        assert_eq!(state(2_000, 1_002), RandomnessState::Committed { seed_slot: 2_000 });
+    }
+
+    #[test]
+    fn rejects_wrong_discriminator_and_short_data() {
+        let mut data = randomness_fixture(1, 0);
+        assert!(oracle(&data).is_err());
+        data.truncate(RANDOMNESS_VALUE);
+        assert!(randomness(&data).is_err());
+        assert!(randomness(&[]).is_err());
+    }
+
+    #[test]
+    fn decodes_oracle_gateway_and_expiry() {
+        let mut data = account_data(OracleAccountData::DISCRIMINATOR, std::mem::size_of::<OracleAccountData>());
+        put(&mut data, ORACLE_VALID_UNTIL, &1_700_000_000i64.to_le_bytes());
+        put(&mut data, ORACLE_GATEWAY_URI, b"https://gateway.example.com");
+
+        let oracle = oracle(&data).unwrap();
+        assert_eq!(oracle.gateway_uri().as_deref(), Some("https://gateway.example.com"));
+        assert_eq!(oracle.enclave.valid_until, 1_700_000_000);
+    }
+
+    #[test]
+    fn oracle_without_gateway() {
+        let data = account_data(OracleAccountData::DISCRIMINATOR, std::mem::size_of::<OracleAccountData>());
+        assert_eq!(oracle(&data).unwrap().gateway_uri(), None);
+    }
+
+    #[test]
+    fn decodes_queue_oracle_keys() {
+        let mut data = account_data(QueueAccountData::DISCRIMINATOR, std::mem::size_of::<QueueAccountData>());
+        put(&mut data, QUEUE_ORACLE_KEYS, &[1; 32]);
+        put(&mut data, QUEUE_ORACLE_KEYS + 32, &[2; 32]);
+        // A stale key past oracle_keys_len must be ignored
+        put(&mut data, QUEUE_ORACLE_KEYS + 64, &[3; 32]);
+        put(&mut data, QUEUE_ORACLE_KEYS_LEN, &2u32.to_le_bytes());
+
+        let keys: Vec<[u8; 32]> = queue(&data).unwrap().oracle_keys().iter().map(|k| k.to_bytes()).collect();
+        assert_eq!(keys, vec![[1; 32], [2; 32]]);
+    }
+}
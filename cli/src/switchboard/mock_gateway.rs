@@ -0,0 +1,448 @@
+//! Local stand-in for an oracle gateway
+//!
+//! Serves `POST /gateway/api/v1/randomness_reveal` like a Switchboard gateway, signing
+//! reveals with a local secp256k1 key, so the reveal and settle paths can run without a
+//! real oracle (tests, `solana-test-validator`). [`oracle_account`] builds the matching
+//! oracle account: its secp256k1 authority is the mock key and its gateway URI points at
+//! the server.
+//!
+//! Values are derived from the key and the commit, so a commit always reveals the same
+//! value. The signature covers `sha256(randomness key || slothash || value)`, which is the
+//! mock's own scheme: only a program that checks reveals against the oracle's
+//! `secp_authority` (not the deployed Switchboard program) will accept it.
+
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use libsecp256k1::{Message, PublicKey, SecretKey};
+use solana_sdk::{hash::hashv, pubkey::Pubkey, rent::Rent};
+use std::path::Path;
+use std::sync::Arc;
+use switchboard_on_demand::Discriminator;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use super::accounts::OracleAccountData;
+use super::{GatewayRevealRequest, GatewayRevealResponse, REVEAL_PATH};
+
+/// Oracle signing key behind the mock gateway
+pub struct MockOracle {
+    secret: SecretKey,
+}
+
+impl MockOracle {
+    pub fn new(secret: &[u8; 32]) -> Result<Self> {
+        let secret = SecretKey::parse(secret).map_err(|e| anyhow!("Invalid secp256k1 secret key: {:?}", e))?;
+        Ok(Self { secret })
+    }
+
+    pub fn random() -> Self {
+        Self {
+            secret: SecretKey::random(&mut rand::thread_rng()),
+        }
+    }
+
+    /// Load the hex secret key in `path`, generating and saving one if the file is missing
+    pub fn load_or_create(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            let oracle = Self::random();
+            std::fs::write(path, hex::encode(oracle.secret.serialize()))
+                .with_context(|| format!("Failed to write mock oracle key {}", path.display()))?;
+            return Ok(oracle);
+        }
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read mock oracle key {}", path.display()))?;
+        let bytes: [u8; 32] = hex::decode(contents.trim())?
+            .try_into()
+            .map_err(|_| anyhow!("Mock oracle key {} must be 32 hex-encoded bytes", path.display()))?;
+        Self::new(&bytes)
+    }
+
+    /// Uncompressed public key without its 0x04 prefix, as stored in oracle accounts
+    pub fn secp_authority(&self) -> [u8; 64] {
+        let public = PublicKey::from_secret_key(&self.secret).serialize();
+        public[1..].try_into().expect("uncompressed key is 65 bytes")
+    }
+
+    /// Value and signature for the commit of `randomness_key` to `slothash`
+    pub fn reveal(&self, randomness_key: &Pubkey, slothash: &[u8; 32]) -> GatewayRevealResponse {
+        let value = hashv(&[&self.secret.serialize(), randomness_key.as_ref(), slothash]).to_bytes();
+        let (signature, recovery_id) = libsecp256k1::sign(&reveal_message(randomness_key, slothash, &value), &self.secret);
+        GatewayRevealResponse {
+            signature: base64::engine::general_purpose::STANDARD.encode(signature.serialize()),
+            recovery_id: recovery_id.serialize(),
+            value,
+        }
+    }
+
+    fn handle_reveal(&self, body: &[u8]) -> Result<GatewayRevealResponse> {
+        let request: GatewayRevealRequest = serde_json::from_slice(body)?;
+        let randomness_key: [u8; 32] = hex::decode(&request.randomness_key)?
+            .try_into()
+            .map_err(|_| anyhow!("randomness_key must be 32 hex-encoded bytes"))?;
+        let slothash: [u8; 32] = request
+            .slothash
+            .try_into()
+            .map_err(|_| anyhow!("slothash must be 32 bytes"))?;
+        eprintln!("Revealing {} for slot {}", Pubkey::new_from_array(randomness_key), request.slot);
+        Ok(self.reveal(&Pubkey::new_from_array(randomness_key), &slothash))
+    }
+}
+
+/// Message the mock oracle signs for a reveal
+fn reveal_message(randomness_key: &Pubkey, slothash: &[u8; 32], value: &[u8; 32]) -> Message {
+    Message::parse(&hashv(&[randomness_key.as_ref(), slothash, value]).to_bytes())
+}
+
+/// Oracle account data whose key is `secp_authority`, on `queue`, with its gateway at
+/// `gateway_url`
+pub fn oracle_account(secp_authority: &[u8; 64], queue: &Pubkey, gateway_url: &str, valid_until: i64) -> Result<Vec<u8>> {
+    let mut oracle: OracleAccountData = bytemuck::Zeroable::zeroed();
+    if gateway_url.len() > oracle.gateway_uri.len() {
+        return Err(anyhow!("Gateway URL longer than {} bytes: {}", oracle.gateway_uri.len(), gateway_url));
+    }
+    oracle.gateway_uri[..gateway_url.len()].copy_from_slice(gateway_url.as_bytes());
+    oracle.secp_authority = *secp_authority;
+    oracle.enclave.secp256k1_signer = *secp_authority;
+    oracle.enclave.valid_until = valid_until;
+    oracle.queue = queue.to_bytes().into();
+
+    let mut data = OracleAccountData::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(bytemuck::bytes_of(&oracle));
+    Ok(data)
+}
+
+/// Write `data` as an account file for `solana-test-validator --account <ADDRESS> <FILE>`
+pub fn write_account_file(path: &Path, address: &Pubkey, owner: &Pubkey, data: &[u8]) -> Result<()> {
+    let account = serde_json::json!({
+        "pubkey": address.to_string(),
+        "account": {
+            "lamports": Rent::default().minimum_balance(data.len()),
+            "data": [base64::engine::general_purpose::STANDARD.encode(data), "base64"],
+            "owner": owner.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+            "space": data.len(),
+        },
+    });
+    std::fs::write(path, serde_json::to_string_pretty(&account)?)
+        .with_context(|| format!("Failed to write account file {}", path.display()))
+}
+
+/// Serve the gateway on `listener` until the process is stopped
+pub async fn serve(listener: TcpListener, oracle: Arc<MockOracle>) -> Result<()> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let oracle = oracle.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &oracle).await {
+                eprintln!("Mock gateway request failed: {:#}", e);
+            }
+        });
+    }
+}
+
+/// Answer one HTTP/1.1 request and close the connection
+async fn handle_connection(mut stream: TcpStream, oracle: &MockOracle) -> Result<()> {
+    let (read, mut write) = stream.split();
+    let mut reader = BufReader::new(read);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 || header.trim_end().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse()?;
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await?;
+
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("POST"), Some(REVEAL_PATH)) => match oracle.handle_reveal(&body) {
+            Ok(reveal) => ("200 OK", serde_json::to_string(&reveal)?),
+            Err(e) => ("400 Bad Request", e.to_string()),
+        },
+        // Health probes only check that the gateway answers
+        (Some("GET"), Some(_)) => ("200 OK", "open-lotto mock gateway".to_string()),
+        _ => ("404 Not Found", String::new()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    write.write_all(response.as_bytes()).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::{Cluster, Network};
+    use crate::signer::Wallet;
+    use crate::switchboard::accounts::{self, RandomnessAccountData};
+    use litesvm::LiteSVM;
+    use open_lotto_client::{AccountSerialize, OpenLotto, Pot, PotManager, Ticket, SWITCHBOARD_ON_DEMAND_PROGRAM_ID};
+    use serde_json::json;
+    use solana_program_runtime::declare_process_instruction;
+    use solana_sdk::{account::Account, clock::Clock, instruction::InstructionError, program_pack::Pack, signature::{Keypair, Signer}};
+    use std::sync::Mutex;
+
+    const SLOTHASH: [u8; 32] = [7; 32];
+
+    // Stand-in for Switchboard's randomnessReveal: checks the gateway's signature against
+    // the oracle account's secp256k1 authority, as the mock signs it, then writes the value
+    // and the current slot as the reveal slot. Accounts are those of the CLI's reveal
+    // instruction (randomness, then oracle); the data is the discriminator, signature,
+    // recovery id and value.
+    declare_process_instruction!(MockRandomnessReveal, 150, |invoke_context| {
+        let slot = invoke_context.get_sysvar_cache().get_clock()?.slot;
+        let transaction_context = &invoke_context.transaction_context;
+        let instruction_context = transaction_context.get_current_instruction_context()?;
+        let data = instruction_context.get_instruction_data();
+        let signature = libsecp256k1::Signature::parse_standard_slice(&data[8..72])
+            .map_err(|_| InstructionError::InvalidInstructionData)?;
+        let recovery_id = libsecp256k1::RecoveryId::parse(data[72]).map_err(|_| InstructionError::InvalidInstructionData)?;
+        let value: [u8; 32] = data[73..105].try_into().map_err(|_| InstructionError::InvalidInstructionData)?;
+
+        let oracle = instruction_context.try_borrow_instruction_account(transaction_context, 1)?;
+        let secp_authority = accounts::oracle(oracle.get_data())
+            .map_err(|_| InstructionError::InvalidAccountData)?
+            .secp_authority;
+        drop(oracle);
+        let mut randomness = instruction_context.try_borrow_instruction_account(transaction_context, 0)?;
+        let slothash: [u8; 32] = randomness.get_data()[72..104].try_into().unwrap();
+        let message = reveal_message(randomness.get_key(), &slothash, &value);
+        let signer = libsecp256k1::recover(&message, &signature, &recovery_id)
+            .map_err(|_| InstructionError::InvalidArgument)?;
+        if signer.serialize()[1..] != secp_authority {
+            return Err(InstructionError::MissingRequiredSignature);
+        }
+
+        // reveal_slot and value follow seed_slot and oracle
+        let data = randomness.get_data_mut()?;
+        data[144..152].copy_from_slice(&slot.to_le_bytes());
+        data[152..184].copy_from_slice(&value);
+        Ok(())
+    });
+
+    fn network() -> Network {
+        Network {
+            cluster: Cluster::Localnet,
+            rpc_url: "http://127.0.0.1:8899".to_string(),
+            program_id: open_lotto_client::ID,
+            switchboard_program_id: SWITCHBOARD_ON_DEMAND_PROGRAM_ID,
+            switchboard_queue: Pubkey::new_unique(),
+        }
+    }
+
+    /// Randomness account committed to `oracle` at slot 1_000, not yet revealed
+    fn randomness_account(oracle: &Pubkey) -> Vec<u8> {
+        let mut randomness: RandomnessAccountData = bytemuck::Zeroable::zeroed();
+        randomness.seed_slothash = SLOTHASH;
+        randomness.seed_slot = 1_000;
+        randomness.oracle = oracle.to_bytes().into();
+        let mut data = RandomnessAccountData::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(&randomness));
+        data
+    }
+
+    fn account(owner: &Pubkey, data: Vec<u8>) -> Account {
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: *owner,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    fn native_program() -> Account {
+        Account {
+            lamports: 1,
+            data: vec![],
+            owner: solana_sdk::native_loader::id(),
+            executable: true,
+            rent_epoch: 0,
+        }
+    }
+
+    fn program_account<A: AccountSerialize>(state: &A) -> Account {
+        let mut data = Vec::new();
+        state.try_serialize(&mut data).unwrap();
+        account(&open_lotto_client::ID, data)
+    }
+
+    fn token_account(mint: &Pubkey, owner: &Pubkey) -> Account {
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        spl_token::state::Account {
+            mint: *mint,
+            owner: *owner,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        }
+        .pack_into_slice(&mut data);
+        account(&spl_token::id(), data)
+    }
+
+    async fn start_gateway(oracle: MockOracle) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, Arc::new(oracle)));
+        url
+    }
+
+    #[test]
+    fn reveal_is_signed_by_the_oracle_key() {
+        let oracle = MockOracle::new(&[1; 32]).unwrap();
+        let randomness = Pubkey::new_unique();
+        let reveal = oracle.reveal(&randomness, &SLOTHASH);
+
+        let signature = base64::engine::general_purpose::STANDARD.decode(&reveal.signature).unwrap();
+        let signer = libsecp256k1::recover(
+            &reveal_message(&randomness, &SLOTHASH, &reveal.value),
+            &libsecp256k1::Signature::parse_standard_slice(&signature).unwrap(),
+            &libsecp256k1::RecoveryId::parse(reveal.recovery_id).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(signer.serialize()[1..], oracle.secp_authority());
+
+        // Same commit, same value; another commit, another value
+        assert_eq!(oracle.reveal(&randomness, &SLOTHASH).value, reveal.value);
+        assert_ne!(oracle.reveal(&randomness, &[8; 32]).value, reveal.value);
+    }
+
+    #[test]
+    fn oracle_account_matches_the_mock_key() {
+        let oracle = MockOracle::random();
+        let queue = Pubkey::new_unique();
+        let data = oracle_account(&oracle.secp_authority(), &queue, "http://127.0.0.1:8787", i64::MAX).unwrap();
+
+        let decoded = accounts::oracle(&data).unwrap();
+        assert_eq!(decoded.gateway_uri().as_deref(), Some("http://127.0.0.1:8787"));
+        assert_eq!(decoded.secp_authority, oracle.secp_authority());
+        assert_eq!(decoded.queue.to_bytes(), queue.to_bytes());
+        assert_eq!(decoded.enclave.valid_until, i64::MAX);
+
+        assert!(oracle_account(&oracle.secp_authority(), &queue, &"x".repeat(65), i64::MAX).is_err());
+    }
+
+    #[tokio::test]
+    async fn gateway_answers_probes_and_rejects_bad_requests() {
+        let url = start_gateway(MockOracle::random()).await;
+        let client = reqwest::Client::new();
+
+        assert!(super::super::oracles::probe_gateway(&client, &url).await.is_ok());
+        let response = client
+            .post(format!("{}{}", url, REVEAL_PATH))
+            .json(&json!({ "slothash": [1, 2], "randomness_key": "00", "slot": 1, "rpc": "" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reveal_and_settle_through_mock_gateway() {
+        let mock_oracle = MockOracle::new(&[3; 32]).unwrap();
+        let secp_authority = mock_oracle.secp_authority();
+        let gateway = start_gateway(MockOracle::new(&[3; 32]).unwrap()).await;
+
+        let network = network();
+        let program = OpenLotto::new(network.program_id);
+        // Separate fee payer, as on a keeper host
+        let (authority, fee_payer) = (Keypair::new(), Keypair::new());
+        let (oracle, randomness) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (pot_address, manager_address, mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let expected = mock_oracle.reveal(&randomness, &SLOTHASH);
+        let winner_index = u64::from_le_bytes(expected.value[..8].try_into().unwrap()) % 4;
+        let winner = Pubkey::new_unique();
+
+        let mut svm = LiteSVM::new();
+        let program_path = concat!(env!("CARGO_MANIFEST_DIR"), "/../target/deploy/open_lotto.so");
+        svm.add_program_from_file(program.program_id, program_path)
+            .expect("build the program with `anchor build` first");
+        svm.add_builtin(SWITCHBOARD_ON_DEMAND_PROGRAM_ID, MockRandomnessReveal::vm);
+        // add_builtin leaves the program account to the BPF loader, which won't run builtins
+        svm.set_account(SWITCHBOARD_ON_DEMAND_PROGRAM_ID, native_program()).unwrap();
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.slot = 1_002;
+        svm.set_sysvar(&clock);
+        svm.airdrop(&authority.pubkey(), 1_000_000_000).unwrap();
+        svm.airdrop(&fee_payer.pubkey(), 1_000_000_000).unwrap();
+
+        let oracle_data = oracle_account(&secp_authority, &network.switchboard_queue, &gateway, i64::MAX).unwrap();
+        let mut mint_data = vec![0; spl_token::state::Mint::LEN];
+        spl_token::state::Mint { is_initialized: true, decimals: 6, ..Default::default() }.pack_into_slice(&mut mint_data);
+        // A pot of four tickets drawn with the randomness, its manager and winning ticket
+        let pot = Pot {
+            pot_manager: manager_address,
+            total_participants: 4,
+            start_timestamp: 0,
+            end_timestamp: 1,
+            winning_slot: 0,
+            randomness_account: randomness,
+            wager: 0,
+            settled: false,
+            bounties_paid: 0,
+            prize_paid: false,
+            payout_reserve: 0,
+            seed_slot: 1_000,
+            draw_slot: 1_001,
+        };
+        let manager = PotManager {
+            authority: Pubkey::new_unique(),
+            treasury: program.treasury_address(),
+            token_mint: mint,
+            rent: 0,
+            last_random_number: 0,
+            timestamps: (1, 2),
+            bump: 255,
+            wager: 0,
+            wager_balance: 0,
+            crank_bounty: 0,
+            auto_payout: false,
+            name: "daily".to_string(),
+        };
+        let ticket = Ticket { participant: winner, index: winner_index };
+        for (address, account) in [
+            (randomness, account(&SWITCHBOARD_ON_DEMAND_PROGRAM_ID, randomness_account(&oracle))),
+            (oracle, account(&SWITCHBOARD_ON_DEMAND_PROGRAM_ID, oracle_data)),
+            (mint, account(&spl_token::id(), mint_data)),
+            (program.treasury_address(), token_account(&mint, &manager.authority)),
+            (pot_address, program_account(&pot)),
+            (manager_address, program_account(&manager)),
+            (program.ticket_address(&pot_address, winner_index), program_account(&ticket)),
+        ] {
+            svm.set_account(address, account).unwrap();
+        }
+
+        let svm = Arc::new(Mutex::new(svm));
+        let rpc_client = crate::litesvm_rpc::rpc_client(svm.clone());
+        let payer = Wallet::new(Box::new(authority), Some(Box::new(fee_payer)));
+
+        let fetched = super::super::fetch_randomness_reveal(&gateway, &randomness, &network.rpc_url, &rpc_client)
+            .await
+            .unwrap();
+        assert_eq!(fetched.value, expected.value);
+        assert_eq!(fetched.signature, expected.signature);
+
+        let settled = crate::settle(&rpc_client, &program, &payer, &pot_address, &randomness, &network)
+            .await
+            .unwrap();
+        assert_eq!(settled.winner_index, winner_index);
+        assert_eq!(settled.winning_ticket, program.ticket_address(&pot_address, winner_index));
+        assert_eq!(settled.winner, Some(winner));
+
+        let revealed = accounts::randomness(&svm.lock().unwrap().get_account(&randomness).unwrap().data).unwrap();
+        assert_eq!((revealed.reveal_slot, revealed.value), (1_002, expected.value));
+    }
+}
@@ -0,0 +1,242 @@
+//! Oracle health checks and selection
+//!
+//! Before committing, every oracle on the queue is screened: its enclave key must stay
+//! valid for at least [`KEY_EXPIRY_MARGIN_SECS`] and its gateway must answer HTTP. The
+//! healthy set is cached per queue in `~/.config/open-lotto/oracles-<queue>.json` for
+//! [`CACHE_TTL_SECS`], and oracles are tried in a random order weighted towards fast
+//! gateways, so load spreads over the queue instead of always hitting its first entry.
+
+use anyhow::{anyhow, Context, Result};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use super::accounts;
+use crate::cluster::Network;
+use crate::keeper::pubkey_string;
+use crate::metrics;
+use crate::unix_now;
+
+/// An oracle whose key expires sooner than this is skipped: the commit would land on a
+/// key that may be gone by reveal time
+const KEY_EXPIRY_MARGIN_SECS: i64 = 300;
+/// How long a screened set of oracles is trusted before re-checking the queue
+const CACHE_TTL_SECS: u64 = 300;
+/// Gateways slower than this to answer are treated as down
+const GATEWAY_TIMEOUT: Duration = Duration::from_secs(3);
+/// Queue entries screened per run (matches the old walk over the queue)
+const MAX_ORACLES: usize = 20;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OracleHealth {
+    #[serde(with = "pubkey_string")]
+    pub oracle: Pubkey,
+    pub gateway: String,
+    /// Unix timestamp the oracle's enclave key expires at
+    pub key_valid_until: i64,
+    pub latency_ms: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct OracleCache {
+    #[serde(with = "pubkey_string")]
+    queue: Pubkey,
+    checked_at: u64,
+    oracles: Vec<OracleHealth>,
+}
+
+impl OracleCache {
+    fn path(queue: &Pubkey) -> Result<PathBuf> {
+        let home = dirs::home_dir().ok_or_else(|| anyhow!("Unable to find home directory"))?;
+        Ok(home.join(".config").join("open-lotto").join(format!("oracles-{}.json", queue)))
+    }
+
+    /// Cached oracles that are still fresh, or `None` if the cache is missing or stale
+    fn load(queue: &Pubkey, now: u64) -> Option<Vec<OracleHealth>> {
+        let contents = std::fs::read_to_string(Self::path(queue).ok()?).ok()?;
+        let cache: Self = serde_json::from_str(&contents).ok()?;
+        if cache.queue != *queue || now.saturating_sub(cache.checked_at) > CACHE_TTL_SECS {
+            return None;
+        }
+        let oracles: Vec<_> = cache.oracles.into_iter().filter(|o| key_usable(o.key_valid_until, now)).collect();
+        (!oracles.is_empty()).then_some(oracles)
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = Self::path(&self.queue)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write oracle cache {}", path.display()))
+    }
+}
+
+/// Healthy oracles on the network's queue, from the cache when it is fresh
+pub async fn healthy_oracles(rpc_client: &RpcClient, network: &Network) -> Result<Vec<OracleHealth>> {
+    let now = unix_now()?;
+    if let Some(oracles) = OracleCache::load(&network.switchboard_queue, now) {
+        return Ok(oracles);
+    }
+
+    let oracles = screen_queue(rpc_client, network, now).await?;
+    if oracles.is_empty() {
+        return Err(anyhow!("No healthy oracles on queue {}", network.switchboard_queue));
+    }
+    OracleCache {
+        queue: network.switchboard_queue,
+        checked_at: now,
+        oracles: oracles.clone(),
+    }
+    .save()?;
+    Ok(oracles)
+}
+
+/// Drop `oracle` from the cached healthy set after it failed us
+pub fn forget(queue: &Pubkey, oracle: &Pubkey) {
+    metrics::oracle_failure(oracle);
+    let Ok(path) = OracleCache::path(queue) else { return };
+    let Some(mut cache) = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<OracleCache>(&contents).ok())
+    else {
+        return;
+    };
+    cache.oracles.retain(|o| o.oracle != *oracle);
+    let _ = cache.save();
+}
+
+/// Read every oracle on the queue and keep those with a usable key and a live gateway
+async fn screen_queue(rpc_client: &RpcClient, network: &Network, now: u64) -> Result<Vec<OracleHealth>> {
+    let queue = accounts::queue(&rpc_client.get_account_data(&network.switchboard_queue).await?)?;
+    let len = (queue.oracle_keys_len as usize).min(queue.oracle_keys.len());
+    let keys: Vec<Pubkey> = queue.oracle_keys[..len]
+        .iter()
+        .map(|k| Pubkey::new_from_array(k.to_bytes()))
+        .filter(|k| *k != Pubkey::default())
+        .take(MAX_ORACLES)
+        .collect();
+    eprintln!("Screening {} oracles on queue {}", keys.len(), network.switchboard_queue);
+
+    let mut candidates = Vec::new();
+    for (oracle, account) in keys.iter().zip(rpc_client.get_multiple_accounts(&keys).await?) {
+        let Some(account) = account else { continue };
+        if account.owner != network.switchboard_program_id {
+            continue;
+        }
+        let Ok(data) = accounts::oracle(&account.data) else { continue };
+        let key_valid_until = data.enclave.valid_until;
+        if !key_usable(key_valid_until, now) {
+            eprintln!("  {} skipped: enclave key expires at {}", oracle, key_valid_until);
+            continue;
+        }
+        let Some(gateway) = data.gateway_uri() else {
+            eprintln!("  {} skipped: no gateway", oracle);
+            continue;
+        };
+        candidates.push((*oracle, gateway, key_valid_until));
+    }
+
+    // Probe all gateways concurrently
+    let client = reqwest::Client::builder().timeout(GATEWAY_TIMEOUT).build()?;
+    let mut probes = tokio::task::JoinSet::new();
+    for (oracle, gateway, key_valid_until) in candidates {
+        let client = client.clone();
+        probes.spawn(async move {
+            let latency = probe_gateway(&client, &gateway).await;
+            (oracle, gateway, key_valid_until, latency)
+        });
+    }
+
+    let mut healthy = Vec::new();
+    while let Some(probe) = probes.join_next().await {
+        let (oracle, gateway, key_valid_until, latency) = probe?;
+        match latency {
+            Ok(latency) => healthy.push(OracleHealth {
+                oracle,
+                gateway,
+                key_valid_until,
+                latency_ms: latency.as_millis() as u64,
+            }),
+            Err(e) => eprintln!("  {} skipped: gateway {} unreachable: {}", oracle, gateway, e),
+        }
+    }
+    eprintln!("{} healthy oracles", healthy.len());
+    Ok(healthy)
+}
+
+/// Time for the gateway to answer at all; any HTTP status counts as up
+pub async fn probe_gateway(client: &reqwest::Client, gateway: &str) -> Result<Duration> {
+    let start = Instant::now();
+    client.get(gateway).send().await?;
+    Ok(start.elapsed())
+}
+
+fn key_usable(key_valid_until: i64, now: u64) -> bool {
+    key_valid_until > now as i64 + KEY_EXPIRY_MARGIN_SECS
+}
+
+/// Order `oracles` by weighted random sampling without replacement, favouring low
+/// gateway latency
+pub fn weighted_order<R: Rng>(mut oracles: Vec<OracleHealth>, rng: &mut R) -> Vec<OracleHealth> {
+    let mut ordered = Vec::with_capacity(oracles.len());
+    while !oracles.is_empty() {
+        let weights: Vec<f64> = oracles.iter().map(|o| 1.0 / (o.latency_ms as f64 + 50.0)).collect();
+        let mut pick = rng.gen::<f64>() * weights.iter().sum::<f64>();
+        let mut index = oracles.len() - 1;
+        for (i, weight) in weights.iter().enumerate() {
+            if pick < *weight {
+                index = i;
+                break;
+            }
+            pick -= weight;
+        }
+        ordered.push(oracles.swap_remove(index));
+    }
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn health(latency_ms: u64) -> OracleHealth {
+        OracleHealth {
+            oracle: Pubkey::new_unique(),
+            gateway: "https://gateway.example.com".to_string(),
+            key_valid_until: i64::MAX,
+            latency_ms,
+        }
+    }
+
+    #[test]
+    fn key_needs_margin_before_expiry() {
+        assert!(key_usable(10_000, 1_000));
+        assert!(!key_usable(1_000 + KEY_EXPIRY_MARGIN_SECS, 1_000));
+        assert!(!key_usable(0, 1_000));
+    }
+
+    #[test]
+    fn weighted_order_keeps_every_oracle_once() {
+        let oracles = vec![health(10), health(200), health(3_000)];
+        let mut ordered = weighted_order(oracles.clone(), &mut StdRng::seed_from_u64(7));
+        ordered.sort_by_key(|o| o.latency_ms);
+        assert_eq!(ordered, oracles);
+    }
+
+    #[test]
+    fn weighted_order_prefers_fast_gateways() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let fast_first = (0..1_000)
+            .filter(|_| weighted_order(vec![health(3_000), health(10)], &mut rng)[0].latency_ms == 10)
+            .count();
+        // Weights 1/60 vs 1/3050: the fast gateway should lead ~98% of the time
+        assert!(fast_first > 950, "fast gateway first only {} times", fast_first);
+        // ...but the slow one still gets some traffic
+        assert!(fast_first < 1_000);
+    }
+}
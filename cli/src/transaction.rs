@@ -0,0 +1,554 @@
+//! Building and sending transactions
+//!
+//! Every transaction the CLI sends goes through [`send`], which prepends the compute
+//! budget chosen with `--compute-unit-limit` and `--priority-fee` and hands the signed
+//! transaction to [`sender::deliver`] to land. The budget is set once at startup with
+//! [`configure`] (a keeper servicing several managers overrides it per manager with
+//! [`with_budget`]), and `--nonce` with [`use_nonce`].
+//!
+//! Admin commands can instead [`build`] a transaction and hand it over as base64
+//! (`--sign-only`, `--dump-transaction`) for an air-gapped signer or a multisig; the
+//! partially signed copies are [`merge`]d back together by `submit`. With `--dry-run`
+//! they are [`simulate`]d instead of sent.
+
+pub mod sender;
+
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig},
+};
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
+    message::Message,
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+    transaction::Transaction,
+};
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::errors;
+use crate::metrics;
+use crate::output::{BalanceChange, SimulatedTransaction};
+use crate::signer::Wallet;
+use sender::{Expired, Landed, Validity};
+
+/// Percentile of recent prioritization fees used by `--priority-fee auto`
+const AUTO_FEE_PERCENTILE: usize = 75;
+
+/// Times [`send`] rebuilds a transaction whose blockhash expired before it landed
+const SEND_ATTEMPTS: usize = 3;
+
+/// Price paid per compute unit, in micro-lamports
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PriorityFee {
+    #[default]
+    None,
+    Fixed(u64),
+    /// Derived from `getRecentPrioritizationFees` for the accounts the transaction writes
+    Auto,
+}
+
+impl FromStr for PriorityFee {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(PriorityFee::Auto);
+        }
+        match s.parse::<u64>() {
+            Ok(0) => Ok(PriorityFee::None),
+            Ok(fee) => Ok(PriorityFee::Fixed(fee)),
+            Err(_) => Err(format!("expected micro-lamports per compute unit or `auto`, got '{}'", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ComputeBudget {
+    pub unit_limit: Option<u32>,
+    pub priority_fee: PriorityFee,
+    /// Upper bound for a fee derived with [`PriorityFee::Auto`]
+    pub max_priority_fee: Option<u64>,
+}
+
+static COMPUTE_BUDGET: OnceLock<ComputeBudget> = OnceLock::new();
+
+/// Set the compute budget applied to every transaction; only the first call counts
+pub fn configure(budget: ComputeBudget) {
+    let _ = COMPUTE_BUDGET.set(budget);
+}
+
+tokio::task_local! {
+    static SCOPED_BUDGET: ComputeBudget;
+}
+
+/// Run `f` with `budget` in place of the configured compute budget
+pub async fn with_budget<F: Future>(budget: ComputeBudget, f: F) -> F::Output {
+    SCOPED_BUDGET.scope(budget, f).await
+}
+
+fn compute_budget() -> ComputeBudget {
+    SCOPED_BUDGET
+        .try_with(|budget| *budget)
+        .unwrap_or_else(|_| COMPUTE_BUDGET.get().copied().unwrap_or_default())
+}
+
+static NONCE: OnceLock<Pubkey> = OnceLock::new();
+
+/// Build every transaction on durable nonce account `nonce`, whose authority must be the
+/// fee payer; only the first call counts
+pub fn use_nonce(nonce: Pubkey) {
+    let _ = NONCE.set(nonce);
+}
+
+/// Number of instructions [`send`] puts in front of the caller's (nonce advance, compute
+/// budget), so instruction indices in transaction errors can be mapped back
+pub fn leading_instruction_count() -> usize {
+    let budget = compute_budget();
+    NONCE.get().is_some() as usize
+        + budget.unit_limit.is_some() as usize
+        + (budget.priority_fee != PriorityFee::None) as usize
+}
+
+/// Sign `instructions` with the wallet and `extra_signers` and send them with the
+/// configured compute budget, waiting for confirmation
+///
+/// The wallet's fee payer pays the fee. Only signers the instructions require sign, so
+/// the authority is not asked for a signature it isn't needed for. Program errors are
+/// named by [`errors::explain`]; the `ClientError` stays the source so callers can
+/// downcast to it. A transaction that expires unlanded is rebuilt on a fresh blockhash
+/// and signed again, which cannot double-execute since the expired one can no longer land.
+pub async fn send(
+    rpc_client: &RpcClient,
+    wallet: &Wallet,
+    extra_signers: &[&dyn Signer],
+    instructions: &[Instruction],
+) -> Result<Signature> {
+    let mut attempt = 1;
+    loop {
+        let (mut transaction, validity) = assemble(rpc_client, wallet, instructions, None, NONCE.get()).await?;
+        let signers = required_signers(&transaction, wallet.signers(extra_signers));
+        transaction.try_sign(&signers, transaction.message.recent_blockhash)?;
+        match sender::deliver(rpc_client, &transaction, validity).await {
+            Ok(landed) => {
+                eprintln!("{}", landed);
+                return Ok(landed.signature);
+            }
+            Err(e) if attempt < SEND_ATTEMPTS && e.is::<Expired>() => {
+                eprintln!("{}; signing it again", e);
+                metrics::resigned();
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Land a transaction signed elsewhere, durable when it advances a nonce account
+pub async fn submit(rpc_client: &RpcClient, transaction: &Transaction) -> Result<Landed> {
+    sender::deliver(rpc_client, transaction, Validity::of(transaction)).await
+}
+
+/// Unsigned transaction for `instructions` with the configured compute budget, paid by
+/// the wallet's fee payer, on `blockhash`, else on the `--nonce` account's nonce, else
+/// on the latest blockhash
+pub async fn build(
+    rpc_client: &RpcClient,
+    wallet: &Wallet,
+    instructions: &[Instruction],
+    blockhash: Option<Hash>,
+) -> Result<Transaction> {
+    Ok(assemble(rpc_client, wallet, instructions, blockhash, NONCE.get()).await?.0)
+}
+
+async fn assemble(
+    rpc_client: &RpcClient,
+    wallet: &Wallet,
+    instructions: &[Instruction],
+    blockhash: Option<Hash>,
+    nonce: Option<&Pubkey>,
+) -> Result<(Transaction, Validity)> {
+    let fee_payer = wallet.fee_payer().pubkey();
+    let mut all_instructions: Vec<Instruction> = nonce.map(|nonce| sender::advance_nonce(nonce, &fee_payer)).into_iter().collect();
+    all_instructions.extend(budget_instructions(rpc_client, instructions).await?);
+    all_instructions.extend_from_slice(instructions);
+
+    let (blockhash, validity) = match (nonce, blockhash) {
+        (Some(nonce), Some(blockhash)) => (blockhash, Validity::Nonce(*nonce)),
+        (Some(nonce), None) => (sender::durable_nonce(rpc_client, nonce).await?, Validity::Nonce(*nonce)),
+        (None, Some(blockhash)) => (blockhash, Validity::Blockhash),
+        (None, None) => {
+            let (blockhash, last_valid) = rpc_client
+                .get_latest_blockhash_with_commitment(rpc_client.commitment())
+                .await?;
+            (blockhash, Validity::BlockHeight(last_valid))
+        }
+    };
+    let mut message = Message::new(&all_instructions, Some(&fee_payer));
+    message.recent_blockhash = blockhash;
+    Ok((Transaction::new_unsigned(message), validity))
+}
+
+/// Add the signatures `signers` can provide that `transaction` still lacks
+///
+/// Signers given as a bare pubkey (`--keypair <PUBKEY>` with `--sign-only`) leave their
+/// slot empty for someone else to fill.
+pub fn partial_sign(transaction: &mut Transaction, signers: Vec<&dyn Signer>) -> Result<()> {
+    let missing = missing_signers(transaction);
+    let signers: Vec<&dyn Signer> = required_signers(transaction, signers)
+        .into_iter()
+        .filter(|signer| missing.contains(&signer.pubkey()))
+        .collect();
+    transaction.try_partial_sign(&signers, transaction.message.recent_blockhash)?;
+    Ok(())
+}
+
+/// Simulate `instructions` as [`send`] would send them, recording the balances of the
+/// `watched` accounts before and after
+///
+/// Nothing is signed: the simulation skips signature verification, so a hardware wallet
+/// is not asked to sign a transaction that is never sent.
+pub async fn simulate(
+    rpc_client: &RpcClient,
+    wallet: &Wallet,
+    label: &str,
+    instructions: &[Instruction],
+    watched: &[(String, Pubkey)],
+) -> Result<SimulatedTransaction> {
+    // Without the nonce advance: the simulation replaces the blockhash anyway
+    let (transaction, _) = assemble(rpc_client, wallet, instructions, None, None).await?;
+    let addresses: Vec<Pubkey> = watched.iter().map(|(_, address)| *address).collect();
+    let before = rpc_client.get_multiple_accounts(&addresses).await?;
+
+    let config = RpcSimulateTransactionConfig {
+        sig_verify: false,
+        replace_recent_blockhash: true,
+        accounts: Some(RpcSimulateTransactionAccountsConfig {
+            encoding: None,
+            addresses: addresses.iter().map(Pubkey::to_string).collect(),
+        }),
+        ..RpcSimulateTransactionConfig::default()
+    };
+    let result = rpc_client.simulate_transaction_with_config(&transaction, config).await?.value;
+    let logs = result.logs.unwrap_or_default();
+    let after = result.accounts.unwrap_or_default();
+
+    let balances = watched
+        .iter()
+        .zip(before)
+        .enumerate()
+        .map(|(i, ((label, address), before))| {
+            let after = after.get(i).cloned().flatten();
+            let after_data = after.as_ref().and_then(|account| account.data.decode());
+            let after_owner = after.as_ref().and_then(|account| Pubkey::from_str(&account.owner).ok());
+            BalanceChange {
+                label: label.clone(),
+                address: *address,
+                lamports_before: before.as_ref().map_or(0, |account| account.lamports),
+                lamports_after: after.as_ref().map_or(0, |account| account.lamports),
+                tokens_before: before.as_ref().and_then(|account| token_amount(&account.owner, &account.data)),
+                tokens_after: after_owner.zip(after_data).and_then(|(owner, data)| token_amount(&owner, &data)),
+            }
+        })
+        .collect();
+
+    let error = match result.err {
+        Some(error) => Some(match errors::describe(rpc_client, &transaction.message, &error, &logs).await {
+            Some(program_error) => program_error.to_string(),
+            None => error.to_string(),
+        }),
+        None => None,
+    };
+
+    Ok(SimulatedTransaction {
+        label: label.to_string(),
+        error,
+        units_consumed: result.units_consumed,
+        balances,
+        logs,
+        skipped: None,
+    })
+}
+
+/// Token balance of an SPL token account: the u64 after its mint and owner
+fn token_amount(owner: &Pubkey, data: &[u8]) -> Option<u64> {
+    const TOKEN_ACCOUNT_LEN: usize = 165;
+
+    if *owner != spl_token::id() || data.len() != TOKEN_ACCOUNT_LEN {
+        return None;
+    }
+    Some(u64::from_le_bytes(data[64..72].try_into().ok()?))
+}
+
+/// Keep the signers `transaction` needs, dropping the rest
+fn required_signers<'a>(transaction: &Transaction, signers: Vec<&'a dyn Signer>) -> Vec<&'a dyn Signer> {
+    let message = &transaction.message;
+    let required = &message.account_keys[..message.header.num_required_signatures as usize];
+    signers
+        .into_iter()
+        .filter(|signer| required.contains(&signer.pubkey()))
+        .collect()
+}
+
+/// Required signers whose signature is still empty
+pub fn missing_signers(transaction: &Transaction) -> Vec<Pubkey> {
+    transaction
+        .message
+        .account_keys
+        .iter()
+        .zip(&transaction.signatures)
+        .filter(|(_, signature)| **signature == Signature::default())
+        .map(|(pubkey, _)| *pubkey)
+        .collect()
+}
+
+/// Combine partially signed copies of the same transaction into one
+pub fn merge(transactions: Vec<Transaction>) -> Result<Transaction> {
+    let mut copies = transactions.into_iter();
+    let mut merged = copies.next().ok_or_else(|| anyhow!("No transaction to merge"))?;
+    for copy in copies {
+        if copy.message != merged.message {
+            return Err(anyhow!(
+                "Transactions differ; every signer must sign the same instructions and blockhash"
+            ));
+        }
+        for (merged_signature, signature) in merged.signatures.iter_mut().zip(copy.signatures) {
+            if signature != Signature::default() {
+                *merged_signature = signature;
+            }
+        }
+    }
+    Ok(merged)
+}
+
+/// Wire format of transactions handed between machines: bincode, base64 encoded
+pub fn encode(transaction: &Transaction) -> Result<String> {
+    Ok(base64::engine::general_purpose::STANDARD.encode(bincode::serialize(transaction)?))
+}
+
+pub fn decode(encoded: &str) -> Result<Transaction> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| anyhow!("Transaction is not valid base64: {}", e))?;
+    bincode::deserialize(&bytes).map_err(|e| anyhow!("Not a serialized transaction: {}", e))
+}
+
+async fn budget_instructions(rpc_client: &RpcClient, instructions: &[Instruction]) -> Result<Vec<Instruction>> {
+    let budget = compute_budget();
+    let mut budget_instructions = Vec::new();
+
+    if let Some(unit_limit) = budget.unit_limit {
+        budget_instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(unit_limit));
+    }
+
+    let price = match budget.priority_fee {
+        PriorityFee::None => None,
+        PriorityFee::Fixed(fee) => Some(fee),
+        PriorityFee::Auto => {
+            let accounts = writable_accounts(instructions);
+            let fees: Vec<u64> = rpc_client
+                .get_recent_prioritization_fees(&accounts)
+                .await?
+                .into_iter()
+                .map(|fee| fee.prioritization_fee)
+                .collect();
+            let fee = percentile(fees, AUTO_FEE_PERCENTILE);
+            let fee = budget.max_priority_fee.map_or(fee, |max| fee.min(max));
+            eprintln!("Priority fee: {} micro-lamports/CU", fee);
+            Some(fee)
+        }
+    };
+    if let Some(price) = price {
+        budget_instructions.push(ComputeBudgetInstruction::set_compute_unit_price(price));
+    }
+
+    Ok(budget_instructions)
+}
+
+/// Accounts written by `instructions`, excluding signers: the contended state (pots,
+/// escrows, the manager) whose recent fees predict what it takes to land
+fn writable_accounts(instructions: &[Instruction]) -> Vec<Pubkey> {
+    let mut accounts = Vec::new();
+    for meta in instructions.iter().flat_map(|ix| &ix.accounts) {
+        if meta.is_writable && !meta.is_signer && !accounts.contains(&meta.pubkey) {
+            accounts.push(meta.pubkey);
+        }
+    }
+    accounts
+}
+
+/// `pct`-th percentile of `values` (nearest rank), 0 when there are none
+fn percentile(mut values: Vec<u64>, pct: usize) -> u64 {
+    if values.is_empty() {
+        return 0;
+    }
+    values.sort_unstable();
+    let rank = (values.len() * pct).div_ceil(100).max(1);
+    values[rank - 1]
+}
+
+/// Parse `--compute-unit-limit`, rejecting values the runtime would refuse
+pub fn parse_unit_limit(s: &str) -> Result<u32> {
+    const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+    let limit: u32 = s.parse().map_err(|e| anyhow!("Invalid compute unit limit '{}': {}", s, e))?;
+    if limit == 0 || limit > MAX_COMPUTE_UNIT_LIMIT {
+        return Err(anyhow!("Compute unit limit must be between 1 and {}", MAX_COMPUTE_UNIT_LIMIT));
+    }
+    Ok(limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{
+        instruction::AccountMeta,
+        signature::{Keypair, NullSigner},
+    };
+
+    #[test]
+    fn parses_priority_fee() {
+        assert_eq!("auto".parse(), Ok(PriorityFee::Auto));
+        assert_eq!("5000".parse(), Ok(PriorityFee::Fixed(5000)));
+        assert_eq!("0".parse(), Ok(PriorityFee::None));
+        assert!("fast".parse::<PriorityFee>().is_err());
+    }
+
+    #[test]
+    fn percentile_uses_nearest_rank() {
+        assert_eq!(percentile(vec![], 75), 0);
+        assert_eq!(percentile(vec![7], 75), 7);
+        assert_eq!(percentile(vec![40, 10, 30, 20], 75), 30);
+        assert_eq!(percentile(vec![0, 0, 0, 100], 75), 0);
+        assert_eq!(percentile(vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10], 75), 8);
+    }
+
+    #[test]
+    fn fee_accounts_are_writable_non_signers() {
+        let (payer, pot, escrow, program) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let instructions = [
+            Instruction::new_with_bytes(
+                program,
+                &[],
+                vec![AccountMeta::new(pot, false), AccountMeta::new(payer, true), AccountMeta::new_readonly(program, false)],
+            ),
+            Instruction::new_with_bytes(program, &[], vec![AccountMeta::new(escrow, false), AccountMeta::new(pot, false)]),
+        ];
+        assert_eq!(writable_accounts(&instructions), vec![pot, escrow]);
+    }
+
+    #[test]
+    fn rejects_out_of_range_unit_limits() {
+        assert_eq!(parse_unit_limit("200000").unwrap(), 200_000);
+        assert!(parse_unit_limit("0").is_err());
+        assert!(parse_unit_limit("1400001").is_err());
+    }
+
+    #[tokio::test]
+    async fn partially_signed_copies_merge_into_a_complete_transaction() {
+        let (authority, fee_payer) = (Keypair::new(), Keypair::new());
+        let (authority_pubkey, fee_payer_pubkey) = (authority.pubkey(), fee_payer.pubkey());
+        let instruction = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[1],
+            vec![AccountMeta::new(Pubkey::new_unique(), false), AccountMeta::new_readonly(authority_pubkey, true)],
+        );
+        let rpc_client = RpcClient::new_mock("succeeds".to_string());
+        let blockhash = Hash::new_unique();
+
+        // The authority signs on one machine, the fee payer on another
+        let offline = Wallet::new(Box::new(authority), Some(Box::new(NullSigner::new(&fee_payer_pubkey))));
+        let mut signed_by_authority = build(&rpc_client, &offline, std::slice::from_ref(&instruction), Some(blockhash)).await.unwrap();
+        partial_sign(&mut signed_by_authority, offline.signers(&[])).unwrap();
+        assert_eq!(missing_signers(&signed_by_authority), vec![fee_payer_pubkey]);
+
+        let online = Wallet::new(Box::new(NullSigner::new(&authority_pubkey)), Some(Box::new(fee_payer)));
+        let mut signed_by_fee_payer = build(&rpc_client, &online, &[instruction], Some(blockhash)).await.unwrap();
+        partial_sign(&mut signed_by_fee_payer, online.signers(&[])).unwrap();
+        assert_eq!(missing_signers(&signed_by_fee_payer), vec![authority_pubkey]);
+
+        let encoded = [encode(&signed_by_authority).unwrap(), encode(&signed_by_fee_payer).unwrap()];
+        let merged = merge(encoded.iter().map(|e| decode(e).unwrap()).collect()).unwrap();
+        assert!(missing_signers(&merged).is_empty());
+        assert!(merged.verify().is_ok());
+    }
+
+    #[tokio::test]
+    async fn simulation_reports_balance_changes() {
+        use serde_json::json;
+        use solana_client::rpc_request::RpcRequest;
+        use solana_rpc_client::mock_sender::MocksMap;
+
+        let token_account = |lamports: u64, amount: u64| {
+            let mut data = vec![0; 165];
+            data[64..72].copy_from_slice(&amount.to_le_bytes());
+            json!({
+                "lamports": lamports,
+                "data": [base64::engine::general_purpose::STANDARD.encode(data), "base64"],
+                "owner": spl_token::id().to_string(),
+                "executable": false,
+                "rentEpoch": 0,
+                "space": 165,
+            })
+        };
+        let mocks: MocksMap = [
+            (
+                RpcRequest::GetMultipleAccounts,
+                json!({ "context": { "slot": 1 }, "value": [token_account(2_039_280, 500), token_account(2_039_280, 0)] }),
+            ),
+            (
+                RpcRequest::SimulateTransaction,
+                json!({
+                    "context": { "slot": 1 },
+                    "value": {
+                        "err": null,
+                        "logs": ["Program log: Instruction: CloseEscrow"],
+                        "accounts": [token_account(2_039_280, 500), null],
+                        "unitsConsumed": 4_210,
+                    },
+                }),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        let rpc_client = RpcClient::new_mock_with_mocks_map("succeeds", mocks);
+
+        let wallet = Wallet::new(Box::new(Keypair::new()), None);
+        let (treasury, escrow) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let instruction = Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![AccountMeta::new(escrow, false)]);
+        let watched = [("treasury".to_string(), treasury), ("escrow".to_string(), escrow)];
+        let simulated = simulate(&rpc_client, &wallet, "close_escrow", &[instruction], &watched).await.unwrap();
+
+        assert!(simulated.error.is_none());
+        assert_eq!(simulated.units_consumed, Some(4_210));
+        assert_eq!(simulated.logs, vec!["Program log: Instruction: CloseEscrow"]);
+        let treasury = &simulated.balances[0];
+        assert_eq!((treasury.tokens_before, treasury.tokens_after), (Some(500), Some(500)));
+        // The closed escrow is gone afterwards
+        let escrow = &simulated.balances[1];
+        assert_eq!((escrow.lamports_before, escrow.lamports_after), (2_039_280, 0));
+        assert_eq!((escrow.tokens_before, escrow.tokens_after), (Some(0), None));
+    }
+
+    #[test]
+    fn refuses_to_merge_different_transactions() {
+        let payer = Keypair::new();
+        let transfer = |blockhash| {
+            let message = Message::new(
+                &[Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![AccountMeta::new(payer.pubkey(), true)])],
+                Some(&payer.pubkey()),
+            );
+            Transaction::new(&[&payer], message, blockhash)
+        };
+        assert!(merge(vec![transfer(Hash::new_unique()), transfer(Hash::new_unique())]).is_err());
+        assert!(merge(vec![]).is_err());
+        assert!(decode("not base64!").is_err());
+    }
+}
@@ -0,0 +1,254 @@
+//! Landing signed transactions
+//!
+//! [`deliver`] broadcasts a transaction and keeps rebroadcasting it (the RPC's own
+//! retries are turned off) until it reaches the client's commitment or can no longer
+//! land: its blockhash is past `last_valid_block_height`, or the durable nonce it was
+//! built on has moved. Before every rebroadcast the signature is looked up, so a
+//! transaction that already landed is never sent again.
+
+use anyhow::{anyhow, Result};
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    nonblocking::rpc_client::RpcClient,
+    nonce_utils::nonblocking as nonce_utils,
+    rpc_config::RpcSendTransactionConfig,
+};
+use solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::Transaction,
+};
+use solana_transaction_status_client_types::{TransactionConfirmationStatus, TransactionStatus};
+use std::fmt;
+use std::time::Duration;
+
+use crate::{errors, metrics};
+
+/// Pause between signature lookups and rebroadcasts
+const REBROADCAST_INTERVAL: Duration = Duration::from_secs(2);
+
+/// SystemInstruction::AdvanceNonceAccount, as bincode encodes it
+const ADVANCE_NONCE_TAG: [u8; 4] = [4, 0, 0, 0];
+
+/// How long a signed transaction can still land
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Validity {
+    /// Until the chain passes this block height
+    BlockHeight(u64),
+    /// Until the durable nonce account stores another nonce than the transaction's
+    Nonce(Pubkey),
+    /// While the RPC still accepts the blockhash (for transactions built elsewhere)
+    Blockhash,
+}
+
+impl Validity {
+    /// Validity of a transaction built elsewhere: durable when it opens by advancing a
+    /// nonce account
+    pub fn of(transaction: &Transaction) -> Self {
+        let message = &transaction.message;
+        let advances_nonce = message.instructions.first().filter(|ix| {
+            message.account_keys.get(ix.program_id_index as usize) == Some(&solana_system_interface::program::id())
+                && ix.data.starts_with(&ADVANCE_NONCE_TAG)
+        });
+        match advances_nonce.and_then(|ix| ix.accounts.first()) {
+            Some(nonce) => Validity::Nonce(message.account_keys[*nonce as usize]),
+            None => Validity::Blockhash,
+        }
+    }
+}
+
+/// The transaction stopped being able to land before it did
+#[derive(Debug)]
+pub struct Expired {
+    pub signature: Signature,
+    pub broadcasts: usize,
+}
+
+impl fmt::Display for Expired {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Transaction {} expired without landing after {} broadcast(s)",
+            self.signature, self.broadcasts
+        )
+    }
+}
+
+impl std::error::Error for Expired {}
+
+/// A transaction that reached the client's commitment
+#[derive(Clone, Debug)]
+pub struct Landed {
+    pub signature: Signature,
+    pub slot: u64,
+    pub status: TransactionConfirmationStatus,
+    pub broadcasts: usize,
+}
+
+impl Landed {
+    pub fn status_name(&self) -> &'static str {
+        match self.status {
+            TransactionConfirmationStatus::Processed => "processed",
+            TransactionConfirmationStatus::Confirmed => "confirmed",
+            TransactionConfirmationStatus::Finalized => "finalized",
+        }
+    }
+}
+
+impl fmt::Display for Landed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Transaction {} {} in slot {} after {} broadcast(s)",
+            self.signature,
+            self.status_name(),
+            self.slot,
+            self.broadcasts
+        )
+    }
+}
+
+/// Broadcast a signed transaction until it lands or `validity` runs out
+///
+/// A failure of the first broadcast's preflight is returned as a named program error;
+/// later rebroadcasts skip preflight. A transaction that landed with an error is
+/// returned as one too, and running out of validity as [`Expired`].
+pub async fn deliver(rpc_client: &RpcClient, transaction: &Transaction, validity: Validity) -> Result<Landed> {
+    let signature = transaction.signatures[0];
+    let commitment = rpc_client.commitment();
+    let mut broadcasts = 0;
+
+    loop {
+        if broadcasts > 0 {
+            if let Some(status) = signature_status(rpc_client, &signature).await? {
+                if let Some(error) = status.err {
+                    let error = ClientError::from(ClientErrorKind::TransactionError(error));
+                    return Err(errors::explain(rpc_client, &transaction.message, error).await);
+                }
+                if status.satisfies_commitment(commitment) {
+                    metrics::rebroadcasts(broadcasts - 1);
+                    return Ok(Landed {
+                        signature,
+                        slot: status.slot,
+                        status: status.confirmation_status(),
+                        broadcasts,
+                    });
+                }
+                // Landed, not yet at the commitment: wait for it rather than resend
+                tokio::time::sleep(REBROADCAST_INTERVAL).await;
+                continue;
+            }
+            if expired(rpc_client, transaction, validity).await? {
+                return Err(Expired { signature, broadcasts }.into());
+            }
+        }
+
+        let config = RpcSendTransactionConfig {
+            skip_preflight: broadcasts > 0,
+            preflight_commitment: Some(commitment.commitment),
+            max_retries: Some(0),
+            ..RpcSendTransactionConfig::default()
+        };
+        match rpc_client.send_transaction_with_config(transaction, config).await {
+            Ok(_) => broadcasts += 1,
+            Err(e) if broadcasts == 0 => return Err(errors::explain(rpc_client, &transaction.message, e).await),
+            Err(e) => eprintln!("Rebroadcast of {} failed: {}", signature, e),
+        }
+        tokio::time::sleep(REBROADCAST_INTERVAL).await;
+    }
+}
+
+async fn signature_status(rpc_client: &RpcClient, signature: &Signature) -> Result<Option<TransactionStatus>> {
+    Ok(rpc_client
+        .get_signature_statuses(std::slice::from_ref(signature))
+        .await?
+        .value
+        .pop()
+        .flatten())
+}
+
+async fn expired(rpc_client: &RpcClient, transaction: &Transaction, validity: Validity) -> Result<bool> {
+    let blockhash = transaction.message.recent_blockhash;
+    let expired = match validity {
+        Validity::BlockHeight(last_valid) => rpc_client.get_block_height().await? > last_valid,
+        Validity::Nonce(nonce) => durable_nonce(rpc_client, &nonce).await? != blockhash,
+        Validity::Blockhash => !rpc_client.is_blockhash_valid(&blockhash, rpc_client.commitment()).await?,
+    };
+    // It may have landed since the last lookup, which is what moved a nonce
+    Ok(expired && signature_status(rpc_client, &transaction.signatures[0]).await?.is_none())
+}
+
+/// Blockhash currently stored in durable nonce account `nonce`
+pub async fn durable_nonce(rpc_client: &RpcClient, nonce: &Pubkey) -> Result<Hash> {
+    let account = nonce_utils::get_account_with_commitment(rpc_client, nonce, rpc_client.commitment())
+        .await
+        .map_err(|e| anyhow!("Failed to fetch nonce account {}: {}", nonce, e))?;
+    let data = nonce_utils::data_from_account(&account)
+        .map_err(|e| anyhow!("{} is not an initialized nonce account: {}", nonce, e))?;
+    Ok(data.blockhash())
+}
+
+/// First instruction of a transaction on durable nonce `nonce`, signed by its authority
+pub fn advance_nonce(nonce: &Pubkey, authority: &Pubkey) -> Instruction {
+    solana_system_interface::instruction::advance_nonce_account(nonce, authority)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{message::Message, signature::Keypair, signer::Signer};
+
+    fn transaction(rpc_url: &str, instructions: &[Instruction], payer: &Keypair) -> (RpcClient, Transaction) {
+        let message = Message::new(instructions, Some(&payer.pubkey()));
+        (
+            RpcClient::new_mock(rpc_url.to_string()),
+            Transaction::new(&[payer], message, Hash::new_unique()),
+        )
+    }
+
+    #[test]
+    fn nonce_transactions_are_recognized() {
+        let (payer, nonce) = (Keypair::new(), Pubkey::new_unique());
+        let memo = Instruction::new_with_bytes(Pubkey::new_unique(), &[1], vec![]);
+
+        let (_, durable) = transaction("succeeds", &[advance_nonce(&nonce, &payer.pubkey()), memo.clone()], &payer);
+        assert_eq!(Validity::of(&durable), Validity::Nonce(nonce));
+        let (_, recent) = transaction("succeeds", &[memo], &payer);
+        assert_eq!(Validity::of(&recent), Validity::Blockhash);
+    }
+
+    #[tokio::test]
+    async fn reports_the_landed_status() {
+        let payer = Keypair::new();
+        let memo = Instruction::new_with_bytes(Pubkey::new_unique(), &[1], vec![]);
+        let (rpc_client, transaction) = transaction("succeeds", &[memo], &payer);
+
+        let landed = deliver(&rpc_client, &transaction, Validity::BlockHeight(2_000)).await.unwrap();
+        assert_eq!(landed.signature, transaction.signatures[0]);
+        assert_eq!(landed.broadcasts, 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_once_the_blockhash_expires() {
+        // The mock never finds the signature and sits at block height 1234
+        let payer = Keypair::new();
+        let memo = Instruction::new_with_bytes(Pubkey::new_unique(), &[1], vec![]);
+        let (rpc_client, transaction) = transaction("sig_not_found", &[memo], &payer);
+
+        let error = deliver(&rpc_client, &transaction, Validity::BlockHeight(1_000)).await.unwrap_err();
+        let expired = error.downcast_ref::<Expired>().unwrap();
+        assert_eq!(expired.broadcasts, 1);
+    }
+
+    #[tokio::test]
+    async fn landed_failures_are_program_errors() {
+        let payer = Keypair::new();
+        let memo = Instruction::new_with_bytes(Pubkey::new_unique(), &[1], vec![]);
+        let (rpc_client, transaction) = transaction("instruction_error", &[memo], &payer);
+
+        let error = deliver(&rpc_client, &transaction, Validity::BlockHeight(2_000)).await.unwrap_err();
+        assert!(error.downcast_ref::<ClientError>().and_then(|e| e.get_transaction_error()).is_some());
+    }
+}
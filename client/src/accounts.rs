@@ -0,0 +1,213 @@
+//! Typed account fetchers, over the nonblocking RPC client
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use anyhow::{anyhow, Result};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+
+use crate::{KeeperLease, OpenLotto, Pot, PotManager, Ticket};
+
+/// Deserialize an Anchor account, checking its discriminator
+pub fn decode<T: AccountDeserialize>(address: &Pubkey, data: &[u8]) -> Result<T> {
+    T::try_deserialize(&mut &data[..])
+        .map_err(|e| anyhow!("Failed to decode account {}: {}", address, e))
+}
+
+/// Fetch and deserialize a single program account
+pub async fn fetch<T: AccountDeserialize>(rpc_client: &RpcClient, address: &Pubkey) -> Result<T> {
+    let data = rpc_client
+        .get_account_data(address)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch account {}: {}", address, e))?;
+    decode(address, &data)
+}
+
+pub async fn fetch_pot_manager(rpc_client: &RpcClient, address: &Pubkey) -> Result<PotManager> {
+    fetch(rpc_client, address).await
+}
+
+pub async fn fetch_pot(rpc_client: &RpcClient, address: &Pubkey) -> Result<Pot> {
+    fetch(rpc_client, address).await
+}
+
+pub async fn fetch_ticket(rpc_client: &RpcClient, address: &Pubkey) -> Result<Ticket> {
+    fetch(rpc_client, address).await
+}
+
+/// Keeper lease at `address`, or `None` if the manager never had one
+pub async fn fetch_keeper_lease(rpc_client: &RpcClient, address: &Pubkey) -> Result<Option<KeeperLease>> {
+    let account = rpc_client
+        .get_account_with_commitment(address, rpc_client.commitment())
+        .await
+        .map_err(|e| anyhow!("Failed to fetch account {}: {}", address, e))?
+        .value;
+    account.map(|account| decode(address, &account.data)).transpose()
+}
+
+/// Filter matching accounts of type `T` by their Anchor discriminator
+pub fn discriminator_filter<T: Discriminator>() -> RpcFilterType {
+    RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, T::DISCRIMINATOR.to_vec()))
+}
+
+/// Offset of the first field, right after the 8-byte discriminator
+const FIRST_FIELD_OFFSET: usize = 8;
+
+/// Filter matching pots belonging to `pot_manager` (`Pot.pot_manager`)
+pub fn pots_by_manager_filter(pot_manager: &Pubkey) -> RpcFilterType {
+    RpcFilterType::Memcmp(Memcmp::new_raw_bytes(FIRST_FIELD_OFFSET, pot_manager.to_bytes().to_vec()))
+}
+
+/// Filter matching pots drawn with `randomness_account` (`Pot.randomness_account`)
+pub fn pots_by_randomness_filter(randomness_account: &Pubkey) -> RpcFilterType {
+    // pot_manager, then total_participants, start/end timestamps and winning_slot
+    const RANDOMNESS_ACCOUNT_OFFSET: usize = FIRST_FIELD_OFFSET + 32 + 4 * 8;
+    RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+        RANDOMNESS_ACCOUNT_OFFSET,
+        randomness_account.to_bytes().to_vec(),
+    ))
+}
+
+/// Filter matching tickets bought by `owner` (`Ticket.participant`)
+pub fn tickets_by_owner_filter(owner: &Pubkey) -> RpcFilterType {
+    RpcFilterType::Memcmp(Memcmp::new_raw_bytes(FIRST_FIELD_OFFSET, owner.to_bytes().to_vec()))
+}
+
+impl OpenLotto {
+    /// Fetch every program account of type `T` matching `filters`
+    ///
+    /// The discriminator filter is added automatically, so `filters` only needs to
+    /// narrow down by field (e.g. a `Memcmp` on the pot manager).
+    pub async fn fetch_all<T: AccountDeserialize + Discriminator>(
+        &self,
+        rpc_client: &RpcClient,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, T)>> {
+        let mut all_filters = vec![discriminator_filter::<T>()];
+        all_filters.extend(filters);
+
+        let config = RpcProgramAccountsConfig {
+            filters: Some(all_filters),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+
+        rpc_client
+            .get_program_accounts_with_config(&self.program_id, config)
+            .await?
+            .into_iter()
+            .map(|(address, account)| Ok((address, decode(&address, &account.data)?)))
+            .collect()
+    }
+
+    /// Fetch every ticket sold in `pot`
+    ///
+    /// Tickets don't record their pot, so they are looked up by PDA (index
+    /// `0..total_participants`) rather than scanned for. Closed tickets are skipped.
+    pub async fn fetch_pot_tickets(
+        &self,
+        rpc_client: &RpcClient,
+        pot_address: &Pubkey,
+        pot: &Pot,
+    ) -> Result<Vec<(Pubkey, Ticket)>> {
+        // getMultipleAccounts accepts at most 100 addresses per call
+        const BATCH_SIZE: usize = 100;
+
+        let addresses: Vec<Pubkey> = (0..pot.total_participants)
+            .map(|index| self.ticket_address(pot_address, index))
+            .collect();
+
+        let mut tickets = Vec::with_capacity(addresses.len());
+        for batch in addresses.chunks(BATCH_SIZE) {
+            let accounts = rpc_client.get_multiple_accounts(batch).await?;
+            for (address, account) in batch.iter().zip(accounts) {
+                if let Some(account) = account {
+                    tickets.push((*address, decode(address, &account.data)?));
+                }
+            }
+        }
+        Ok(tickets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::AccountSerialize;
+
+    fn memcmp(filter: RpcFilterType) -> Memcmp {
+        match filter {
+            RpcFilterType::Memcmp(memcmp) => memcmp,
+            other => panic!("expected a memcmp filter, got {:?}", other),
+        }
+    }
+
+    fn serialize<T: AccountSerialize>(account: &T) -> Vec<u8> {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    /// A drawn pot, with every field set so a misplaced offset lands on the wrong bytes
+    fn pot(pot_manager: Pubkey, randomness_account: Pubkey) -> Pot {
+        Pot {
+            pot_manager,
+            total_participants: 4,
+            start_timestamp: 1_000,
+            end_timestamp: 2_000,
+            winning_slot: 3,
+            randomness_account,
+            wager: 100,
+            settled: false,
+            bounties_paid: 0,
+            prize_paid: false,
+            payout_reserve: 0,
+            seed_slot: 1_000,
+            draw_slot: 1_001,
+        }
+    }
+
+    #[test]
+    fn randomness_filter_matches_pot_layout() {
+        let randomness = Pubkey::new_unique();
+        let filter = memcmp(pots_by_randomness_filter(&randomness));
+        // Discriminator, pot_manager, then four u64s
+        assert_eq!(filter.offset(), 72);
+        assert!(filter.bytes_match(&serialize(&pot(Pubkey::new_unique(), randomness))));
+        assert!(!filter.bytes_match(&serialize(&pot(randomness, Pubkey::new_unique()))));
+    }
+
+    #[test]
+    fn manager_filter_matches_pot_layout() {
+        let manager = Pubkey::new_unique();
+        let filter = memcmp(pots_by_manager_filter(&manager));
+        assert!(filter.bytes_match(&serialize(&pot(manager, Pubkey::new_unique()))));
+        assert!(!filter.bytes_match(&serialize(&pot(Pubkey::new_unique(), manager))));
+    }
+
+    #[test]
+    fn owner_filter_matches_ticket_layout() {
+        let owner = Pubkey::new_unique();
+        let filter = memcmp(tickets_by_owner_filter(&owner));
+        assert!(filter.bytes_match(&serialize(&Ticket { participant: owner, index: 7 })));
+        assert!(!filter.bytes_match(&serialize(&Ticket { participant: Pubkey::new_unique(), index: 7 })));
+    }
+
+    #[test]
+    fn decode_checks_discriminator() {
+        let address = Pubkey::new_unique();
+        let randomness = Pubkey::new_unique();
+        let data = serialize(&pot(Pubkey::new_unique(), randomness));
+        assert!(memcmp(discriminator_filter::<Pot>()).bytes_match(&data));
+        assert!(!memcmp(discriminator_filter::<Ticket>()).bytes_match(&data));
+
+        let decoded: Pot = decode(&address, &data).unwrap();
+        assert_eq!((decoded.randomness_account, decoded.draw_slot), (randomness, 1_001));
+        assert!(decode::<Ticket>(&address, &data).is_err());
+    }
+}
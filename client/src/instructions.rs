@@ -0,0 +1,339 @@
+//! Typed instruction builders for every Open Lotto instruction
+
+use anchor_lang::prelude::{Pubkey, ToAccountMetas};
+use anchor_lang::solana_program::{instruction::Instruction, sysvar};
+use anchor_lang::{system_program, InstructionData};
+use anchor_spl::{associated_token, token};
+use open_lotto::{accounts, instruction};
+
+use crate::{OpenLotto, Pot, PotManager};
+
+impl OpenLotto {
+    fn instruction(&self, accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+        Instruction {
+            program_id: self.program_id,
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        }
+    }
+
+    /// Create a pot manager together with its treasury, escrow and first two pots
+    pub fn init_pot_manager(
+        &self,
+        authority: &Pubkey,
+        token_mint: &Pubkey,
+        manager_name: &str,
+        end_ts: u64,
+        pot_duration: u64,
+        wager: u64,
+    ) -> Instruction {
+        let pot_manager = self.pot_manager_address(authority, manager_name);
+        self.instruction(
+            accounts::InitPotManager {
+                pot_manager,
+                token_mint: *token_mint,
+                treasury_token_account: self.treasury_address(),
+                escrow_token_account: self.escrow_address(),
+                first_pot: self.pot_address(&pot_manager, end_ts),
+                next_pot: self.pot_address(&pot_manager, end_ts + pot_duration),
+                authority: *authority,
+                system_program: system_program::ID,
+                token_program: token::ID,
+                rent: sysvar::rent::ID,
+            },
+            instruction::InitPotManager {
+                end_ts,
+                pot_duration,
+                manager_name: manager_name.to_string(),
+                wager,
+            },
+        )
+    }
+
+    /// Buy the ticket at `ticket_index` (the pot's current `total_participants`)
+    pub fn enter_ticket(
+        &self,
+        user: &Pubkey,
+        pot: &Pubkey,
+        ticket_index: u64,
+        user_token_account: &Pubkey,
+        token_mint: &Pubkey,
+    ) -> Instruction {
+        self.instruction(
+            accounts::EnterLottery {
+                user: *user,
+                pot: *pot,
+                ticket: self.ticket_address(pot, ticket_index),
+                user_token_account: *user_token_account,
+                escrow_token_account: self.escrow_address(),
+                treasury_token_account: self.treasury_address(),
+                token_mint: *token_mint,
+                token_program: token::ID,
+                system_program: system_program::ID,
+            },
+            instruction::EnterTicket {},
+        )
+    }
+
+    /// Bind a committed randomness account to an ended pot, paying the crank bounty to
+    /// `caller_token_account`
+    pub fn draw_lottery(
+        &self,
+        pot: &Pubkey,
+        pot_manager: &Pubkey,
+        authority: &Pubkey,
+        randomness_account: &Pubkey,
+        caller_token_account: &Pubkey,
+    ) -> Instruction {
+        self.instruction(
+            accounts::DrawLottery {
+                pot: *pot,
+                pot_manager: *pot_manager,
+                authority: *authority,
+                randomness_account_data: *randomness_account,
+                wager_escrow: self.wager_escrow_address(),
+                system_program: system_program::ID,
+                keeper_lease: self.keeper_lease_address(pot_manager),
+                treasury_token_account: self.treasury_address(),
+                bounty_authority: self.bounty_authority_address(),
+                caller_token_account: *caller_token_account,
+                token_program: token::ID,
+            },
+            instruction::DrawLottery {
+                randomness_account: *randomness_account,
+            },
+        )
+    }
+
+    /// Pick the winning ticket from revealed randomness, paying the crank bounty to
+    /// `caller_token_account`
+    pub fn settle_lottery(
+        &self,
+        pot: &Pubkey,
+        pot_manager: &Pubkey,
+        randomness_account: &Pubkey,
+        user: &Pubkey,
+        caller_token_account: &Pubkey,
+    ) -> Instruction {
+        self.instruction(
+            accounts::SettleLottery {
+                pot: *pot,
+                pot_manager: *pot_manager,
+                randomness_account_data: *randomness_account,
+                user: *user,
+                treasury_token_account: self.treasury_address(),
+                bounty_authority: self.bounty_authority_address(),
+                caller_token_account: *caller_token_account,
+                token_program: token::ID,
+            },
+            instruction::SettleLottery {},
+        )
+    }
+
+    /// Advance `manager` past its settled current pot, creating the pot after the next
+    pub fn roll_pot_manager(&self, pot_manager: &Pubkey, manager: &PotManager, payer: &Pubkey) -> Instruction {
+        self.instruction(
+            accounts::RollPotManager {
+                pot_manager: *pot_manager,
+                current_pot: self.pot_address(pot_manager, manager.timestamps.0),
+                new_pot: self.pot_address(pot_manager, manager.roll_end_timestamp()),
+                payer: *payer,
+                system_program: system_program::ID,
+            },
+            instruction::RollPotManager {},
+        )
+    }
+
+    /// Pay the prize pool out of escrow to the winning ticket's owner
+    pub fn claim_prize(
+        &self,
+        ticket: &Pubkey,
+        winner: &Pubkey,
+        pot: &Pubkey,
+        winner_token_account: &Pubkey,
+        token_mint: &Pubkey,
+    ) -> Instruction {
+        self.instruction(
+            accounts::ClaimPrize {
+                ticket: *ticket,
+                winner: *winner,
+                pot: *pot,
+                escrow_token_account: self.escrow_address(),
+                winner_token_account: *winner_token_account,
+                token_mint: *token_mint,
+                token_program: token::ID,
+            },
+            instruction::ClaimPrize {},
+        )
+    }
+
+    /// Push a settled pot's prize to `winner`'s associated token account, creating it at
+    /// `caller`'s expense (refunded from the pot's payout reserve) if it doesn't exist
+    pub fn payout_winner(
+        &self,
+        pot: &Pubkey,
+        pot_state: &Pot,
+        token_mint: &Pubkey,
+        winner: &Pubkey,
+        caller: &Pubkey,
+    ) -> Instruction {
+        self.instruction(
+            accounts::PayoutWinner {
+                pot: *pot,
+                pot_manager: pot_state.pot_manager,
+                ticket: self.ticket_address(pot, pot_state.winning_slot),
+                winner: *winner,
+                winner_token_account: associated_token::get_associated_token_address(winner, token_mint),
+                escrow_token_account: self.escrow_address(),
+                token_mint: *token_mint,
+                caller: *caller,
+                token_program: token::ID,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            },
+            instruction::PayoutWinner {},
+        )
+    }
+
+    /// Acquire, renew or take over the keeper lease of `pot_manager` for `duration_slots`
+    pub fn acquire_keeper_lease(
+        &self,
+        pot_manager: &Pubkey,
+        keeper: &Pubkey,
+        payer: &Pubkey,
+        duration_slots: u64,
+    ) -> Instruction {
+        self.instruction(
+            accounts::AcquireKeeperLease {
+                pot_manager: *pot_manager,
+                keeper_lease: self.keeper_lease_address(pot_manager),
+                keeper: *keeper,
+                payer: *payer,
+                system_program: system_program::ID,
+            },
+            instruction::AcquireKeeperLease { duration_slots },
+        )
+    }
+
+    /// Replace the keepers besides `authority` allowed to acquire `pot_manager`'s lease
+    pub fn set_lease_keepers(&self, pot_manager: &Pubkey, authority: &Pubkey, keepers: Vec<Pubkey>) -> Instruction {
+        self.instruction(
+            accounts::SetLeaseKeepers {
+                pot_manager: *pot_manager,
+                keeper_lease: self.keeper_lease_address(pot_manager),
+                authority: *authority,
+                system_program: system_program::ID,
+            },
+            instruction::SetLeaseKeepers { keepers },
+        )
+    }
+
+    /// Update the token bounty paid to whoever draws or settles a pot
+    pub fn set_crank_bounty(&self, pot_manager: &Pubkey, authority: &Pubkey, bounty: u64) -> Instruction {
+        self.instruction(
+            accounts::SetCrankBounty {
+                pot_manager: *pot_manager,
+                authority: *authority,
+            },
+            instruction::SetCrankBounty { bounty },
+        )
+    }
+
+    /// Switch auto-payout mode, where the keeper pushes settled prizes to winners
+    pub fn set_auto_payout(&self, pot_manager: &Pubkey, authority: &Pubkey, enabled: bool) -> Instruction {
+        self.instruction(
+            accounts::SetAutoPayout {
+                pot_manager: *pot_manager,
+                authority: *authority,
+            },
+            instruction::SetAutoPayout { enabled },
+        )
+    }
+
+    /// Update the SOL wager escrowed on each draw
+    pub fn set_wager(&self, pot_manager: &Pubkey, authority: &Pubkey, wager: u64) -> Instruction {
+        self.instruction(
+            accounts::SetWager {
+                pot_manager: *pot_manager,
+                authority: *authority,
+            },
+            instruction::SetWager { wager },
+        )
+    }
+
+    /// Withdraw escrowed oracle wagers to `destination`
+    pub fn withdraw_wager_escrow(
+        &self,
+        pot_manager: &Pubkey,
+        authority: &Pubkey,
+        destination: &Pubkey,
+        amount: u64,
+    ) -> Instruction {
+        self.instruction(
+            accounts::WithdrawWagerEscrow {
+                pot_manager: *pot_manager,
+                authority: *authority,
+                wager_escrow: self.wager_escrow_address(),
+                destination: *destination,
+                system_program: system_program::ID,
+            },
+            instruction::WithdrawWagerEscrow { amount },
+        )
+    }
+
+    /// Close a pot account, returning its rent to `authority`
+    pub fn close_pot(&self, pot: &Pubkey, authority: &Pubkey) -> Instruction {
+        self.instruction(
+            accounts::ClosePot {
+                pot: *pot,
+                authority: *authority,
+            },
+            instruction::ClosePot {},
+        )
+    }
+
+    /// Close a pot manager account, returning its rent to `authority`
+    pub fn close_pot_manager(&self, pot_manager: &Pubkey, authority: &Pubkey) -> Instruction {
+        self.instruction(
+            accounts::ClosePotManager {
+                pot_manager: *pot_manager,
+                authority: *authority,
+            },
+            instruction::ClosePotManager {},
+        )
+    }
+
+    /// Close a ticket account, returning its rent to `authority`
+    pub fn close_ticket(&self, ticket: &Pubkey, authority: &Pubkey) -> Instruction {
+        self.instruction(
+            accounts::CloseTicket {
+                ticket: *ticket,
+                authority: *authority,
+            },
+            instruction::CloseTicket {},
+        )
+    }
+
+    /// Force close any program-owned account (for cleaning up legacy accounts)
+    pub fn force_close_account(&self, account: &Pubkey, authority: &Pubkey) -> Instruction {
+        self.instruction(
+            accounts::ForceCloseAccount {
+                account: *account,
+                authority: *authority,
+            },
+            instruction::ForceCloseAccount {},
+        )
+    }
+
+    /// Close the escrow token account (self-authority PDA)
+    pub fn close_escrow(&self, authority: &Pubkey) -> Instruction {
+        self.instruction(
+            accounts::CloseEscrow {
+                escrow_token_account: self.escrow_address(),
+                authority: *authority,
+                token_program: token::ID,
+            },
+            instruction::CloseEscrow {},
+        )
+    }
+}
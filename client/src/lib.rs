@@ -0,0 +1,36 @@
+//! Rust client for the Open Lotto program
+//!
+//! Wraps the program crate's generated account and instruction types so callers never
+//! hand-assemble `AccountMeta` lists, discriminators or byte offsets.
+
+use anchor_lang::prelude::Pubkey;
+
+pub mod accounts;
+pub mod instructions;
+pub mod pda;
+pub mod status;
+
+pub use anchor_lang::{error::ErrorCode as AnchorErrorCode, AccountDeserialize, AccountSerialize, Discriminator};
+pub use open_lotto::{
+    ErrorCode, KeeperLease, Pot, PotManager, Ticket, FEE, ID, MAX_CRANK_BOUNTY, MAX_LEASE_KEEPERS, MAX_LEASE_SLOTS, POT_AMOUNT,
+    REDRAW_TIMEOUT_SLOTS, SWITCHBOARD_ON_DEMAND_PROGRAM_ID,
+};
+pub use status::PotStatus;
+
+/// Instruction builders and PDA helpers bound to a deployed Open Lotto program
+#[derive(Clone, Copy, Debug)]
+pub struct OpenLotto {
+    pub program_id: Pubkey,
+}
+
+impl OpenLotto {
+    pub fn new(program_id: Pubkey) -> Self {
+        Self { program_id }
+    }
+}
+
+impl Default for OpenLotto {
+    fn default() -> Self {
+        Self::new(ID)
+    }
+}
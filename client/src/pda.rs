@@ -0,0 +1,96 @@
+//! PDA derivation helpers
+
+use anchor_lang::prelude::Pubkey;
+
+use crate::OpenLotto;
+
+impl OpenLotto {
+    /// PotManager PDA: ["manager", authority, name]
+    pub fn pot_manager_address(&self, authority: &Pubkey, name: &str) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"manager", authority.as_ref(), name.as_bytes()],
+            &self.program_id,
+        )
+        .0
+    }
+
+    /// Pot PDA: ["pot", pot_manager, end_timestamp]
+    pub fn pot_address(&self, pot_manager: &Pubkey, end_timestamp: u64) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"pot", pot_manager.as_ref(), &end_timestamp.to_le_bytes()],
+            &self.program_id,
+        )
+        .0
+    }
+
+    /// Ticket PDA: ["ticket", pot, index]
+    pub fn ticket_address(&self, pot: &Pubkey, index: u64) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"ticket", pot.as_ref(), &index.to_le_bytes()],
+            &self.program_id,
+        )
+        .0
+    }
+
+    /// Treasury token account PDA: ["treasury"]
+    pub fn treasury_address(&self) -> Pubkey {
+        Pubkey::find_program_address(&[b"treasury"], &self.program_id).0
+    }
+
+    /// Escrow token account PDA holding the prize pool: ["escrow"]
+    pub fn escrow_address(&self) -> Pubkey {
+        Pubkey::find_program_address(&[b"escrow"], &self.program_id).0
+    }
+
+    /// PDA the treasury delegates its crank bounty allowance to: ["crankBounty"]
+    pub fn bounty_authority_address(&self) -> Pubkey {
+        Pubkey::find_program_address(&[b"crankBounty"], &self.program_id).0
+    }
+
+    /// Keeper lease PDA of a pot manager: ["lease", pot_manager]
+    pub fn keeper_lease_address(&self, pot_manager: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"lease", pot_manager.as_ref()], &self.program_id).0
+    }
+
+    /// Wager escrow PDA holding SOL oracle wagers: ["wagerEscrow"]
+    pub fn wager_escrow_address(&self) -> Pubkey {
+        Pubkey::find_program_address(&[b"wagerEscrow"], &self.program_id).0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ID;
+
+    /// PDA of `ID` from seeds spelled as in the program's account constraints
+    fn program_pda(seeds: &[&[u8]]) -> Pubkey {
+        Pubkey::find_program_address(seeds, &ID).0
+    }
+
+    #[test]
+    fn addresses_match_program_seeds() {
+        let program = OpenLotto::default();
+        let authority = Pubkey::new_unique();
+
+        let manager = program.pot_manager_address(&authority, "daily");
+        assert_eq!(manager, program_pda(&[b"manager", authority.as_ref(), b"daily"]));
+        let pot = program.pot_address(&manager, 1_700_000_000);
+        assert_eq!(pot, program_pda(&[b"pot", manager.as_ref(), &1_700_000_000u64.to_le_bytes()]));
+        assert_eq!(program.ticket_address(&pot, 3), program_pda(&[b"ticket", pot.as_ref(), &3u64.to_le_bytes()]));
+        assert_eq!(program.keeper_lease_address(&manager), program_pda(&[b"lease", manager.as_ref()]));
+        assert_eq!(program.treasury_address(), program_pda(&[b"treasury"]));
+        assert_eq!(program.escrow_address(), program_pda(&[b"escrow"]));
+        assert_eq!(program.bounty_authority_address(), program_pda(&[b"crankBounty"]));
+        assert_eq!(program.wager_escrow_address(), program_pda(&[b"wagerEscrow"]));
+    }
+
+    #[test]
+    fn addresses_follow_program_id() {
+        let deployed = OpenLotto::default();
+        let other = OpenLotto::new(Pubkey::new_unique());
+        let authority = Pubkey::new_unique();
+        assert_ne!(other.pot_manager_address(&authority, "daily"), deployed.pot_manager_address(&authority, "daily"));
+        assert_ne!(other.treasury_address(), deployed.treasury_address());
+    }
+}
@@ -0,0 +1,107 @@
+//! Lifecycle status of a pot, derived from its timestamps and draw state
+
+use std::fmt;
+
+use crate::Pot;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PotStatus {
+    /// The pot hasn't started yet
+    Pending,
+    /// The pot is accepting tickets
+    Active,
+    /// The pot has ended and is waiting to be drawn
+    Ended,
+    /// Randomness is bound, waiting for reveal and settle
+    Drawing,
+    /// The winner has been picked
+    Settled,
+}
+
+impl PotStatus {
+    /// Status of `pot` at unix time `now`
+    pub fn of(pot: &Pot, now: u64) -> Self {
+        if pot.settled {
+            PotStatus::Settled
+        } else if now < pot.start_timestamp {
+            PotStatus::Pending
+        } else if now <= pot.end_timestamp {
+            PotStatus::Active
+        } else if pot.randomness_account != Default::default() {
+            PotStatus::Drawing
+        } else {
+            PotStatus::Ended
+        }
+    }
+}
+
+impl fmt::Display for PotStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            PotStatus::Pending => "pending",
+            PotStatus::Active => "active",
+            PotStatus::Ended => "ended",
+            PotStatus::Drawing => "drawing",
+            PotStatus::Settled => "settled",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::prelude::Pubkey;
+
+    /// An undrawn pot open from 1_000 through 2_000
+    fn pot() -> Pot {
+        Pot {
+            pot_manager: Pubkey::new_unique(),
+            total_participants: 2,
+            start_timestamp: 1_000,
+            end_timestamp: 2_000,
+            winning_slot: 0,
+            randomness_account: Pubkey::default(),
+            wager: 0,
+            settled: false,
+            bounties_paid: 0,
+            prize_paid: false,
+            payout_reserve: 0,
+            seed_slot: 0,
+            draw_slot: 0,
+        }
+    }
+
+    #[test]
+    fn status_follows_timestamps() {
+        let pot = pot();
+        assert_eq!(PotStatus::of(&pot, 999), PotStatus::Pending);
+        assert_eq!(PotStatus::of(&pot, 1_000), PotStatus::Active);
+        assert_eq!(PotStatus::of(&pot, 2_000), PotStatus::Active);
+        assert_eq!(PotStatus::of(&pot, 2_001), PotStatus::Ended);
+    }
+
+    #[test]
+    fn status_follows_draw_and_settle() {
+        let drawn = Pot { randomness_account: Pubkey::new_unique(), ..pot() };
+        assert_eq!(PotStatus::of(&drawn, 2_001), PotStatus::Drawing);
+        let settled = Pot { settled: true, ..drawn };
+        assert_eq!(PotStatus::of(&settled, 2_001), PotStatus::Settled);
+        // Settled wins over the timestamps
+        assert_eq!(PotStatus::of(&settled, 999), PotStatus::Settled);
+    }
+
+    #[test]
+    fn status_displays_lowercase() {
+        let names: Vec<String> = [
+            PotStatus::Pending,
+            PotStatus::Active,
+            PotStatus::Ended,
+            PotStatus::Drawing,
+            PotStatus::Settled,
+        ]
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+        assert_eq!(names, ["pending", "active", "ended", "drawing", "settled"]);
+    }
+}
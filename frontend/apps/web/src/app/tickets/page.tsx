@@ -124,7 +124,9 @@ export default function TicketsPage() {
               !pot.winningSlot.isZero() &&
               pot.winningSlot.eq(ticket.index);
             const canClaim =
-              isWinner && getPotStatus(pot) === PotStatus.Settled;
+              isWinner &&
+              getPotStatus(pot) === PotStatus.Settled &&
+              !pot.prizePaid;
 
             return (
               <div
@@ -156,6 +158,11 @@ export default function TicketsPage() {
                           Winner!
                         </span>
                       )}
+                      {isWinner && pot.prizePaid && (
+                        <span className="inline-block mt-1 ml-1 px-2 py-0.5 bg-slate-100 text-slate-600 text-xs font-medium rounded-full">
+                          Paid out
+                        </span>
+                      )}
                     </div>
                   </div>
 
@@ -9,6 +9,7 @@ import {
 } from "@solana/web3.js";
 import {
   TOKEN_PROGRAM_ID,
+  ASSOCIATED_TOKEN_PROGRAM_ID,
   getAssociatedTokenAddress,
   createAssociatedTokenAccountInstruction,
 } from "@solana/spl-token";
@@ -22,6 +23,7 @@ import {
   Ticket,
   PotManager,
   DISCRIMINATORS,
+  DEFAULT_WAGER,
   getPotStatus,
   PotStatus,
 } from "@open-lotto/types";
@@ -31,7 +33,9 @@ import {
   deriveTicketPDA,
   deriveTreasuryPDA,
   deriveEscrowPDA,
+  deriveBountyAuthorityPDA,
   deriveWagerEscrowPDA,
+  deriveKeeperLeasePDA,
 } from "@open-lotto/utils";
 
 // Switchboard constants
@@ -103,6 +107,7 @@ export class OpenLottoClient {
     tokenMint: PublicKey;
     endTimestamp: BN;
     potDuration: BN;
+    wager?: BN;
   }): Promise<string> {
     if (!this.wallet?.publicKey) throw new Error("Wallet not connected");
 
@@ -123,6 +128,7 @@ export class OpenLottoClient {
       params.endTimestamp.toArrayLike(Buffer, "le", 8),
       params.potDuration.toArrayLike(Buffer, "le", 8),
       this.encodeString(params.managerName),
+      (params.wager ?? DEFAULT_WAGER).toArrayLike(Buffer, "le", 8),
     ]);
 
     const keys = [
@@ -154,6 +160,16 @@ export class OpenLottoClient {
     if (!this.wallet?.publicKey) throw new Error("Wallet not connected");
 
     const [wagerEscrow] = deriveWagerEscrowPDA();
+    const [treasuryTokenAccount] = deriveTreasuryPDA();
+    const [bountyAuthority] = deriveBountyAuthorityPDA();
+
+    // The pot's manager holds the configured wager and crank bounty
+    const pot = await this.getPot(params.pot);
+    if (!pot) throw new Error("Pot not found");
+    const [keeperLease] = deriveKeeperLeasePDA(pot.potManager);
+    const { bountyAccount, preInstructions } = await this.bountyTokenAccount(
+      pot.potManager
+    );
 
     const discriminator = this.getDiscriminator("draw_lottery");
     const data = Buffer.concat([
@@ -163,10 +179,16 @@ export class OpenLottoClient {
 
     const keys = [
       { pubkey: params.pot, isSigner: false, isWritable: true },
+      { pubkey: pot.potManager, isSigner: false, isWritable: true },
       { pubkey: this.wallet.publicKey, isSigner: true, isWritable: true },
       { pubkey: params.randomnessAccount, isSigner: false, isWritable: false },
       { pubkey: wagerEscrow, isSigner: false, isWritable: true },
       { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+      { pubkey: keeperLease, isSigner: false, isWritable: false },
+      { pubkey: treasuryTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: bountyAuthority, isSigner: false, isWritable: false },
+      { pubkey: bountyAccount, isSigner: false, isWritable: true },
+      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
     ];
 
     const instruction = new TransactionInstruction({
@@ -175,7 +197,7 @@ export class OpenLottoClient {
       data,
     });
 
-    return this.sendTransaction([instruction]);
+    return this.sendTransaction([...preInstructions, instruction]);
   }
 
   async settleLottery(params: {
@@ -184,12 +206,25 @@ export class OpenLottoClient {
   }): Promise<string> {
     if (!this.wallet?.publicKey) throw new Error("Wallet not connected");
 
+    const [treasuryTokenAccount] = deriveTreasuryPDA();
+    const [bountyAuthority] = deriveBountyAuthorityPDA();
+    const pot = await this.getPot(params.pot);
+    if (!pot) throw new Error("Pot not found");
+    const { bountyAccount, preInstructions } = await this.bountyTokenAccount(
+      pot.potManager
+    );
+
     const discriminator = this.getDiscriminator("settle_lottery");
 
     const keys = [
       { pubkey: params.pot, isSigner: false, isWritable: true },
+      { pubkey: pot.potManager, isSigner: false, isWritable: false },
       { pubkey: params.randomnessAccount, isSigner: false, isWritable: false },
       { pubkey: this.wallet.publicKey, isSigner: true, isWritable: false },
+      { pubkey: treasuryTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: bountyAuthority, isSigner: false, isWritable: false },
+      { pubkey: bountyAccount, isSigner: false, isWritable: true },
+      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
     ];
 
     const instruction = new TransactionInstruction({
@@ -198,6 +233,177 @@ export class OpenLottoClient {
       data: discriminator,
     });
 
+    return this.sendTransaction([...preInstructions, instruction]);
+  }
+
+  // The wallet's token account receiving a manager's crank bounty, created if missing
+  private async bountyTokenAccount(potManager: PublicKey): Promise<{
+    bountyAccount: PublicKey;
+    preInstructions: TransactionInstruction[];
+  }> {
+    if (!this.wallet?.publicKey) throw new Error("Wallet not connected");
+
+    const manager = await this.getPotManager(potManager);
+    if (!manager) throw new Error("Pot manager not found");
+
+    const bountyAccount = await getAssociatedTokenAddress(
+      manager.tokenMint,
+      this.wallet.publicKey
+    );
+    const preInstructions: TransactionInstruction[] = [];
+    if (!(await this.connection.getAccountInfo(bountyAccount))) {
+      preInstructions.push(
+        createAssociatedTokenAccountInstruction(
+          this.wallet.publicKey,
+          bountyAccount,
+          this.wallet.publicKey,
+          manager.tokenMint
+        )
+      );
+    }
+    return { bountyAccount, preInstructions };
+  }
+
+  // Advance a manager whose current pot has ended and been settled (or had no
+  // tickets); anyone can pay for the new pot
+  async rollPotManager(params: { potManager: PublicKey }): Promise<string> {
+    if (!this.wallet?.publicKey) throw new Error("Wallet not connected");
+
+    const manager = await this.getPotManager(params.potManager);
+    if (!manager) throw new Error("Pot manager not found");
+
+    const [currentEnd, nextEnd] = manager.timestamps;
+    const [currentPot] = derivePotPDA(params.potManager, currentEnd);
+    const [newPot] = derivePotPDA(
+      params.potManager,
+      nextEnd.add(nextEnd.sub(currentEnd))
+    );
+
+    const keys = [
+      { pubkey: params.potManager, isSigner: false, isWritable: true },
+      { pubkey: currentPot, isSigner: false, isWritable: false },
+      { pubkey: newPot, isSigner: false, isWritable: true },
+      { pubkey: this.wallet.publicKey, isSigner: true, isWritable: true },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ];
+
+    const instruction = new TransactionInstruction({
+      programId: PROGRAM_ID,
+      keys,
+      data: this.getDiscriminator("roll_pot_manager"),
+    });
+
+    return this.sendTransaction([instruction]);
+  }
+
+  async setCrankBounty(params: {
+    potManager: PublicKey;
+    bounty: BN;
+  }): Promise<string> {
+    if (!this.wallet?.publicKey) throw new Error("Wallet not connected");
+
+    const discriminator = this.getDiscriminator("set_crank_bounty");
+    const data = Buffer.concat([
+      discriminator,
+      params.bounty.toArrayLike(Buffer, "le", 8),
+    ]);
+
+    const keys = [
+      { pubkey: params.potManager, isSigner: false, isWritable: true },
+      { pubkey: this.wallet.publicKey, isSigner: true, isWritable: false },
+    ];
+
+    const instruction = new TransactionInstruction({
+      programId: PROGRAM_ID,
+      keys,
+      data,
+    });
+
+    return this.sendTransaction([instruction]);
+  }
+
+  async setAutoPayout(params: {
+    potManager: PublicKey;
+    enabled: boolean;
+  }): Promise<string> {
+    if (!this.wallet?.publicKey) throw new Error("Wallet not connected");
+
+    const discriminator = this.getDiscriminator("set_auto_payout");
+    const data = Buffer.concat([
+      discriminator,
+      Buffer.from([params.enabled ? 1 : 0]),
+    ]);
+
+    const keys = [
+      { pubkey: params.potManager, isSigner: false, isWritable: true },
+      { pubkey: this.wallet.publicKey, isSigner: true, isWritable: false },
+    ];
+
+    const instruction = new TransactionInstruction({
+      programId: PROGRAM_ID,
+      keys,
+      data,
+    });
+
+    return this.sendTransaction([instruction]);
+  }
+
+  async setWager(params: { potManager: PublicKey; wager: BN }): Promise<string> {
+    if (!this.wallet?.publicKey) throw new Error("Wallet not connected");
+
+    const discriminator = this.getDiscriminator("set_wager");
+    const data = Buffer.concat([
+      discriminator,
+      params.wager.toArrayLike(Buffer, "le", 8),
+    ]);
+
+    const keys = [
+      { pubkey: params.potManager, isSigner: false, isWritable: true },
+      { pubkey: this.wallet.publicKey, isSigner: true, isWritable: false },
+    ];
+
+    const instruction = new TransactionInstruction({
+      programId: PROGRAM_ID,
+      keys,
+      data,
+    });
+
+    return this.sendTransaction([instruction]);
+  }
+
+  async withdrawWagerEscrow(params: {
+    potManager: PublicKey;
+    amount: BN;
+    destination?: PublicKey;
+  }): Promise<string> {
+    if (!this.wallet?.publicKey) throw new Error("Wallet not connected");
+
+    const [wagerEscrow] = deriveWagerEscrowPDA();
+
+    const discriminator = this.getDiscriminator("withdraw_wager_escrow");
+    const data = Buffer.concat([
+      discriminator,
+      params.amount.toArrayLike(Buffer, "le", 8),
+    ]);
+
+    const keys = [
+      { pubkey: params.potManager, isSigner: false, isWritable: true },
+      { pubkey: this.wallet.publicKey, isSigner: true, isWritable: true },
+      { pubkey: wagerEscrow, isSigner: false, isWritable: true },
+      {
+        pubkey: params.destination ?? this.wallet.publicKey,
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ];
+
+    const instruction = new TransactionInstruction({
+      programId: PROGRAM_ID,
+      keys,
+      data,
+    });
+
     return this.sendTransaction([instruction]);
   }
 
@@ -296,6 +502,49 @@ export class OpenLottoClient {
     return this.sendTransaction([instruction]);
   }
 
+  // Push a settled pot's prize to the winner's associated token account (auto-payout
+  // managers); the wallet pays for a missing account and is refunded from the pot
+  async payoutWinner(params: { pot: PublicKey }): Promise<string> {
+    if (!this.wallet?.publicKey) throw new Error("Wallet not connected");
+
+    const pot = await this.getPot(params.pot);
+    if (!pot) throw new Error("Pot not found");
+    const manager = await this.getPotManager(pot.potManager);
+    if (!manager) throw new Error("Pot manager not found");
+
+    const [ticketAddress] = deriveTicketPDA(params.pot, pot.winningSlot);
+    const ticket = await this.getTicket(ticketAddress);
+    if (!ticket) throw new Error("Winning ticket not found");
+
+    const [escrowTokenAccount] = deriveEscrowPDA();
+    const winnerTokenAccount = await getAssociatedTokenAddress(
+      manager.tokenMint,
+      ticket.participant
+    );
+
+    const keys = [
+      { pubkey: params.pot, isSigner: false, isWritable: true },
+      { pubkey: pot.potManager, isSigner: false, isWritable: false },
+      { pubkey: ticketAddress, isSigner: false, isWritable: false },
+      { pubkey: ticket.participant, isSigner: false, isWritable: false },
+      { pubkey: winnerTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: escrowTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: manager.tokenMint, isSigner: false, isWritable: false },
+      { pubkey: this.wallet.publicKey, isSigner: true, isWritable: true },
+      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+      { pubkey: ASSOCIATED_TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ];
+
+    const instruction = new TransactionInstruction({
+      programId: PROGRAM_ID,
+      keys,
+      data: this.getDiscriminator("payout_winner"),
+    });
+
+    return this.sendTransaction([instruction]);
+  }
+
   // ============ Account Fetchers ============
 
   async getPot(address: PublicKey): Promise<Pot | null> {
@@ -547,6 +796,27 @@ export class OpenLottoClient {
       offset += 8;
 
       const randomnessAccount = new PublicKey(data.slice(offset, offset + 32));
+      offset += 32;
+
+      const wager = new BN(data.slice(offset, offset + 8), "le");
+      offset += 8;
+
+      const settled = data[offset] === 1;
+      offset += 1;
+
+      const bountiesPaid = new BN(data.slice(offset, offset + 8), "le");
+      offset += 8;
+
+      const prizePaid = data[offset] === 1;
+      offset += 1;
+
+      const payoutReserve = new BN(data.slice(offset, offset + 8), "le");
+      offset += 8;
+
+      const seedSlot = new BN(data.slice(offset, offset + 8), "le");
+      offset += 8;
+
+      const drawSlot = new BN(data.slice(offset, offset + 8), "le");
 
       return {
         potManager,
@@ -555,6 +825,13 @@ export class OpenLottoClient {
         endTimestamp,
         winningSlot,
         randomnessAccount,
+        wager,
+        settled,
+        bountiesPaid,
+        prizePaid,
+        payoutReserve,
+        seedSlot,
+        drawSlot,
       };
     } catch {
       return null;
@@ -605,6 +882,15 @@ export class OpenLottoClient {
       const bump = data[offset];
       offset += 1;
 
+      const wager = new BN(data.slice(offset, offset + 8), "le");
+      offset += 8;
+      const wagerBalance = new BN(data.slice(offset, offset + 8), "le");
+      offset += 8;
+      const crankBounty = new BN(data.slice(offset, offset + 8), "le");
+      offset += 8;
+      const autoPayout = data[offset] === 1;
+      offset += 1;
+
       // Read string: 4 bytes length prefix + content
       const nameLen = data.readUInt32LE(offset);
       offset += 4;
@@ -618,6 +904,10 @@ export class OpenLottoClient {
         lastRandomNumber,
         timestamps: [currentEndTs, nextEndTs],
         bump,
+        wager,
+        wagerBalance,
+        crankBounty,
+        autoPayout,
         name,
       };
     } catch {
@@ -9,6 +9,10 @@ export const PROGRAM_ID = new PublicKey(
 // Constants
 export const POT_AMOUNT = new BN(100_000_000); // 0.1 tokens (assuming 9 decimals)
 export const FEE_AMOUNT = new BN(10_000_000); // 0.01 tokens (assuming 9 decimals)
+export const MAX_CRANK_BOUNTY = FEE_AMOUNT.divn(2); // Cap on set_crank_bounty
+export const MAX_LEASE_KEEPERS = 8; // Cap on set_lease_keepers
+export const REDRAW_TIMEOUT_SLOTS = 1_500; // Slots before an unsettled draw can be redrawn
+export const DEFAULT_WAGER = new BN(100); // Oracle wager in lamports escrowed per draw
 
 // PDA Seeds
 export const SEEDS = {
@@ -18,6 +22,8 @@ export const SEEDS = {
   TREASURY: Buffer.from("treasury"),
   ESCROW: Buffer.from("escrow"),
   WAGER_ESCROW: Buffer.from("wagerEscrow"),
+  LEASE: Buffer.from("lease"),
+  CRANK_BOUNTY: Buffer.from("crankBounty"),
 } as const;
 
 // Account Types
@@ -28,6 +34,13 @@ export interface Pot {
   endTimestamp: BN;
   winningSlot: BN;
   randomnessAccount: PublicKey;
+  wager: BN; // SOL wager escrowed by this pot's draws
+  settled: boolean; // Winner has been picked
+  bountiesPaid: BN; // Crank bounties the treasury paid for this pot
+  prizePaid: boolean; // Prize has been claimed or paid out
+  payoutReserve: BN; // Lamports set aside for the winner's token account rent
+  seedSlot: BN; // Slot the drawn randomness was committed at
+  drawSlot: BN; // Slot of the latest draw, which starts the redraw timeout
 }
 
 export interface Ticket {
@@ -43,14 +56,27 @@ export interface PotManager {
   lastRandomNumber: BN;
   timestamps: [BN, BN]; // (currentEndTs, nextEndTs)
   bump: number;
+  wager: BN; // SOL wager escrowed on each draw
+  wagerBalance: BN; // Wagers escrowed and not yet withdrawn
+  crankBounty: BN; // Tokens paid from the treasury per draw and per settle
+  autoPayout: boolean; // Push prizes to winners instead of waiting for claims
   name: string;
 }
 
+export interface KeeperLease {
+  potManager: PublicKey;
+  keeper: PublicKey; // Only keeper allowed to draw until expirySlot
+  expirySlot: BN;
+  bump: number;
+  keepers: PublicKey[]; // Keepers besides the authority allowed to acquire the lease
+}
+
 // Instruction Args
 export interface InitPotManagerArgs {
   endTs: BN;
   potDuration: BN;
   managerName: string;
+  wager: BN;
 }
 
 export interface DrawLotteryArgs {
@@ -66,6 +92,22 @@ export enum OpenLottoError {
   InvalidRandomnessAccount = 6004,
   RandomnessNotResolved = 6005,
   TicketAccountNotWinning = 6006,
+  InsufficientWagerBalance = 6007,
+  WagerEscrowBelowRent = 6008,
+  PotNotEnded = 6009,
+  PotAlreadySettled = 6010,
+  KeeperLeaseHeld = 6011,
+  InvalidLeaseDuration = 6012,
+  PotAlreadyDrawn = 6013,
+  PotNotSettled = 6014,
+  PrizeAlreadyPaid = 6015,
+  AutoPayoutDisabled = 6016,
+  BountyTooLarge = 6017,
+  PotEmpty = 6018,
+  RandomnessSeedMismatch = 6019,
+  KeeperNotAllowed = 6020,
+  TooManyLeaseKeepers = 6021,
+  NoPayoutReserve = 6022,
 }
 
 export const ERROR_MESSAGES: Record<OpenLottoError, string> = {
@@ -77,6 +119,26 @@ export const ERROR_MESSAGES: Record<OpenLottoError, string> = {
   [OpenLottoError.InvalidRandomnessAccount]: "Invalid randomness account",
   [OpenLottoError.RandomnessNotResolved]: "Randomness not resolved",
   [OpenLottoError.TicketAccountNotWinning]: "Ticket account is not winning",
+  [OpenLottoError.InsufficientWagerBalance]:
+    "Amount exceeds the manager's escrowed wagers",
+  [OpenLottoError.WagerEscrowBelowRent]:
+    "Withdrawal would leave the wager escrow below rent exemption",
+  [OpenLottoError.PotNotEnded]: "The current pot has not ended yet",
+  [OpenLottoError.PotAlreadySettled]: "The pot has already been settled",
+  [OpenLottoError.KeeperLeaseHeld]: "Another keeper holds this manager's lease",
+  [OpenLottoError.InvalidLeaseDuration]:
+    "Lease duration must be between 1 and MAX_LEASE_SLOTS slots",
+  [OpenLottoError.PotAlreadyDrawn]: "The pot has already been drawn",
+  [OpenLottoError.PotNotSettled]: "The pot has not been settled yet",
+  [OpenLottoError.PrizeAlreadyPaid]: "The prize has already been paid",
+  [OpenLottoError.AutoPayoutDisabled]: "The manager is not in auto-payout mode",
+  [OpenLottoError.BountyTooLarge]: "Crank bounty exceeds MAX_CRANK_BOUNTY",
+  [OpenLottoError.PotEmpty]: "The pot has no tickets",
+  [OpenLottoError.RandomnessSeedMismatch]: "Randomness was re-committed after the draw",
+  [OpenLottoError.KeeperNotAllowed]:
+    "Keeper is not allowed to acquire this manager's lease",
+  [OpenLottoError.TooManyLeaseKeepers]: "More than MAX_LEASE_KEEPERS keepers",
+  [OpenLottoError.NoPayoutReserve]: "The pot was drawn outside auto-payout mode",
 };
 
 // Pot Status Helper
@@ -93,7 +155,7 @@ export function getPotStatus(pot: Pot): PotStatus {
   const startTime = pot.startTimestamp.toNumber();
   const endTime = pot.endTimestamp.toNumber();
   const hasRandomness = !pot.randomnessAccount.equals(PublicKey.default);
-  const hasWinner = pot.winningSlot.toNumber() > 0;
+  const hasWinner = pot.settled;
 
   // Pot hasn't started yet
   if (now < startTime) {
@@ -120,10 +120,21 @@ export function deriveEscrowPDA(): [PublicKey, number] {
   return PublicKey.findProgramAddressSync([SEEDS.ESCROW], PROGRAM_ID);
 }
 
+export function deriveBountyAuthorityPDA(): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync([SEEDS.CRANK_BOUNTY], PROGRAM_ID);
+}
+
 export function deriveWagerEscrowPDA(): [PublicKey, number] {
   return PublicKey.findProgramAddressSync([SEEDS.WAGER_ESCROW], PROGRAM_ID);
 }
 
+export function deriveKeeperLeasePDA(potManager: PublicKey): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync(
+    [SEEDS.LEASE, potManager.toBuffer()],
+    PROGRAM_ID
+  );
+}
+
 // Validation
 export function isValidPublicKey(address: string): boolean {
   try {
@@ -1,24 +1,45 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_spl::associated_token::{self, get_associated_token_address, AssociatedToken};
 use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
 
 declare_id!("FVzki74o5zsTDK1ShhQ6EyR3m2ft7HRgeSkCiEsE8aDf");
 
+// Token amounts (using smallest token unit, e.g., 6 decimals = 1 token = 1_000_000)
+pub const POT_AMOUNT: u64 = 9_000_000; // 9 tokens to prize pool
+pub const FEE: u64 = 1_000_000;        // 1 token to treasury
+
+// Longest keeper lease, about an hour of slots, so a lapsed keeper is replaced in time
+pub const MAX_LEASE_SLOTS: u64 = 9_000;
+
+// Most keepers the manager authority can allow to acquire its lease
+pub const MAX_LEASE_KEEPERS: usize = 8;
+
+// Slots a draw may stay unsettled before the pot can be drawn again, about ten minutes,
+// so a drawer that never reveals or an oracle that went down can't strand the pot
+pub const REDRAW_TIMEOUT_SLOTS: u64 = 1_500;
+
+// Largest crank bounty, so a pot's draw and settle bounties never exceed the fee on one ticket
+pub const MAX_CRANK_BOUNTY: u64 = FEE / 2;
+
+// Switchboard On-Demand, which owns the randomness accounts draws and settles read
+#[cfg(not(feature = "devnet"))]
+pub const SWITCHBOARD_ON_DEMAND_PROGRAM_ID: Pubkey = pubkey!("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv");
+#[cfg(feature = "devnet")]
+pub const SWITCHBOARD_ON_DEMAND_PROGRAM_ID: Pubkey = pubkey!("Aio4gaXjXzJNVLtzwtNVmSqGKpANtXhybbkhtAC94ji2");
+
 #[program]
 pub mod open_lotto {
     use super::*;
     use anchor_lang::solana_program::program::set_return_data;
     use switchboard_on_demand::RandomnessAccountData;
 
-    // Token amounts (using smallest token unit, e.g., 6 decimals = 1 token = 1_000_000)
-    const POT_AMOUNT: u64 = 9_000_000; // 9 tokens to prize pool
-    const FEE: u64 = 1_000_000;        // 1 token to treasury
-    const WAGER: u64 = 100;            // Oracle wager
-
     pub fn init_pot_manager(
         ctx: Context<InitPotManager>,
         end_ts: u64,
         pot_duration: u64,
         manager_name: String,
+        wager: u64,
     ) -> Result<()> {
         let now = Clock::get()?.unix_timestamp as u64;
         if end_ts < now {
@@ -39,6 +60,10 @@ pub mod open_lotto {
         pot_manager.token_mint = ctx.accounts.token_mint.key();
         pot_manager.last_random_number = 0;
         pot_manager.rent = ctx.accounts.rent.minimum_balance(PotManager::space());
+        pot_manager.wager = wager;
+        pot_manager.wager_balance = 0;
+        pot_manager.crank_bounty = 0;
+        pot_manager.auto_payout = false;
         pot_manager.name = manager_name;
 
         // initialize pots with reference to pot manager
@@ -93,8 +118,36 @@ pub mod open_lotto {
         Ok(())
     }
 
+    /// Bind committed randomness to an ended pot, paying the caller the manager's crank
+    /// bounty
+    ///
+    /// Anyone can draw (unless a keeper holds the manager's lease), but only once while
+    /// the draw can still be settled: an early redraw would let the caller pick among
+    /// randomness values. A draw left unsettled for REDRAW_TIMEOUT_SLOTS (the drawer never
+    /// revealed, closed the randomness account, or its oracle went down) can be replaced
+    /// by a redraw with fresh randomness, which pays no second bounty.
+    ///
+    /// In auto-payout mode the first draw also has the caller fund the pot's payout
+    /// reserve, enough rent for the winner's token account.
     pub fn draw_lottery(ctx: Context<DrawLottery>, randomness_account: Pubkey) -> Result<()> {
         let clock = Clock::get()?;
+        if clock.unix_timestamp as u64 <= ctx.accounts.pot.end_timestamp {
+            return Err(ErrorCode::PotNotEnded.into());
+        }
+        if ctx.accounts.pot.settled {
+            return Err(ErrorCode::PotAlreadySettled.into());
+        }
+        let redraw = ctx.accounts.pot.randomness_account != Pubkey::default();
+        if redraw && !ctx.accounts.pot.redraw_open(clock.slot) {
+            return Err(ErrorCode::PotAlreadyDrawn.into());
+        }
+        // Nobody could win, and settling would divide by zero participants
+        if ctx.accounts.pot.total_participants == 0 {
+            return Err(ErrorCode::PotEmpty.into());
+        }
+        if ctx.accounts.randomness_account_data.key() != randomness_account {
+            return Err(ErrorCode::InvalidRandomnessAccount.into());
+        }
         let randomness_data =
             RandomnessAccountData::parse(ctx.accounts.randomness_account_data.data.borrow())
                 .map_err(|_| ErrorCode::RandomnessNotResolved)?;
@@ -104,7 +157,15 @@ pub mod open_lotto {
             return Err(ErrorCode::RandomnessAlreadyRevealed.into());
         }
 
+        // While a keeper holds the manager's lease, only it may draw
+        if let Some(lease) = KeeperLease::load(&ctx.accounts.keeper_lease)? {
+            if lease.blocks(&ctx.accounts.authority.key(), clock.slot) {
+                return Err(ErrorCode::KeeperLeaseHeld.into());
+            }
+        }
+
         // Transfer SOL wager for oracle (this stays as SOL)
+        let wager = ctx.accounts.pot_manager.wager;
         anchor_lang::system_program::transfer(
             CpiContext::new(
                 ctx.accounts.system_program.to_account_info(),
@@ -113,18 +174,58 @@ pub mod open_lotto {
                     to: ctx.accounts.wager_escrow.to_account_info(),
                 },
             ),
-            WAGER,
+            wager,
         )?;
 
+        // Track the wager against both the pot and its manager so it can be withdrawn later
+        ctx.accounts.pot.wager += wager;
+        ctx.accounts.pot_manager.wager_balance += wager;
         ctx.accounts.pot.randomness_account = randomness_account;
+        ctx.accounts.pot.seed_slot = randomness_data.seed_slot;
+        ctx.accounts.pot.draw_slot = clock.slot;
 
-        Ok(())
+        if redraw {
+            return Ok(());
+        }
+        // Reserved when the pot is drawn rather than created, so a manager switched to
+        // auto-payout after a pot was created still funds that pot's payout
+        if ctx.accounts.pot_manager.auto_payout {
+            let reserve = Rent::get()?.minimum_balance(TokenAccount::LEN);
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: ctx.accounts.pot.to_account_info(),
+                    },
+                ),
+                reserve,
+            )?;
+            ctx.accounts.pot.payout_reserve = reserve;
+        }
+        pay_crank_bounty(
+            &mut ctx.accounts.pot,
+            ctx.accounts.pot_manager.crank_bounty,
+            &ctx.accounts.treasury_token_account,
+            &ctx.accounts.bounty_authority,
+            &ctx.accounts.caller_token_account,
+            &ctx.accounts.token_program,
+            ctx.bumps.bounty_authority,
+        )
     }
 
+    /// Pick the winning ticket from revealed randomness, paying the caller the manager's
+    /// crank bounty
     pub fn settle_lottery(ctx: Context<SettleLottery>) -> Result<()> {
         let clock = Clock::get()?;
         let pot = &mut ctx.accounts.pot;
 
+        if pot.settled {
+            return Err(ErrorCode::PotAlreadySettled.into());
+        }
+        if pot.total_participants == 0 {
+            return Err(ErrorCode::PotEmpty.into());
+        }
         if ctx.accounts.randomness_account_data.key() != pot.randomness_account {
             return Err(ErrorCode::InvalidRandomnessAccount.into());
         }
@@ -132,6 +233,10 @@ pub mod open_lotto {
         let randomness_data =
             RandomnessAccountData::parse(ctx.accounts.randomness_account_data.data.borrow())
                 .map_err(|_| ErrorCode::RandomnessNotResolved)?;
+        // Re-committing after the draw would let the caller pick another outcome
+        if randomness_data.seed_slot != pot.seed_slot {
+            return Err(ErrorCode::RandomnessSeedMismatch.into());
+        }
         let revealed_random_value = randomness_data
             .get_value(clock.slot)
             .map_err(|_| ErrorCode::RandomnessNotResolved)?;
@@ -142,11 +247,27 @@ pub mod open_lotto {
         );
         let winner = number % pot.total_participants;
         pot.winning_slot = winner;
+        pot.settled = true;
         set_return_data(&winner.to_le_bytes());
-        Ok(())
+
+        pay_crank_bounty(
+            &mut ctx.accounts.pot,
+            ctx.accounts.pot_manager.crank_bounty,
+            &ctx.accounts.treasury_token_account,
+            &ctx.accounts.bounty_authority,
+            &ctx.accounts.caller_token_account,
+            &ctx.accounts.token_program,
+            ctx.bumps.bounty_authority,
+        )
     }
 
     pub fn claim_prize(ctx: Context<ClaimPrize>) -> Result<()> {
+        if !ctx.accounts.pot.settled {
+            return Err(ErrorCode::PotNotSettled.into());
+        }
+        if ctx.accounts.pot.prize_paid {
+            return Err(ErrorCode::PrizeAlreadyPaid.into());
+        }
         if ctx.accounts.ticket.index != ctx.accounts.pot.winning_slot {
             return Err(ErrorCode::TicketAccountNotWinning.into());
         }
@@ -154,7 +275,7 @@ pub mod open_lotto {
             return Err(ErrorCode::TicketAccountNotWinning.into());
         }
 
-        let prize_amount = ctx.accounts.pot.total_participants * POT_AMOUNT;
+        let prize_amount = ctx.accounts.pot.prize_amount();
 
         // Transfer tokens from escrow to winner using PDA signer
         let escrow_seeds = &[b"escrow".as_ref(), &[ctx.bumps.escrow_token_account]];
@@ -173,22 +294,224 @@ pub mod open_lotto {
             prize_amount,
         )?;
 
+        ctx.accounts.pot.prize_paid = true;
+        Ok(())
+    }
+
+    /// Push a settled pot's prize to the winner's associated token account
+    ///
+    /// Permissionless, for managers in auto-payout mode and pots drawn in it. If the
+    /// winner has no token account yet the caller creates it and is refunded from the
+    /// pot's payout reserve.
+    pub fn payout_winner(ctx: Context<PayoutWinner>) -> Result<()> {
+        if !ctx.accounts.pot_manager.auto_payout {
+            return Err(ErrorCode::AutoPayoutDisabled.into());
+        }
+        if !ctx.accounts.pot.settled {
+            return Err(ErrorCode::PotNotSettled.into());
+        }
+        if ctx.accounts.pot.prize_paid {
+            return Err(ErrorCode::PrizeAlreadyPaid.into());
+        }
+        // Drawn before auto-payout was switched on, so nothing would refund the caller
+        if ctx.accounts.pot.payout_reserve == 0 {
+            return Err(ErrorCode::NoPayoutReserve.into());
+        }
+
+        if ctx.accounts.winner_token_account.data_is_empty() {
+            let caller_lamports = ctx.accounts.caller.lamports();
+            associated_token::create(CpiContext::new(
+                ctx.accounts.associated_token_program.to_account_info(),
+                associated_token::Create {
+                    payer: ctx.accounts.caller.to_account_info(),
+                    associated_token: ctx.accounts.winner_token_account.to_account_info(),
+                    authority: ctx.accounts.winner.to_account_info(),
+                    mint: ctx.accounts.token_mint.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    token_program: ctx.accounts.token_program.to_account_info(),
+                },
+            ))?;
+
+            // The pot is program owned, so the refund moves its lamports directly
+            let spent = caller_lamports - ctx.accounts.caller.lamports();
+            let refund = spent.min(ctx.accounts.pot.payout_reserve);
+            ctx.accounts.pot.payout_reserve -= refund;
+            **ctx.accounts.pot.to_account_info().try_borrow_mut_lamports()? -= refund;
+            **ctx.accounts.caller.try_borrow_mut_lamports()? += refund;
+        }
+
+        let escrow_seeds = &[b"escrow".as_ref(), &[ctx.bumps.escrow_token_account]];
+        let signer_seeds = &[&escrow_seeds[..]];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.escrow_token_account.to_account_info(),
+                    to: ctx.accounts.winner_token_account.to_account_info(),
+                    authority: ctx.accounts.escrow_token_account.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            ctx.accounts.pot.prize_amount(),
+        )?;
+
+        ctx.accounts.pot.prize_paid = true;
+        Ok(())
+    }
+
+    /// Advance the manager to its next pot once the current one has ended and been settled
+    /// (or ended without tickets)
+    ///
+    /// Permissionless so that any keeper can keep the lottery rolling; the caller pays
+    /// rent for the newly created pot.
+    pub fn roll_pot_manager(ctx: Context<RollPotManager>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp as u64;
+        let pot_manager = &mut ctx.accounts.pot_manager;
+        let (current_end, next_end) = pot_manager.timestamps;
+        if now <= current_end {
+            return Err(ErrorCode::PotNotEnded.into());
+        }
+        // Rolling past an unsettled pot would leave its winner to be picked by nobody
+        let current_pot = &ctx.accounts.current_pot;
+        if !current_pot.settled && current_pot.total_participants != 0 {
+            return Err(ErrorCode::PotNotSettled.into());
+        }
+
+        let new_end = pot_manager.roll_end_timestamp();
+        ctx.accounts.new_pot.pot_manager = pot_manager.key();
+        ctx.accounts.new_pot.start_timestamp = next_end + 1;
+        ctx.accounts.new_pot.end_timestamp = new_end;
+        ctx.accounts.new_pot.total_participants = 0;
+
+        pot_manager.timestamps = (next_end, new_end);
+        Ok(())
+    }
+
+    /// Acquire, renew or take over the manager's keeper lease for `duration_slots`
+    ///
+    /// The holder can always renew; anyone can take over a lease that has lapsed, and the
+    /// manager authority can take it over at any time.
+    pub fn acquire_keeper_lease(ctx: Context<AcquireKeeperLease>, duration_slots: u64) -> Result<()> {
+        if duration_slots == 0 || duration_slots > MAX_LEASE_SLOTS {
+            return Err(ErrorCode::InvalidLeaseDuration.into());
+        }
+
+        let slot = Clock::get()?.slot;
+        let keeper = ctx.accounts.keeper.key();
+        let authority = ctx.accounts.pot_manager.authority;
+        let lease = &mut ctx.accounts.keeper_lease;
+        // Only keepers the authority allowed may hold the lease, so nobody can squat on it
+        if keeper != authority && !lease.keepers.contains(&keeper) {
+            return Err(ErrorCode::KeeperNotAllowed.into());
+        }
+        if lease.blocks(&keeper, slot) && keeper != authority {
+            return Err(ErrorCode::KeeperLeaseHeld.into());
+        }
+
+        lease.pot_manager = ctx.accounts.pot_manager.key();
+        lease.keeper = keeper;
+        lease.expiry_slot = slot + duration_slots;
+        lease.bump = ctx.bumps.keeper_lease;
+        Ok(())
+    }
+
+    /// Replace the keepers allowed to acquire the manager's lease (the authority always
+    /// may), revoking the lease from a holder that is no longer allowed
+    pub fn set_lease_keepers(ctx: Context<SetLeaseKeepers>, keepers: Vec<Pubkey>) -> Result<()> {
+        if keepers.len() > MAX_LEASE_KEEPERS {
+            return Err(ErrorCode::TooManyLeaseKeepers.into());
+        }
+
+        let authority = ctx.accounts.authority.key();
+        let lease = &mut ctx.accounts.keeper_lease;
+        if lease.keeper != authority && !keepers.contains(&lease.keeper) {
+            lease.keeper = Pubkey::default();
+            lease.expiry_slot = 0;
+        }
+        lease.pot_manager = ctx.accounts.pot_manager.key();
+        lease.bump = ctx.bumps.keeper_lease;
+        lease.keepers = keepers;
+        Ok(())
+    }
+
+    // ============ Admin Wager Instructions ============
+
+    /// Update the bounty, in token base units, paid from the treasury to whoever draws
+    /// each pot and whoever settles it
+    ///
+    /// The treasury pays out of the allowance its owner delegates to the `crankBounty`
+    /// PDA; without one, cranking is unpaid.
+    pub fn set_crank_bounty(ctx: Context<SetCrankBounty>, bounty: u64) -> Result<()> {
+        if bounty > MAX_CRANK_BOUNTY {
+            return Err(ErrorCode::BountyTooLarge.into());
+        }
+        ctx.accounts.pot_manager.crank_bounty = bounty;
+        Ok(())
+    }
+
+    /// Switch auto-payout mode, where settled prizes are pushed to winners by
+    /// `payout_winner` instead of waiting for `claim_prize`
+    pub fn set_auto_payout(ctx: Context<SetAutoPayout>, enabled: bool) -> Result<()> {
+        ctx.accounts.pot_manager.auto_payout = enabled;
+        Ok(())
+    }
+
+    /// Update the SOL wager escrowed on each draw
+    pub fn set_wager(ctx: Context<SetWager>, wager: u64) -> Result<()> {
+        ctx.accounts.pot_manager.wager = wager;
+        Ok(())
+    }
+
+    /// Withdraw wagers escrowed by this manager's draws, e.g. to pay the oracle operator
+    pub fn withdraw_wager_escrow(ctx: Context<WithdrawWagerEscrow>, amount: u64) -> Result<()> {
+        let pot_manager = &mut ctx.accounts.pot_manager;
+        if amount > pot_manager.wager_balance {
+            return Err(ErrorCode::InsufficientWagerBalance.into());
+        }
+
+        // The escrow is a plain system account, so it must stay rent exempt unless emptied
+        let remaining = ctx
+            .accounts
+            .wager_escrow
+            .lamports()
+            .checked_sub(amount)
+            .ok_or(ErrorCode::InsufficientWagerBalance)?;
+        if remaining != 0 && remaining < Rent::get()?.minimum_balance(0) {
+            return Err(ErrorCode::WagerEscrowBelowRent.into());
+        }
+
+        let wager_escrow_seeds = &[b"wagerEscrow".as_ref(), &[ctx.bumps.wager_escrow]];
+        let signer_seeds = &[&wager_escrow_seeds[..]];
+
+        anchor_lang::system_program::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.wager_escrow.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+        )?;
+
+        pot_manager.wager_balance -= amount;
         Ok(())
     }
 
     // ============ Admin Close Instructions ============
 
-    pub fn close_pot(ctx: Context<ClosePot>) -> Result<()> {
+    pub fn close_pot(_ctx: Context<ClosePot>) -> Result<()> {
         // Rent is returned to authority automatically via close constraint
         Ok(())
     }
 
-    pub fn close_pot_manager(ctx: Context<ClosePotManager>) -> Result<()> {
+    pub fn close_pot_manager(_ctx: Context<ClosePotManager>) -> Result<()> {
         // Rent is returned to authority automatically via close constraint
         Ok(())
     }
 
-    pub fn close_ticket(ctx: Context<CloseTicket>) -> Result<()> {
+    pub fn close_ticket(_ctx: Context<CloseTicket>) -> Result<()> {
         // Rent is returned to authority automatically via close constraint
         Ok(())
     }
@@ -232,6 +555,166 @@ pub mod open_lotto {
     }
 }
 
+/// Pay `bounty` from the treasury to the crank caller, capped at the allowance the treasury
+/// has delegated to `bounty_authority`, and record it against the pot
+fn pay_crank_bounty<'info>(
+    pot: &mut Account<'info, Pot>,
+    bounty: u64,
+    treasury_token_account: &Account<'info, TokenAccount>,
+    bounty_authority: &AccountInfo<'info>,
+    caller_token_account: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    bounty_authority_bump: u8,
+) -> Result<()> {
+    let allowance = if treasury_token_account.delegate == COption::Some(bounty_authority.key()) {
+        treasury_token_account.delegated_amount.min(treasury_token_account.amount)
+    } else {
+        0
+    };
+    let bounty = bounty.min(allowance);
+    if bounty == 0 {
+        return Ok(());
+    }
+
+    let bounty_authority_seeds = &[b"crankBounty".as_ref(), &[bounty_authority_bump]];
+    let signer_seeds = &[&bounty_authority_seeds[..]];
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Transfer {
+                from: treasury_token_account.to_account_info(),
+                to: caller_token_account.to_account_info(),
+                authority: bounty_authority.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        bounty,
+    )?;
+
+    pot.bounties_paid += bounty;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RollPotManager<'info> {
+    #[account(mut)]
+    pub pot_manager: Account<'info, PotManager>,
+
+    /// The pot the manager is rolling away from
+    #[account(
+        has_one = pot_manager,
+        seeds = [
+            b"pot",
+            pot_manager.key().as_ref(),
+            &pot_manager.timestamps.0.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub current_pot: Account<'info, Pot>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = Pot::space(),
+        seeds = [
+            b"pot",
+            pot_manager.key().as_ref(),
+            &pot_manager.roll_end_timestamp().to_le_bytes(),
+        ],
+        bump
+    )]
+    pub new_pot: Account<'info, Pot>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcquireKeeperLease<'info> {
+    pub pot_manager: Account<'info, PotManager>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = KeeperLease::space(),
+        seeds = [b"lease", pot_manager.key().as_ref()],
+        bump
+    )]
+    pub keeper_lease: Account<'info, KeeperLease>,
+
+    pub keeper: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetLeaseKeepers<'info> {
+    #[account(has_one = authority)]
+    pub pot_manager: Account<'info, PotManager>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = KeeperLease::space(),
+        seeds = [b"lease", pot_manager.key().as_ref()],
+        bump
+    )]
+    pub keeper_lease: Account<'info, KeeperLease>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetCrankBounty<'info> {
+    #[account(mut, has_one = authority)]
+    pub pot_manager: Account<'info, PotManager>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAutoPayout<'info> {
+    #[account(mut, has_one = authority)]
+    pub pot_manager: Account<'info, PotManager>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetWager<'info> {
+    #[account(mut, has_one = authority)]
+    pub pot_manager: Account<'info, PotManager>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawWagerEscrow<'info> {
+    #[account(mut, has_one = authority)]
+    pub pot_manager: Account<'info, PotManager>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: This is a PDA escrow account holding SOL for oracle wagers.
+    #[account(mut, seeds = [b"wagerEscrow".as_ref()], bump)]
+    pub wager_escrow: AccountInfo<'info>,
+
+    /// CHECK: Any system account receiving the wager, e.g. the oracle operator.
+    #[account(mut)]
+    pub destination: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 pub struct ClosePot<'info> {
     #[account(
@@ -331,26 +814,84 @@ pub struct ClaimPrize<'info> {
 }
 
 #[derive(Accounts)]
-pub struct SettleLottery<'info> {
+pub struct PayoutWinner<'info> {
+    #[account(mut, has_one = pot_manager)]
+    pub pot: Account<'info, Pot>,
+    pub pot_manager: Account<'info, PotManager>,
+    /// The pot's winning ticket
+    #[account(seeds = [b"ticket", pot.key().as_ref(), &pot.winning_slot.to_le_bytes()], bump)]
+    pub ticket: Account<'info, Ticket>,
+    /// CHECK: Winner's wallet - validated via ticket.participant
+    #[account(address = ticket.participant)]
+    pub winner: AccountInfo<'info>,
+    /// CHECK: The winner's associated token account, created in the handler if missing
+    #[account(
+        mut,
+        address = get_associated_token_address(&winner.key(), &pot_manager.token_mint),
+    )]
+    pub winner_token_account: AccountInfo<'info>,
+    /// Escrow token account holding prize pool
+    #[account(mut, seeds = [b"escrow"], bump)]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+    #[account(address = pot_manager.token_mint)]
+    pub token_mint: Account<'info, Mint>,
     #[account(mut)]
+    pub caller: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleLottery<'info> {
+    #[account(mut, has_one = pot_manager)]
     pub pot: Account<'info, Pot>,
-    /// CHECK: The account's data is validated manually within the handler.
+    pub pot_manager: Account<'info, PotManager>,
+    /// CHECK: Owned by Switchboard; the account's data is validated manually within the handler.
+    #[account(owner = SWITCHBOARD_ON_DEMAND_PROGRAM_ID)]
     pub randomness_account_data: AccountInfo<'info>,
     pub user: Signer<'info>,
+    /// Treasury token account the bounty is paid from
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+    /// CHECK: PDA the treasury delegates its bounty allowance to; signs the bounty transfer
+    #[account(seeds = [b"crankBounty"], bump)]
+    pub bounty_authority: AccountInfo<'info>,
+    /// Token account receiving the crank bounty, chosen by the caller
+    #[account(mut, token::mint = pot_manager.token_mint)]
+    pub caller_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
 pub struct DrawLottery<'info> {
-    #[account(mut)]
+    #[account(mut, has_one = pot_manager)]
     pub pot: Account<'info, Pot>,
     #[account(mut)]
+    pub pot_manager: Account<'info, PotManager>,
+    #[account(mut)]
     pub authority: Signer<'info>,
-    /// CHECK: The account's data is validated manually within the handler.
+    /// CHECK: Owned by Switchboard; the account's data is validated manually within the handler.
+    #[account(owner = SWITCHBOARD_ON_DEMAND_PROGRAM_ID)]
     pub randomness_account_data: AccountInfo<'info>,
     /// CHECK: This is a PDA escrow account holding SOL for oracle wagers.
     #[account(mut, seeds = [b"wagerEscrow".as_ref()], bump)]
     pub wager_escrow: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
+    /// CHECK: The manager's keeper lease PDA, read in the handler; managers without a
+    /// lease never created it.
+    #[account(seeds = [b"lease", pot_manager.key().as_ref()], bump)]
+    pub keeper_lease: AccountInfo<'info>,
+    /// Treasury token account the bounty is paid from
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+    /// CHECK: PDA the treasury delegates its bounty allowance to; signs the bounty transfer
+    #[account(seeds = [b"crankBounty"], bump)]
+    pub bounty_authority: AccountInfo<'info>,
+    /// Token account receiving the crank bounty, chosen by the caller
+    #[account(mut, token::mint = pot_manager.token_mint)]
+    pub caller_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
@@ -475,6 +1016,8 @@ pub struct InitPotManager<'info> {
 }
 
 // address: program-id + "potmanager"
+// Layouts are unversioned: accounts from an older layout are force-closed and re-created
+// on redeploy, not migrated (see README "Accounts")
 #[account]
 pub struct PotManager {
     pub authority: Pubkey,
@@ -484,6 +1027,10 @@ pub struct PotManager {
     pub last_random_number: u64,
     pub timestamps: (u64, u64),
     pub bump: u8,
+    pub wager: u64,         // SOL wager escrowed on each draw
+    pub wager_balance: u64, // Wagers escrowed and not yet withdrawn
+    pub crank_bounty: u64,  // Tokens paid from the treasury per draw and per settle
+    pub auto_payout: bool,  // Push prizes to winners instead of waiting for claims
     pub name: String, // Max 32 bytes (PDA seed limit)
 }
 
@@ -499,8 +1046,18 @@ impl PotManager {
         8 +  // last_random_number
         16 + // timestamps (u64, u64)
         1 +  // bump
+        8 +  // wager
+        8 +  // wager_balance
+        8 +  // crank_bounty
+        1 +  // auto_payout
         4 + Self::MAX_NAME_LEN // name (4 bytes for string length prefix + max content)
     }
+
+    /// End timestamp of the pot created by the next roll (pots are evenly spaced)
+    pub fn roll_end_timestamp(&self) -> u64 {
+        let (current_end, next_end) = self.timestamps;
+        next_end + (next_end - current_end)
+    }
 }
 
 // address: program-id + "pot" + pot end timestamp`
@@ -512,6 +1069,13 @@ pub struct Pot {
     pub end_timestamp: u64,
     pub winning_slot: u64,
     pub randomness_account: Pubkey,
+    pub wager: u64, // SOL wager escrowed by this pot's draws
+    pub settled: bool,
+    pub bounties_paid: u64, // Crank bounties the treasury paid for this pot
+    pub prize_paid: bool,
+    pub payout_reserve: u64, // Lamports set aside for the winner's token account rent
+    pub seed_slot: u64, // Slot the drawn randomness was committed at
+    pub draw_slot: u64, // Slot of the latest draw, which starts the redraw timeout
 }
 
 impl Pot {
@@ -522,7 +1086,60 @@ impl Pot {
         8 +  // start_ts
         8 +  // end_ts
         8 +  // winning_slot
-        32   // randomness_account
+        32 + // randomness_account
+        8 +  // wager
+        1 +  // settled
+        8 +  // bounties_paid
+        1 +  // prize_paid
+        8 +  // payout_reserve
+        8 +  // seed_slot
+        8    // draw_slot
+    }
+
+    /// Tokens paid to the winner: every ticket's prize-pool share
+    pub fn prize_amount(&self) -> u64 {
+        self.total_participants * POT_AMOUNT
+    }
+
+    /// Whether a draw has gone unsettled long enough at `slot` to be replaced
+    pub fn redraw_open(&self, slot: u64) -> bool {
+        self.randomness_account != Pubkey::default()
+            && !self.settled
+            && slot >= self.draw_slot + REDRAW_TIMEOUT_SLOTS
+    }
+}
+
+// address: program-id + "lease" + pot manager
+#[account]
+pub struct KeeperLease {
+    pub pot_manager: Pubkey,
+    pub keeper: Pubkey,
+    pub expiry_slot: u64, // Last slot the lease holds
+    pub bump: u8,
+    pub keepers: Vec<Pubkey>, // Keepers besides the authority allowed to acquire the lease
+}
+
+impl KeeperLease {
+    pub fn space() -> usize {
+        8 +  // discriminator
+        32 + // pot_manager
+        32 + // keeper
+        8 +  // expiry_slot
+        1 +  // bump
+        4 + 32 * MAX_LEASE_KEEPERS // keepers
+    }
+
+    /// Whether the lease keeps `keeper` from drawing at `slot`
+    pub fn blocks(&self, keeper: &Pubkey, slot: u64) -> bool {
+        self.keeper != *keeper && slot <= self.expiry_slot
+    }
+
+    /// The lease behind `info`, or `None` if it was never acquired
+    pub fn load(info: &AccountInfo) -> Result<Option<Self>> {
+        if info.data_is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self::try_deserialize(&mut &info.data.borrow()[..])?))
     }
 }
 
@@ -558,6 +1175,38 @@ pub enum ErrorCode {
     RandomnessNotResolved,
     #[msg("Ticket account is not winning")]
     TicketAccountNotWinning,
+    #[msg("Amount exceeds the manager's escrowed wagers")]
+    InsufficientWagerBalance,
+    #[msg("Withdrawal would leave the wager escrow below rent exemption")]
+    WagerEscrowBelowRent,
+    #[msg("The current pot has not ended yet")]
+    PotNotEnded,
+    #[msg("The pot has already been settled")]
+    PotAlreadySettled,
+    #[msg("Another keeper holds this manager's lease")]
+    KeeperLeaseHeld,
+    #[msg("Lease duration must be between 1 and MAX_LEASE_SLOTS slots")]
+    InvalidLeaseDuration,
+    #[msg("The pot has already been drawn")]
+    PotAlreadyDrawn,
+    #[msg("The pot has not been settled yet")]
+    PotNotSettled,
+    #[msg("The prize has already been paid")]
+    PrizeAlreadyPaid,
+    #[msg("The manager is not in auto-payout mode")]
+    AutoPayoutDisabled,
+    #[msg("Crank bounty exceeds MAX_CRANK_BOUNTY")]
+    BountyTooLarge,
+    #[msg("The pot has no tickets")]
+    PotEmpty,
+    #[msg("Randomness was re-committed after the draw")]
+    RandomnessSeedMismatch,
+    #[msg("Keeper is not allowed to acquire this manager's lease")]
+    KeeperNotAllowed,
+    #[msg("More than MAX_LEASE_KEEPERS keepers")]
+    TooManyLeaseKeepers,
+    #[msg("The pot was drawn outside auto-payout mode")]
+    NoPayoutReserve,
 }
 
 impl ErrorCode {
@@ -1,12 +1,16 @@
-use anchor_lang::prelude::*;
-
 #[cfg(test)]
 mod test {
     use anchor_lang::prelude::{AccountMeta, Clock, Rent};
     use anchor_lang::InstructionData;
     use litesvm::LiteSVM;
-    use open_lotto::instruction::{InitPotManager, EnterTicket, DrawLottery};
-    use open_lotto::{ErrorCode, Pot};
+    use anchor_lang::AccountSerialize;
+    use open_lotto::instruction::{InitPotManager, EnterTicket, DrawLottery, SettleLottery, SetCrankBounty, SetAutoPayout, PayoutWinner, WithdrawWagerEscrow, RollPotManager, AcquireKeeperLease, SetLeaseKeepers};
+    use anchor_spl::associated_token::{self, get_associated_token_address};
+    use anchor_lang::error::ErrorCode as AnchorErrorCode;
+    use open_lotto::{
+        ErrorCode, KeeperLease, Pot, FEE, MAX_CRANK_BOUNTY, MAX_LEASE_KEEPERS, MAX_LEASE_SLOTS, POT_AMOUNT,
+        REDRAW_TIMEOUT_SLOTS,
+    };
     use open_lotto::PotManager;
     use solana_keypair::Keypair;
     use solana_message::Message;
@@ -20,9 +24,419 @@ mod test {
     use rand::{thread_rng, Rng};
     use spl_token::state::{Mint, Account as TokenAccount};
     use solana_program::program_pack::Pack;
+    use solana_program_runtime::declare_process_instruction;
+    use switchboard_on_demand::{Discriminator, RandomnessAccountData};
 
     static PROGRAM_BYTES: &[u8] = include_bytes!("../../../target/deploy/open_lotto.so");
 
+    const INIT_TIMESTAMP: i64 = 1_725_000_000;
+
+    // Stand-in for Switchboard's randomnessCommit and randomnessReveal on the randomness
+    // account passed as the first account. Without instruction data it commits, recording
+    // the previous slot as the seed slot; with a 32-byte value it reveals that value at the
+    // current slot.
+    declare_process_instruction!(MockSwitchboard, 150, |invoke_context| {
+        let slot = invoke_context.get_sysvar_cache().get_clock()?.slot;
+        let transaction_context = &invoke_context.transaction_context;
+        let instruction_context = transaction_context.get_current_instruction_context()?;
+        let value = instruction_context.get_instruction_data().to_vec();
+        let mut randomness = instruction_context.try_borrow_instruction_account(transaction_context, 0)?;
+        let data = randomness.get_data_mut()?;
+        if value.is_empty() {
+            // seed_slot: discriminator + authority + queue + seed_slothash
+            data[104..112].copy_from_slice(&(slot - 1).to_le_bytes());
+        } else {
+            // reveal_slot and value follow seed_slot and oracle
+            data[144..152].copy_from_slice(&slot.to_le_bytes());
+            data[152..184].copy_from_slice(&value);
+        }
+        Ok(())
+    });
+
+    /// A LiteSVM with open-lotto and SPL Token loaded, and the addresses of a "daily"
+    /// manager owned by `payer`
+    struct TestLotto {
+        svm: LiteSVM,
+        clock: Clock,
+        program_id: Pubkey,
+        switchboard_pid: Pubkey,
+        payer: Keypair,
+        mint: Pubkey,
+        end_ts: u64,
+        pot_duration: u64,
+        pot_manager: Pubkey,
+        manager_bump: u8,
+        first_pot: Pubkey,
+        next_pot: Pubkey,
+        treasury_token_account: Pubkey,
+        escrow_token_account: Pubkey,
+        bounty_authority: Pubkey,
+        wager_escrow: Pubkey,
+        keeper_lease: Pubkey,
+    }
+
+    impl TestLotto {
+        /// Load the programs at slot 1 and derive the manager's accounts, without
+        /// initializing it
+        fn new(end_ts: u64, pot_duration: u64) -> Self {
+            let mut svm = LiteSVM::new();
+            let clock = Clock {
+                slot: 1,
+                epoch_start_timestamp: 0,
+                epoch: 0,
+                leader_schedule_epoch: 0,
+                unix_timestamp: INIT_TIMESTAMP,
+            };
+            svm.set_sysvar(&clock);
+            let program_id = open_lotto::ID;
+            svm.add_program(program_id, PROGRAM_BYTES).unwrap();
+            svm.add_program(spl_token::id(), include_bytes!("spl_token.so")).unwrap();
+            // The mock commit runs as the real Switchboard program, which draws and settles
+            // require to own the randomness account
+            let switchboard_pid = open_lotto::SWITCHBOARD_ON_DEMAND_PROGRAM_ID;
+            svm.add_builtin(switchboard_pid, MockSwitchboard::vm);
+            // add_builtin leaves the program account to the BPF loader, which won't run builtins
+            svm.set_account(switchboard_pid, SolanaAccount {
+                lamports: 1,
+                data: vec![],
+                owner: solana_sdk_ids::native_loader::id(),
+                executable: true,
+                rent_epoch: 0,
+            }).unwrap();
+
+            // payer
+            let payer = Keypair::new();
+            svm.airdrop(&payer.pubkey(), 1_000_000_000).unwrap();
+
+            // Create token mint
+            let mint = Pubkey::new_unique();
+            svm.set_account(mint, create_mint_account(&payer.pubkey())).unwrap();
+
+            // Derive PDAs
+            let (pot_manager, manager_bump) = Pubkey::find_program_address(
+                &[b"manager", payer.pubkey().as_ref(), b"daily"],
+                &program_id
+            );
+            let (first_pot, _) = Pubkey::find_program_address(
+                &[b"pot", pot_manager.as_ref(), &end_ts.to_le_bytes()],
+                &program_id
+            );
+            let (next_pot, _) = Pubkey::find_program_address(
+                &[b"pot", pot_manager.as_ref(), &(end_ts + pot_duration).to_le_bytes()],
+                &program_id
+            );
+            let (treasury_token_account, _) = Pubkey::find_program_address(&[b"treasury"], &program_id);
+            let (escrow_token_account, _) = Pubkey::find_program_address(&[b"escrow"], &program_id);
+            let (bounty_authority, _) = Pubkey::find_program_address(&[b"crankBounty"], &program_id);
+            let (wager_escrow, _) = Pubkey::find_program_address(&[b"wagerEscrow"], &program_id);
+            let (keeper_lease, _) = Pubkey::find_program_address(&[b"lease", pot_manager.as_ref()], &program_id);
+
+            Self {
+                svm,
+                clock,
+                program_id,
+                switchboard_pid,
+                payer,
+                mint,
+                end_ts,
+                pot_duration,
+                pot_manager,
+                manager_bump,
+                first_pot,
+                next_pot,
+                treasury_token_account,
+                escrow_token_account,
+                bounty_authority,
+                wager_escrow,
+                keeper_lease,
+            }
+        }
+
+        /// A manager whose first pot ends 100 seconds after `INIT_TIMESTAMP`, escrowing
+        /// `wager` lamports per draw
+        fn initialized(wager: u64) -> Self {
+            let mut lotto = Self::new(INIT_TIMESTAMP as u64 + 100, 100);
+            let result = lotto.init_manager(wager);
+            assert!(result.is_ok(), "InitPotManager failed: {:?}", result);
+            lotto
+        }
+
+        fn init_manager(&mut self, wager: u64) -> Result<(), TransactionError> {
+            let accounts = vec![
+                AccountMeta::new(self.pot_manager, false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new(self.treasury_token_account, false),
+                AccountMeta::new(self.escrow_token_account, false),
+                AccountMeta::new(self.first_pot, false),
+                AccountMeta::new(self.next_pot, false),
+                AccountMeta::new(self.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(solana_program::sysvar::rent::ID, false),
+            ];
+            let data = InitPotManager {
+                end_ts: self.end_ts,
+                pot_duration: self.pot_duration,
+                manager_name: String::from("daily"),
+                wager,
+            }.data();
+            let ix = Instruction::new_with_bytes(self.program_id, &data, accounts);
+            send(&mut self.svm, &[ix], &self.payer)
+        }
+
+        fn warp(&mut self, slot: u64, unix_timestamp: i64) {
+            self.clock.slot = slot;
+            self.clock.unix_timestamp = unix_timestamp;
+            self.svm.set_sysvar(&self.clock);
+        }
+
+        /// Move past the first pot's end, keeping the current slot
+        fn end_first_pot(&mut self) {
+            self.warp(self.clock.slot, self.end_ts as i64 + 1);
+        }
+
+        fn funded_keypair(&mut self) -> Keypair {
+            let keypair = Keypair::new();
+            self.svm.airdrop(&keypair.pubkey(), 1_000_000_000).unwrap();
+            keypair
+        }
+
+        fn token_account(&mut self, owner: &Pubkey, amount: u64) -> Pubkey {
+            let pubkey = Pubkey::new_unique();
+            self.svm.set_account(pubkey, create_token_account(&self.mint, owner, amount)).unwrap();
+            pubkey
+        }
+
+        /// Enter `user` into the first pot with a freshly funded token account, returning
+        /// the ticket
+        fn enter_ticket(&mut self, user: &Keypair) -> Pubkey {
+            let user_token_account = self.token_account(&user.pubkey(), 100_000_000);
+            let pot: Pot = get_account(&self.first_pot, &self.svm);
+            let (ticket, _) = Pubkey::find_program_address(
+                &[b"ticket", self.first_pot.as_ref(), &pot.total_participants.to_le_bytes()],
+                &self.program_id
+            );
+            let ix = Instruction::new_with_bytes(
+                self.program_id,
+                &EnterTicket {}.data(),
+                vec![
+                    AccountMeta::new(user.pubkey(), true),
+                    AccountMeta::new(self.first_pot, false),
+                    AccountMeta::new(ticket, false),
+                    AccountMeta::new(user_token_account, false),
+                    AccountMeta::new(self.escrow_token_account, false),
+                    AccountMeta::new(self.treasury_token_account, false),
+                    AccountMeta::new_readonly(self.mint, false),
+                    AccountMeta::new_readonly(spl_token::id(), false),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                ],
+            );
+            let result = send(&mut self.svm, &[ix], user);
+            assert!(result.is_ok(), "EnterTicket failed: {:?}", result);
+            ticket
+        }
+
+        /// Mock Switchboard randomness account committed at `seed_slot` and revealed with
+        /// a random value at `reveal_slot` (0 for not yet)
+        fn randomness_account(&mut self, seed_slot: u64, reveal_slot: u64) -> Pubkey {
+            let mut randomness: RandomnessAccountData = bytemuck::Zeroable::zeroed();
+            randomness.seed_slot = seed_slot;
+            randomness.reveal_slot = reveal_slot;
+            if reveal_slot != 0 {
+                randomness.value = thread_rng().gen();
+            }
+            let mut data = RandomnessAccountData::DISCRIMINATOR.to_vec();
+            data.extend_from_slice(bytemuck::bytes_of(&randomness));
+
+            let pubkey = Pubkey::new_unique();
+            self.svm.set_account(pubkey, SolanaAccount {
+                lamports: 1_000_000,
+                data,
+                owner: self.switchboard_pid,
+                executable: false,
+                rent_epoch: 0,
+            }).unwrap();
+            pubkey
+        }
+
+        /// The mock Switchboard commit, setting the account's seed slot to the previous slot
+        fn commit_ix(&self, randomness: &Pubkey) -> Instruction {
+            Instruction::new_with_bytes(
+                self.switchboard_pid,
+                &[],
+                vec![AccountMeta::new(*randomness, false)],
+            )
+        }
+
+        /// The mock Switchboard reveal of `value`, at the current slot
+        fn reveal_ix(&self, randomness: &Pubkey, value: [u8; 32]) -> Instruction {
+            Instruction::new_with_bytes(
+                self.switchboard_pid,
+                &value,
+                vec![AccountMeta::new(*randomness, false)],
+            )
+        }
+
+        fn draw_ix(&self, caller: &Pubkey, randomness: &Pubkey, bounty_account: &Pubkey) -> Instruction {
+            Instruction::new_with_bytes(
+                self.program_id,
+                &DrawLottery { randomness_account: *randomness }.data(),
+                vec![
+                    AccountMeta::new(self.first_pot, false),
+                    AccountMeta::new(self.pot_manager, false),
+                    AccountMeta::new(*caller, true),
+                    AccountMeta::new_readonly(*randomness, false),
+                    AccountMeta::new(self.wager_escrow, false),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                    AccountMeta::new_readonly(self.keeper_lease, false),
+                    AccountMeta::new(self.treasury_token_account, false),
+                    AccountMeta::new_readonly(self.bounty_authority, false),
+                    AccountMeta::new(*bounty_account, false),
+                    AccountMeta::new_readonly(spl_token::id(), false),
+                ],
+            )
+        }
+
+        fn settle_ix(&self, caller: &Pubkey, randomness: &Pubkey, bounty_account: &Pubkey) -> Instruction {
+            Instruction::new_with_bytes(
+                self.program_id,
+                &SettleLottery {}.data(),
+                vec![
+                    AccountMeta::new(self.first_pot, false),
+                    AccountMeta::new_readonly(self.pot_manager, false),
+                    AccountMeta::new_readonly(*randomness, false),
+                    AccountMeta::new_readonly(*caller, true),
+                    AccountMeta::new(self.treasury_token_account, false),
+                    AccountMeta::new_readonly(self.bounty_authority, false),
+                    AccountMeta::new(*bounty_account, false),
+                    AccountMeta::new_readonly(spl_token::id(), false),
+                ],
+            )
+        }
+
+        fn payout_ix(&self, ticket: &Pubkey, winner: &Pubkey, caller: &Pubkey) -> Instruction {
+            Instruction::new_with_bytes(
+                self.program_id,
+                &PayoutWinner {}.data(),
+                vec![
+                    AccountMeta::new(self.first_pot, false),
+                    AccountMeta::new_readonly(self.pot_manager, false),
+                    AccountMeta::new_readonly(*ticket, false),
+                    AccountMeta::new_readonly(*winner, false),
+                    AccountMeta::new(get_associated_token_address(winner, &self.mint), false),
+                    AccountMeta::new(self.escrow_token_account, false),
+                    AccountMeta::new_readonly(self.mint, false),
+                    AccountMeta::new(*caller, true),
+                    AccountMeta::new_readonly(spl_token::id(), false),
+                    AccountMeta::new_readonly(associated_token::ID, false),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                ],
+            )
+        }
+
+        /// Roll the manager from the first pot, paid for by `payer`; returns the instruction
+        /// and the pot it creates
+        fn roll_ix(&self, payer: &Pubkey) -> (Instruction, Pubkey) {
+            let (rolled_pot, _) = Pubkey::find_program_address(
+                &[b"pot", self.pot_manager.as_ref(), &(self.end_ts + 2 * self.pot_duration).to_le_bytes()],
+                &self.program_id
+            );
+            let ix = Instruction::new_with_bytes(
+                self.program_id,
+                &RollPotManager {}.data(),
+                vec![
+                    AccountMeta::new(self.pot_manager, false),
+                    AccountMeta::new_readonly(self.first_pot, false),
+                    AccountMeta::new(rolled_pot, false),
+                    AccountMeta::new(*payer, true),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                ],
+            );
+            (ix, rolled_pot)
+        }
+
+        /// Enter `user`, then end the first pot and draw it at slot 2 with randomness
+        /// revealed at that slot, returning the ticket and the randomness account
+        fn drawn_pot(&mut self, user: &Keypair) -> (Pubkey, Pubkey) {
+            self.enter_ticket(user);
+            self.drawn_pot_entered()
+        }
+
+        /// `drawn_pot` for a first pot that already has its tickets
+        fn drawn_pot_entered(&mut self) -> (Pubkey, Pubkey) {
+            let (ticket, _) = Pubkey::find_program_address(
+                &[b"ticket", self.first_pot.as_ref(), &0u64.to_le_bytes()],
+                &self.program_id
+            );
+            let randomness = self.randomness_account(1, 2);
+            self.warp(2, self.end_ts as i64 + 1);
+            let bounty_account = self.token_account(&self.payer.pubkey(), 0);
+            let ix = self.draw_ix(&self.payer.pubkey(), &randomness, &bounty_account);
+            let result = send(&mut self.svm, &[ix], &self.payer);
+            assert!(result.is_ok(), "DrawLottery failed: {:?}", result);
+            (ticket, randomness)
+        }
+
+        fn set_crank_bounty(&mut self, bounty: u64) -> Result<(), TransactionError> {
+            let ix = Instruction::new_with_bytes(
+                self.program_id,
+                &SetCrankBounty { bounty }.data(),
+                vec![
+                    AccountMeta::new(self.pot_manager, false),
+                    AccountMeta::new_readonly(self.payer.pubkey(), true),
+                ],
+            );
+            send(&mut self.svm, &[ix], &self.payer)
+        }
+
+        fn set_auto_payout(&mut self, enabled: bool) -> Result<(), TransactionError> {
+            let ix = Instruction::new_with_bytes(
+                self.program_id,
+                &SetAutoPayout { enabled }.data(),
+                vec![
+                    AccountMeta::new(self.pot_manager, false),
+                    AccountMeta::new_readonly(self.payer.pubkey(), true),
+                ],
+            );
+            send(&mut self.svm, &[ix], &self.payer)
+        }
+
+        /// Let the program pay up to `allowance` of crank bounties out of the treasury
+        fn approve_bounty_allowance(&mut self, allowance: u64) {
+            let ix = spl_token::instruction::approve(
+                &spl_token::id(),
+                &self.treasury_token_account,
+                &self.bounty_authority,
+                &self.payer.pubkey(),
+                &[],
+                allowance,
+            ).unwrap();
+            let result = send(&mut self.svm, &[ix], &self.payer);
+            assert!(result.is_ok(), "Approve failed: {:?}", result);
+        }
+
+        /// Overwrite a program account's data in place, keeping its size
+        fn write_account<A: AccountSerialize>(&mut self, pubkey: Pubkey, state: &A) {
+            let mut account = self.svm.get_account(&pubkey).unwrap();
+            let mut data = Vec::new();
+            state.try_serialize(&mut data).unwrap();
+            account.data[..data.len()].copy_from_slice(&data);
+            self.svm.set_account(pubkey, account).unwrap();
+        }
+    }
+
+    /// Send `instructions` in a fresh transaction signed and paid for by `signer`
+    fn send(svm: &mut LiteSVM, instructions: &[Instruction], signer: &Keypair) -> Result<(), TransactionError> {
+        svm.expire_blockhash();
+        let message = Message::new(instructions, Some(&signer.pubkey()));
+        let tx = Transaction::new(&[signer], message, svm.latest_blockhash());
+        svm.send_transaction(tx).map(|_| ()).map_err(|e| e.err)
+    }
+
+    fn program_error(index: u8, code: ErrorCode) -> TransactionError {
+        TransactionError::InstructionError(index, InstructionError::Custom(code.as_u32()))
+    }
+
     /// Create a mock SPL token mint account
     fn create_mint_account(mint_authority: &Pubkey) -> SolanaAccount {
         let mut data = vec![0u8; Mint::LEN];
@@ -70,353 +484,590 @@ mod test {
 
     #[test]
     fn test_fail_if_end_timestamp_passed() {
-        // load program
-        let mut svm = LiteSVM::new();
-        // Prepare a fake timestamp
-        let fake_clock = Clock {
-            slot: 1,
-            epoch_start_timestamp: 0,
-            epoch: 0,
-            leader_schedule_epoch: 0,
-            unix_timestamp: 1_725_000_000,
-        };
-        svm.set_sysvar(&fake_clock);
-        let program_id = open_lotto::ID;
-        svm.add_program(program_id, PROGRAM_BYTES);
-        svm.add_program(spl_token::id(), include_bytes!("spl_token.so"));
-
-        // payer
-        let payer = Keypair::new();
-        svm.airdrop(&payer.pubkey(), 1_000_000_000);
-        let end_ts: u64 = 5; // In the past
-        let pot_duration: u64 = 10;
-        let manager_name = String::from("daily");
-
-        // Create token mint
-        let mint_keypair = Keypair::new();
-        let mint_account = create_mint_account(&payer.pubkey());
-        svm.set_account(mint_keypair.pubkey(), mint_account);
-
-        // Derive PDAs
-        let (pot_manager, _) = Pubkey::find_program_address(
-            &[b"manager", payer.pubkey().as_ref(), manager_name.as_bytes()],
-            &program_id
-        );
-        let (first_pot, _) = Pubkey::find_program_address(
-            &[b"pot", pot_manager.as_ref(), &end_ts.to_le_bytes()],
-            &program_id
-        );
-        let (next_pot, _) = Pubkey::find_program_address(
-            &[b"pot", pot_manager.as_ref(), &(end_ts + pot_duration).to_le_bytes()],
-            &program_id
-        );
-        let (treasury_token_account, _) = Pubkey::find_program_address(
-            &[b"treasury"],
-            &program_id
-        );
-        let (escrow_token_account, _) = Pubkey::find_program_address(
-            &[b"escrow"],
-            &program_id
-        );
-
-        let accounts = vec![
-            AccountMeta::new(pot_manager, false),
-            AccountMeta::new_readonly(mint_keypair.pubkey(), false),
-            AccountMeta::new(treasury_token_account, false),
-            AccountMeta::new(escrow_token_account, false),
-            AccountMeta::new(first_pot, false),
-            AccountMeta::new(next_pot, false),
-            AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(system_program::ID, false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-            AccountMeta::new_readonly(solana_program::sysvar::rent::ID, false),
-        ];
-
-        let data = InitPotManager {
-            end_ts,
-            pot_duration,
-            manager_name: manager_name
-        }.data();
-        let ix = Instruction::new_with_bytes(program_id, &data, accounts);
-        let message = Message::new(&[ix], Some(&payer.pubkey()));
-        let tx = Transaction::new(&[&payer], message, svm.latest_blockhash());
-        let result = svm.send_transaction(tx);
-        let r = result.unwrap_err().err;
-        assert_eq!(r, TransactionError::InstructionError(0, InstructionError::Custom(ErrorCode::EndTimestampPassed.as_u32())));
+        let mut lotto = TestLotto::new(5, 10); // In the past
+        let r = lotto.init_manager(100).unwrap_err();
+        assert_eq!(r, program_error(0, ErrorCode::EndTimestampPassed));
     }
 
     #[test]
     fn test_open_lotto_init() {
-        let init_timestamp = 1_725_000_000;
-        let mut svm = LiteSVM::new();
-
-        let fake_clock = Clock {
-            slot: 1,
-            epoch_start_timestamp: 0,
-            epoch: 0,
-            leader_schedule_epoch: 0,
-            unix_timestamp: init_timestamp,
-        };
-        svm.set_sysvar(&fake_clock);
-        let program_id = open_lotto::ID;
-        svm.add_program(program_id, PROGRAM_BYTES);
-        svm.add_program(spl_token::id(), include_bytes!("spl_token.so"));
-
-        // payer
-        let payer = Keypair::new();
-        svm.airdrop(&payer.pubkey(), 1_000_000_000);
-        let end_ts: u64 = init_timestamp as u64 + 5;
-        let pot_duration: u64 = 10;
-        let manager_name = String::from("daily");
-
-        // Create token mint
-        let mint_keypair = Keypair::new();
-        let mint_account = create_mint_account(&payer.pubkey());
-        svm.set_account(mint_keypair.pubkey(), mint_account);
-
-        // Derive PDAs
-        let (pot_manager, bump) = Pubkey::find_program_address(
-            &[b"manager", payer.pubkey().as_ref(), manager_name.as_bytes()],
-            &program_id
-        );
-        let (first_pot, _) = Pubkey::find_program_address(
-            &[b"pot", pot_manager.as_ref(), &end_ts.to_le_bytes()],
-            &program_id
-        );
-        let (next_pot, _) = Pubkey::find_program_address(
-            &[b"pot", pot_manager.as_ref(), &(end_ts + pot_duration).to_le_bytes()],
-            &program_id
-        );
-        let (treasury_token_account, _) = Pubkey::find_program_address(
-            &[b"treasury"],
-            &program_id
-        );
-        let (escrow_token_account, _) = Pubkey::find_program_address(
-            &[b"escrow"],
-            &program_id
-        );
-
-        let accounts = vec![
-            AccountMeta::new(pot_manager, false),
-            AccountMeta::new_readonly(mint_keypair.pubkey(), false),
-            AccountMeta::new(treasury_token_account, false),
-            AccountMeta::new(escrow_token_account, false),
-            AccountMeta::new(first_pot, false),
-            AccountMeta::new(next_pot, false),
-            AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(system_program::ID, false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-            AccountMeta::new_readonly(solana_program::sysvar::rent::ID, false),
-        ];
-
-        let data = InitPotManager {
-            end_ts,
-            pot_duration,
-            manager_name: manager_name.clone()
-        }.data();
-        let ix = Instruction::new_with_bytes(program_id, &data, accounts);
-        let message = Message::new(&[ix], Some(&payer.pubkey()));
-        let tx = Transaction::new(&[&payer], message, svm.latest_blockhash());
-        let result = svm.send_transaction(tx);
+        let end_ts = INIT_TIMESTAMP as u64 + 5;
+        let mut lotto = TestLotto::new(end_ts, 10);
+        let result = lotto.init_manager(100);
         assert!(result.is_ok(), "InitPotManager failed: {:?}", result);
 
         // Check pot manager
-        let created_pot_manager: PotManager = get_account(&pot_manager, &svm);
-        assert_eq!(created_pot_manager.authority, payer.pubkey());
-        assert_eq!(created_pot_manager.treasury, treasury_token_account);
-        assert_eq!(created_pot_manager.token_mint, mint_keypair.pubkey());
-        assert_eq!(created_pot_manager.bump, bump);
-        assert_eq!(created_pot_manager.timestamps, (end_ts, end_ts + pot_duration));
+        let created_pot_manager: PotManager = get_account(&lotto.pot_manager, &lotto.svm);
+        assert_eq!(created_pot_manager.authority, lotto.payer.pubkey());
+        assert_eq!(created_pot_manager.treasury, lotto.treasury_token_account);
+        assert_eq!(created_pot_manager.token_mint, lotto.mint);
+        assert_eq!(created_pot_manager.bump, lotto.manager_bump);
+        assert_eq!(created_pot_manager.timestamps, (end_ts, end_ts + 10));
 
         // Check first pot
-        let created_first_pot: Pot = get_account(&first_pot, &svm);
+        let created_first_pot: Pot = get_account(&lotto.first_pot, &lotto.svm);
         assert_eq!(created_first_pot.total_participants, 0);
-        assert_eq!(created_first_pot.start_timestamp, init_timestamp as u64);
+        assert_eq!(created_first_pot.start_timestamp, INIT_TIMESTAMP as u64);
         assert_eq!(created_first_pot.end_timestamp, end_ts);
 
         // Check second pot
-        let created_second_pot: Pot = get_account(&next_pot, &svm);
+        let created_second_pot: Pot = get_account(&lotto.next_pot, &lotto.svm);
         assert_eq!(created_second_pot.total_participants, 0);
         assert_eq!(created_second_pot.start_timestamp, end_ts + 1);
-        assert_eq!(created_second_pot.end_timestamp, end_ts + pot_duration);
+        assert_eq!(created_second_pot.end_timestamp, end_ts + 10);
     }
 
     #[test]
     fn test_draw_lottery() {
-        let init_timestamp = 1_725_000_000;
-        let mut svm = LiteSVM::new();
-
-        let mut fake_clock = Clock {
-            slot: 1,
-            epoch_start_timestamp: 0,
-            epoch: 0,
-            leader_schedule_epoch: 0,
-            unix_timestamp: init_timestamp,
-        };
-        svm.set_sysvar(&fake_clock);
-        let program_id = open_lotto::ID;
-        svm.add_program(program_id, PROGRAM_BYTES);
-        svm.add_program(spl_token::id(), include_bytes!("spl_token.so"));
-
-        // payer
-        let payer = Keypair::new();
-        svm.airdrop(&payer.pubkey(), 1_000_000_000);
-        let end_ts: u64 = init_timestamp as u64 + 100;
-        let pot_duration: u64 = 100;
-        let manager_name = String::from("daily");
-
-        // Create token mint
-        let mint_keypair = Keypair::new();
-        let mint_account = create_mint_account(&payer.pubkey());
-        svm.set_account(mint_keypair.pubkey(), mint_account);
-
-        // Derive PDAs
-        let (pot_manager, _) = Pubkey::find_program_address(
-            &[b"manager", payer.pubkey().as_ref(), manager_name.as_bytes()],
-            &program_id
-        );
-        let (first_pot, _) = Pubkey::find_program_address(
-            &[b"pot", pot_manager.as_ref(), &end_ts.to_le_bytes()],
-            &program_id
-        );
-        let (next_pot, _) = Pubkey::find_program_address(
-            &[b"pot", pot_manager.as_ref(), &(end_ts + pot_duration).to_le_bytes()],
-            &program_id
-        );
-        let (treasury_token_account, _) = Pubkey::find_program_address(
-            &[b"treasury"],
-            &program_id
-        );
-        let (escrow_token_account, _) = Pubkey::find_program_address(
-            &[b"escrow"],
-            &program_id
-        );
-
-        // Initialize pot manager
-        let accounts = vec![
-            AccountMeta::new(pot_manager, false),
-            AccountMeta::new_readonly(mint_keypair.pubkey(), false),
-            AccountMeta::new(treasury_token_account, false),
-            AccountMeta::new(escrow_token_account, false),
-            AccountMeta::new(first_pot, false),
-            AccountMeta::new(next_pot, false),
-            AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(system_program::ID, false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-            AccountMeta::new_readonly(solana_program::sysvar::rent::ID, false),
-        ];
-
-        let data = InitPotManager {
-            end_ts,
-            pot_duration,
-            manager_name: manager_name.clone()
-        }.data();
-        let ix = Instruction::new_with_bytes(program_id, &data, accounts);
-        let message = Message::new(&[ix], Some(&payer.pubkey()));
-        let tx = Transaction::new(&[&payer], message, svm.latest_blockhash());
-        let result = svm.send_transaction(tx);
-        assert!(result.is_ok(), "InitPotManager failed: {:?}", result);
+        let mut lotto = TestLotto::initialized(100);
 
         // Create and enter a ticket
-        let user = Keypair::new();
-        svm.airdrop(&user.pubkey(), 1_000_000_000);
-
-        // Create user token account with enough tokens
-        let user_token_account_keypair = Keypair::new();
-        let user_token_account = create_token_account(
-            &mint_keypair.pubkey(),
-            &user.pubkey(),
-            100_000_000, // 100 tokens
-        );
-        svm.set_account(user_token_account_keypair.pubkey(), user_token_account);
-
-        let current_pot: Pot = get_account(&first_pot, &svm);
-        let (current_ticket, _) = Pubkey::find_program_address(
-            &[b"ticket", first_pot.as_ref(), &current_pot.total_participants.to_le_bytes()],
-            &program_id
-        );
-
-        let enter_data = EnterTicket {}.data();
-        let enter_accounts = vec![
-            AccountMeta::new(user.pubkey(), true),
-            AccountMeta::new(first_pot, false),
-            AccountMeta::new(current_ticket, false),
-            AccountMeta::new(user_token_account_keypair.pubkey(), false),
-            AccountMeta::new(escrow_token_account, false),
-            AccountMeta::new(treasury_token_account, false),
-            AccountMeta::new_readonly(mint_keypair.pubkey(), false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-            AccountMeta::new_readonly(system_program::ID, false),
-        ];
-        let ix = Instruction::new_with_bytes(program_id, &enter_data, enter_accounts);
-        let message = Message::new(&[ix], Some(&user.pubkey()));
-        let tx = Transaction::new(&[&user], message, svm.latest_blockhash());
-        let result = svm.send_transaction(tx);
-        assert!(result.is_ok(), "EnterTicket failed: {:?}", result);
-
-        // Create mock Switchboard randomness account
-        let mut rng = thread_rng();
-        let randomness_pubkey = Pubkey::new_unique();
-
-        let mut randomness_data: Vec<u8> = vec![];
-        // 8-byte discriminator
-        randomness_data.extend_from_slice(&[10, 66, 229, 135, 220, 239, 217, 114]);
-        // authority: Pubkey (32 bytes)
-        randomness_data.extend_from_slice(&[0u8; 32]);
-        // queue: Pubkey (32 bytes)
-        randomness_data.extend_from_slice(&[0u8; 32]);
-        // seed_slothash: [u8; 32]
-        randomness_data.extend_from_slice(&[0u8; 32]);
-        // seed_slot: u64 - must be clock.slot - 1 for draw_lottery check
-        randomness_data.extend_from_slice(&1u64.to_le_bytes());
-        // oracle: Pubkey (32 bytes)
-        randomness_data.extend_from_slice(&[0u8; 32]);
-        // reveal_slot: u64
-        randomness_data.extend_from_slice(&2u64.to_le_bytes());
-        // value: [u8; 32]
-        let random_value: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
-        randomness_data.extend_from_slice(&random_value);
-        // _ebuf2: [u8; 96]
-        randomness_data.extend_from_slice(&[0u8; 96]);
-        // _ebuf1: [u8; 128]
-        randomness_data.extend_from_slice(&[0u8; 128]);
-
-        let switchboard_pid = Pubkey::try_from("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv").unwrap();
-        let randomness_account = SolanaAccount {
-            lamports: 1_000_000,
-            data: randomness_data,
-            owner: switchboard_pid,
-            executable: false,
-            rent_epoch: 0,
-        };
-        svm.set_account(randomness_pubkey, randomness_account);
+        let user = lotto.funded_keypair();
+        lotto.enter_ticket(&user);
+
+        // Create mock Switchboard randomness account; seed_slot must be clock.slot - 1
+        let randomness_pubkey = lotto.randomness_account(1, 2);
 
         // Update clock for draw
-        fake_clock.slot = 2;
-        fake_clock.unix_timestamp += 10;
-        svm.set_sysvar(&fake_clock);
-
-        let (wager_escrow, _) = Pubkey::find_program_address(&[b"wagerEscrow"], &program_id);
-
-        let draw_data = DrawLottery {
-            randomness_account: randomness_pubkey
-        }.data();
-        let draw_accounts = vec![
-            AccountMeta::new(first_pot, false),
-            AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(randomness_pubkey, false),
-            AccountMeta::new(wager_escrow, false),
-            AccountMeta::new_readonly(system_program::ID, false),
-        ];
-        let ix = Instruction::new_with_bytes(program_id, &draw_data, draw_accounts);
-        let message = Message::new(&[ix], Some(&payer.pubkey()));
-        let tx = Transaction::new(&[&payer], message, svm.latest_blockhash());
-        let result = svm.send_transaction(tx);
+        lotto.warp(2, lotto.end_ts as i64 + 1);
+
+        let bounty_account = lotto.token_account(&lotto.payer.pubkey(), 0);
+        let ix = lotto.draw_ix(&lotto.payer.pubkey(), &randomness_pubkey, &bounty_account);
+        let result = send(&mut lotto.svm, &[ix], &lotto.payer);
         assert!(result.is_ok(), "DrawLottery failed: {:?}", result);
 
         // Verify randomness account is stored in pot
-        let updated_pot: Pot = get_account(&first_pot, &svm);
+        let updated_pot: Pot = get_account(&lotto.first_pot, &lotto.svm);
         assert_eq!(updated_pot.randomness_account, randomness_pubkey);
+        assert_eq!(updated_pot.seed_slot, 1);
+
+        // Verify the wager is tracked against the pot and its manager
+        assert_eq!(updated_pot.wager, 100);
+        let updated_pot_manager: PotManager = get_account(&lotto.pot_manager, &lotto.svm);
+        assert_eq!(updated_pot_manager.wager_balance, 100);
+    }
+
+    #[test]
+    fn test_draw_before_pot_ends() {
+        let mut lotto = TestLotto::initialized(100);
+        let user = lotto.funded_keypair();
+        lotto.enter_ticket(&user);
+        let randomness = lotto.randomness_account(1, 0);
+        lotto.warp(2, lotto.end_ts as i64);
+
+        let bounty_account = lotto.token_account(&lotto.payer.pubkey(), 0);
+        let ix = lotto.draw_ix(&lotto.payer.pubkey(), &randomness, &bounty_account);
+        let r = send(&mut lotto.svm, &[ix], &lotto.payer).unwrap_err();
+        assert_eq!(r, program_error(0, ErrorCode::PotNotEnded));
+    }
+
+    #[test]
+    fn test_draw_empty_pot() {
+        let mut lotto = TestLotto::initialized(100);
+        let randomness = lotto.randomness_account(1, 0);
+        lotto.warp(2, lotto.end_ts as i64 + 1);
+
+        let bounty_account = lotto.token_account(&lotto.payer.pubkey(), 0);
+        let ix = lotto.draw_ix(&lotto.payer.pubkey(), &randomness, &bounty_account);
+        let r = send(&mut lotto.svm, &[ix], &lotto.payer).unwrap_err();
+        assert_eq!(r, program_error(0, ErrorCode::PotEmpty));
+    }
+
+    #[test]
+    fn test_draw_rejects_foreign_randomness() {
+        let mut lotto = TestLotto::initialized(100);
+        let user = lotto.funded_keypair();
+        lotto.enter_ticket(&user);
+        let randomness = lotto.randomness_account(1, 0);
+        lotto.warp(2, lotto.end_ts as i64 + 1);
+        let bounty_account = lotto.token_account(&lotto.payer.pubkey(), 0);
+
+        // The same data under another owner is not Switchboard randomness
+        let forged = Pubkey::new_unique();
+        let mut forged_account = lotto.svm.get_account(&randomness).unwrap();
+        forged_account.owner = Pubkey::new_unique();
+        lotto.svm.set_account(forged, forged_account).unwrap();
+        let ix = lotto.draw_ix(&lotto.payer.pubkey(), &forged, &bounty_account);
+        let r = send(&mut lotto.svm, &[ix], &lotto.payer).unwrap_err();
+        assert_eq!(r, TransactionError::InstructionError(0, InstructionError::Custom(AnchorErrorCode::ConstraintOwner as u32)));
+
+        // The pot stores the account the draw read, not a different argument
+        let mut ix = lotto.draw_ix(&lotto.payer.pubkey(), &randomness, &bounty_account);
+        ix.data = DrawLottery { randomness_account: Pubkey::new_unique() }.data();
+        let r = send(&mut lotto.svm, &[ix], &lotto.payer).unwrap_err();
+        assert_eq!(r, program_error(0, ErrorCode::InvalidRandomnessAccount));
+    }
+
+    #[test]
+    fn test_crank_bounty() {
+        let mut lotto = TestLotto::initialized(100);
+
+        // Bounties are capped so a pot's two never exceed the fee on its one ticket
+        let r = lotto.set_crank_bounty(MAX_CRANK_BOUNTY + 1).unwrap_err();
+        assert_eq!(r, program_error(0, ErrorCode::BountyTooLarge));
+
+        // Pay 0.5 tokens to whoever draws and to whoever settles, out of an allowance that
+        // only covers the draw's in full
+        let bounty = MAX_CRANK_BOUNTY;
+        assert!(lotto.set_crank_bounty(bounty).is_ok());
+        let user = lotto.funded_keypair();
+        lotto.enter_ticket(&user);
+        lotto.approve_bounty_allowance(bounty + 100_000);
+        let (_, randomness) = lotto.drawn_pot_entered();
+
+        // The drawer's bounty comes out of the treasury, not the prize pool
+        let pot: Pot = get_account(&lotto.first_pot, &lotto.svm);
+        assert_eq!(pot.bounties_paid, bounty);
+        assert_eq!(pot.prize_amount(), POT_AMOUNT);
+        assert_eq!(token_balance(&lotto.treasury_token_account, &lotto.svm), FEE - bounty);
+
+        // A third party can't draw again for a second bounty, but can settle for one
+        let cranker = lotto.funded_keypair();
+        let cranker_bounty_account = lotto.token_account(&cranker.pubkey(), 0);
+        let ix = lotto.draw_ix(&cranker.pubkey(), &randomness, &cranker_bounty_account);
+        let r = send(&mut lotto.svm, &[ix], &cranker).unwrap_err();
+        assert_eq!(r, program_error(0, ErrorCode::PotAlreadyDrawn));
+
+        let ix = lotto.settle_ix(&cranker.pubkey(), &randomness, &cranker_bounty_account);
+        let result = send(&mut lotto.svm, &[ix], &cranker);
+        assert!(result.is_ok(), "SettleLottery failed: {:?}", result);
+
+        // The settle bounty is cut to what is left of the allowance
+        let settled_pot: Pot = get_account(&lotto.first_pot, &lotto.svm);
+        assert_eq!(token_balance(&cranker_bounty_account, &lotto.svm), 100_000);
+        assert_eq!(settled_pot.bounties_paid, bounty + 100_000);
+        assert_eq!(settled_pot.prize_amount(), POT_AMOUNT);
+        assert_eq!(token_balance(&lotto.escrow_token_account, &lotto.svm), POT_AMOUNT);
+    }
+
+    #[test]
+    fn test_settle_lottery() {
+        let mut lotto = TestLotto::initialized(100);
+        let user = lotto.funded_keypair();
+        let (_, randomness) = lotto.drawn_pot(&user);
+        let bounty_account = lotto.token_account(&lotto.payer.pubkey(), 0);
+
+        // Only the randomness bound at draw time can settle the pot
+        let other_randomness = lotto.randomness_account(1, 2);
+        let ix = lotto.settle_ix(&lotto.payer.pubkey(), &other_randomness, &bounty_account);
+        let r = send(&mut lotto.svm, &[ix], &lotto.payer).unwrap_err();
+        assert_eq!(r, program_error(0, ErrorCode::InvalidRandomnessAccount));
+
+        let ix = lotto.settle_ix(&lotto.payer.pubkey(), &randomness, &bounty_account);
+        let result = send(&mut lotto.svm, &[ix], &lotto.payer);
+        assert!(result.is_ok(), "SettleLottery failed: {:?}", result);
+
+        // A single ticket always wins
+        let settled_pot: Pot = get_account(&lotto.first_pot, &lotto.svm);
+        assert!(settled_pot.settled);
+        assert_eq!(settled_pot.winning_slot, 0);
+
+        let ix = lotto.settle_ix(&lotto.payer.pubkey(), &randomness, &bounty_account);
+        let r = send(&mut lotto.svm, &[ix], &lotto.payer).unwrap_err();
+        assert_eq!(r, program_error(0, ErrorCode::PotAlreadySettled));
+    }
+
+    #[test]
+    fn test_reveal_and_settle() {
+        let mut lotto = TestLotto::initialized(100);
+        let bounty_account = lotto.token_account(&lotto.payer.pubkey(), 0);
+
+        // A pot of four tickets drawn with randomness committed at slot 1_000
+        let randomness = lotto.randomness_account(1_000, 0);
+        let pot: Pot = get_account(&lotto.first_pot, &lotto.svm);
+        let drawn_pot = Pot {
+            total_participants: 4,
+            randomness_account: randomness,
+            seed_slot: 1_000,
+            ..pot
+        };
+        lotto.write_account(lotto.first_pot, &drawn_pot);
+        lotto.warp(1_002, lotto.end_ts as i64 + 1);
+
+        // Nothing to settle on until the oracle reveals
+        let ix = lotto.settle_ix(&lotto.payer.pubkey(), &randomness, &bounty_account);
+        let r = send(&mut lotto.svm, &[ix], &lotto.payer).unwrap_err();
+        assert_eq!(r, program_error(0, ErrorCode::RandomnessNotResolved));
+
+        let value: [u8; 32] = thread_rng().gen();
+        let reveal = lotto.reveal_ix(&randomness, value);
+        let settle = lotto.settle_ix(&lotto.payer.pubkey(), &randomness, &bounty_account);
+        let result = send(&mut lotto.svm, &[reveal, settle], &lotto.payer);
+        assert!(result.is_ok(), "Reveal and settle failed: {:?}", result);
+
+        let settled_pot: Pot = get_account(&lotto.first_pot, &lotto.svm);
+        assert!(settled_pot.settled);
+        let number = u64::from_le_bytes(value[..8].try_into().unwrap());
+        assert_eq!(settled_pot.winning_slot, number % 4);
+    }
+
+    #[test]
+    fn test_settle_rejects_recommitted_randomness() {
+        let mut lotto = TestLotto::initialized(100);
+        let user = lotto.funded_keypair();
+        let (_, randomness) = lotto.drawn_pot(&user);
+        let bounty_account = lotto.token_account(&lotto.payer.pubkey(), 0);
+
+        // Re-committing after the draw moves the seed slot away from the one the pot stored
+        lotto.warp(5, lotto.end_ts as i64 + 1);
+        let commit = lotto.commit_ix(&randomness);
+        let settle = lotto.settle_ix(&lotto.payer.pubkey(), &randomness, &bounty_account);
+        let r = send(&mut lotto.svm, &[commit, settle], &lotto.payer).unwrap_err();
+        assert_eq!(r, program_error(1, ErrorCode::RandomnessSeedMismatch));
+        assert!(!get_account::<Pot>(&lotto.first_pot, &lotto.svm).settled);
+    }
+
+    #[test]
+    fn test_redraw_after_timeout() {
+        let mut lotto = TestLotto::initialized(100);
+        assert!(lotto.set_crank_bounty(MAX_CRANK_BOUNTY).is_ok());
+        let user = lotto.funded_keypair();
+        lotto.enter_ticket(&user);
+        lotto.approve_bounty_allowance(2 * MAX_CRANK_BOUNTY);
+        // Drawn at slot 2, and its randomness never revealed
+        let (_, stale) = lotto.drawn_pot_entered();
+        let redraw_slot = 2 + REDRAW_TIMEOUT_SLOTS;
+
+        // Until the draw times out, nobody can replace it
+        let cranker = lotto.funded_keypair();
+        let cranker_bounty_account = lotto.token_account(&cranker.pubkey(), 0);
+        let randomness = lotto.randomness_account(0, 0);
+        lotto.warp(redraw_slot - 1, lotto.end_ts as i64 + 1);
+        let commit = lotto.commit_ix(&randomness);
+        let draw = lotto.draw_ix(&cranker.pubkey(), &randomness, &cranker_bounty_account);
+        let r = send(&mut lotto.svm, &[commit, draw], &cranker).unwrap_err();
+        assert_eq!(r, program_error(1, ErrorCode::PotAlreadyDrawn));
+
+        // Once it has, anyone can draw again with fresh randomness, for no second bounty
+        lotto.warp(redraw_slot, lotto.end_ts as i64 + 1);
+        let commit = lotto.commit_ix(&randomness);
+        let draw = lotto.draw_ix(&cranker.pubkey(), &randomness, &cranker_bounty_account);
+        let result = send(&mut lotto.svm, &[commit, draw], &cranker);
+        assert!(result.is_ok(), "Redraw failed: {:?}", result);
+        let pot: Pot = get_account(&lotto.first_pot, &lotto.svm);
+        assert_eq!(pot.randomness_account, randomness);
+        assert_eq!(pot.seed_slot, redraw_slot - 1);
+        assert_eq!(pot.draw_slot, redraw_slot);
+        assert_eq!(pot.bounties_paid, MAX_CRANK_BOUNTY);
+        assert_eq!(token_balance(&cranker_bounty_account, &lotto.svm), 0);
+
+        // The replaced draw can no longer settle the pot, the redraw can once revealed
+        let settle = lotto.settle_ix(&cranker.pubkey(), &stale, &cranker_bounty_account);
+        let r = send(&mut lotto.svm, &[settle], &cranker).unwrap_err();
+        assert_eq!(r, program_error(0, ErrorCode::InvalidRandomnessAccount));
+        lotto.warp(redraw_slot + 1, lotto.end_ts as i64 + 1);
+        let reveal = lotto.reveal_ix(&randomness, [7; 32]);
+        let settle = lotto.settle_ix(&cranker.pubkey(), &randomness, &cranker_bounty_account);
+        let result = send(&mut lotto.svm, &[reveal, settle], &cranker);
+        assert!(result.is_ok(), "SettleLottery failed: {:?}", result);
+
+        // A settled pot is never drawn again
+        let randomness = lotto.randomness_account(0, 0);
+        lotto.warp(redraw_slot + 2 * REDRAW_TIMEOUT_SLOTS, lotto.end_ts as i64 + 1);
+        let commit = lotto.commit_ix(&randomness);
+        let draw = lotto.draw_ix(&cranker.pubkey(), &randomness, &cranker_bounty_account);
+        let r = send(&mut lotto.svm, &[commit, draw], &cranker).unwrap_err();
+        assert_eq!(r, program_error(1, ErrorCode::PotAlreadySettled));
+    }
+
+    #[test]
+    fn test_payout_winner() {
+        let mut lotto = TestLotto::initialized(100);
+        let user = lotto.funded_keypair();
+
+        // Drawing in auto-payout mode has the drawer reserve rent for the winner's token account
+        assert!(lotto.set_auto_payout(true).is_ok());
+        let pot_lamports = lotto.svm.get_balance(&lotto.first_pot).unwrap();
+        let (ticket, randomness) = lotto.drawn_pot(&user);
+        let reserve = Rent::default().minimum_balance(TokenAccount::LEN);
+        assert_eq!(get_account::<Pot>(&lotto.first_pot, &lotto.svm).payout_reserve, reserve);
+        assert_eq!(lotto.svm.get_balance(&lotto.first_pot).unwrap(), pot_lamports + reserve);
+
+        // Prizes can't be pushed before the pot is settled
+        let cranker = lotto.funded_keypair();
+        let ix = lotto.payout_ix(&ticket, &user.pubkey(), &cranker.pubkey());
+        let r = send(&mut lotto.svm, &[ix], &cranker).unwrap_err();
+        assert_eq!(r, program_error(0, ErrorCode::PotNotSettled));
+
+        let bounty_account = lotto.token_account(&cranker.pubkey(), 0);
+        let ix = lotto.settle_ix(&cranker.pubkey(), &randomness, &bounty_account);
+        let result = send(&mut lotto.svm, &[ix], &cranker);
+        assert!(result.is_ok(), "SettleLottery failed: {:?}", result);
+
+        // Nor outside auto-payout mode
+        assert!(lotto.set_auto_payout(false).is_ok());
+        let ix = lotto.payout_ix(&ticket, &user.pubkey(), &cranker.pubkey());
+        let r = send(&mut lotto.svm, &[ix], &cranker).unwrap_err();
+        assert_eq!(r, program_error(0, ErrorCode::AutoPayoutDisabled));
+        assert!(lotto.set_auto_payout(true).is_ok());
+
+        // The winner has no token account yet; payout creates it and pushes the prize
+        let cranker_lamports = lotto.svm.get_balance(&cranker.pubkey()).unwrap();
+        let ix = lotto.payout_ix(&ticket, &user.pubkey(), &cranker.pubkey());
+        let result = send(&mut lotto.svm, &[ix], &cranker);
+        assert!(result.is_ok(), "PayoutWinner failed: {:?}", result);
+
+        let paid_pot: Pot = get_account(&lotto.first_pot, &lotto.svm);
+        assert!(paid_pot.prize_paid);
+        assert_eq!(paid_pot.payout_reserve, 0);
+        let winner_token_account = get_associated_token_address(&user.pubkey(), &lotto.mint);
+        assert_eq!(token_balance(&winner_token_account, &lotto.svm), POT_AMOUNT);
+        assert_eq!(token_balance(&lotto.escrow_token_account, &lotto.svm), 0);
+        // The reserve refunds the account rent, leaving the cranker only the fee
+        assert_eq!(lotto.svm.get_balance(&cranker.pubkey()).unwrap(), cranker_lamports - 5_000);
+
+        // The prize can't be paid twice
+        let ix = lotto.payout_ix(&ticket, &user.pubkey(), &cranker.pubkey());
+        let r = send(&mut lotto.svm, &[ix], &cranker).unwrap_err();
+        assert_eq!(r, program_error(0, ErrorCode::PrizeAlreadyPaid));
+    }
+
+    #[test]
+    fn test_auto_payout_enabled_after_draw() {
+        let mut lotto = TestLotto::initialized(100);
+        let user = lotto.funded_keypair();
+
+        // Drawn and settled before the manager switched to auto-payout: nothing was reserved
+        let (ticket, randomness) = lotto.drawn_pot(&user);
+        assert_eq!(get_account::<Pot>(&lotto.first_pot, &lotto.svm).payout_reserve, 0);
+        let cranker = lotto.funded_keypair();
+        let bounty_account = lotto.token_account(&cranker.pubkey(), 0);
+        let ix = lotto.settle_ix(&cranker.pubkey(), &randomness, &bounty_account);
+        let result = send(&mut lotto.svm, &[ix], &cranker);
+        assert!(result.is_ok(), "SettleLottery failed: {:?}", result);
+        assert!(lotto.set_auto_payout(true).is_ok());
+
+        // So the pot is left for its winner to claim rather than paid out at the cranker's cost
+        let cranker_lamports = lotto.svm.get_balance(&cranker.pubkey()).unwrap();
+        let ix = lotto.payout_ix(&ticket, &user.pubkey(), &cranker.pubkey());
+        let r = send(&mut lotto.svm, &[ix], &cranker).unwrap_err();
+        assert_eq!(r, program_error(0, ErrorCode::NoPayoutReserve));
+        assert!(!get_account::<Pot>(&lotto.first_pot, &lotto.svm).prize_paid);
+        assert_eq!(lotto.svm.get_balance(&cranker.pubkey()).unwrap(), cranker_lamports);
+    }
+
+    #[test]
+    fn test_withdraw_wager_escrow() {
+        let wager: u64 = 1_000_000;
+        let mut lotto = TestLotto::initialized(wager);
+
+        // Simulate a draw having escrowed the wager
+        lotto.svm.airdrop(&lotto.wager_escrow, wager).unwrap();
+        let mut created_pot_manager: PotManager = get_account(&lotto.pot_manager, &lotto.svm);
+        created_pot_manager.wager_balance = wager;
+        lotto.write_account(lotto.pot_manager, &created_pot_manager);
+
+        let oracle_operator = Pubkey::new_unique();
+        let withdraw = |lotto: &TestLotto, amount: u64| {
+            Instruction::new_with_bytes(
+                lotto.program_id,
+                &WithdrawWagerEscrow { amount }.data(),
+                vec![
+                    AccountMeta::new(lotto.pot_manager, false),
+                    AccountMeta::new(lotto.payer.pubkey(), true),
+                    AccountMeta::new(lotto.wager_escrow, false),
+                    AccountMeta::new(oracle_operator, false),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                ],
+            )
+        };
+
+        // Cannot withdraw more than the manager has escrowed
+        let ix = withdraw(&lotto, wager + 1);
+        let r = send(&mut lotto.svm, &[ix], &lotto.payer).unwrap_err();
+        assert_eq!(r, program_error(0, ErrorCode::InsufficientWagerBalance));
+
+        // Withdraw everything to the oracle operator
+        let ix = withdraw(&lotto, wager);
+        let result = send(&mut lotto.svm, &[ix], &lotto.payer);
+        assert!(result.is_ok(), "WithdrawWagerEscrow failed: {:?}", result);
+
+        assert_eq!(lotto.svm.get_balance(&oracle_operator).unwrap_or(0), wager);
+        let updated_pot_manager: PotManager = get_account(&lotto.pot_manager, &lotto.svm);
+        assert_eq!(updated_pot_manager.wager_balance, 0);
+    }
+
+    #[test]
+    fn test_roll_pot_manager() {
+        let mut lotto = TestLotto::initialized(100);
+        let (end_ts, pot_duration) = (lotto.end_ts, lotto.pot_duration);
+
+        // The payout reserve waits for the draw, even in auto-payout mode
+        assert!(lotto.set_auto_payout(true).is_ok());
+
+        // Anyone can roll, so a fresh keeper pays for the new pot
+        let keeper = lotto.funded_keypair();
+        let (roll, rolled_pot) = lotto.roll_ix(&keeper.pubkey());
+
+        // Cannot roll before the current pot ends
+        let r = send(&mut lotto.svm, std::slice::from_ref(&roll), &keeper).unwrap_err();
+        assert_eq!(r, program_error(0, ErrorCode::PotNotEnded));
+
+        // Roll once the first pot has ended; without tickets there is nothing to settle
+        lotto.end_first_pot();
+        let result = send(&mut lotto.svm, &[roll], &keeper);
+        assert!(result.is_ok(), "RollPotManager failed: {:?}", result);
+
+        let rolled_pot_manager: PotManager = get_account(&lotto.pot_manager, &lotto.svm);
+        assert_eq!(rolled_pot_manager.timestamps, (end_ts + pot_duration, end_ts + 2 * pot_duration));
+
+        let created_pot: Pot = get_account(&rolled_pot, &lotto.svm);
+        assert_eq!(created_pot.pot_manager, lotto.pot_manager);
+        assert_eq!(created_pot.start_timestamp, end_ts + pot_duration + 1);
+        assert_eq!(created_pot.end_timestamp, end_ts + 2 * pot_duration);
+        assert!(!created_pot.settled);
+        assert_eq!(created_pot.payout_reserve, 0);
+        assert_eq!(lotto.svm.get_balance(&rolled_pot).unwrap(), Rent::default().minimum_balance(Pot::space()));
+    }
+
+    #[test]
+    fn test_roll_requires_settled_pot() {
+        let mut lotto = TestLotto::initialized(100);
+        let user = lotto.funded_keypair();
+        let (_, randomness) = lotto.drawn_pot(&user);
+
+        // The first pot has ended but its winner has not been picked
+        let (roll, _) = lotto.roll_ix(&lotto.payer.pubkey());
+        let r = send(&mut lotto.svm, std::slice::from_ref(&roll), &lotto.payer).unwrap_err();
+        assert_eq!(r, program_error(0, ErrorCode::PotNotSettled));
+
+        let bounty_account = lotto.token_account(&lotto.payer.pubkey(), 0);
+        let settle = lotto.settle_ix(&lotto.payer.pubkey(), &randomness, &bounty_account);
+        let result = send(&mut lotto.svm, &[settle, roll], &lotto.payer);
+        assert!(result.is_ok(), "SettleLottery + RollPotManager failed: {:?}", result);
+
+        let rolled_pot_manager: PotManager = get_account(&lotto.pot_manager, &lotto.svm);
+        assert_eq!(rolled_pot_manager.timestamps.0, lotto.end_ts + lotto.pot_duration);
+    }
+
+    #[test]
+    fn test_commit_and_draw_same_transaction() {
+        let mut lotto = TestLotto::initialized(100);
+        let user = lotto.funded_keypair();
+        lotto.enter_ticket(&user);
+
+        // Draws need the pot to have ended
+        lotto.warp(10, lotto.end_ts as i64 + 1);
+        let bounty_account = lotto.token_account(&lotto.payer.pubkey(), 0);
+
+        // Uncommitted randomness account (seed_slot 0)
+        let randomness = lotto.randomness_account(0, 0);
+        let commit_ix = lotto.commit_ix(&randomness);
+        let draw_ix = lotto.draw_ix(&lotto.payer.pubkey(), &randomness, &bounty_account);
+
+        // Commit in one transaction, draw in the next slot: the seed slot is already stale
+        assert!(send(&mut lotto.svm, std::slice::from_ref(&commit_ix), &lotto.payer).is_ok());
+        lotto.warp(11, lotto.clock.unix_timestamp);
+        let r = send(&mut lotto.svm, std::slice::from_ref(&draw_ix), &lotto.payer).unwrap_err();
+        assert_eq!(r, program_error(0, ErrorCode::RandomnessAlreadyRevealed));
+
+        // Commit and draw in the same transaction always see seed_slot == clock.slot - 1
+        lotto.warp(12, lotto.clock.unix_timestamp);
+        let result = send(&mut lotto.svm, &[commit_ix, draw_ix], &lotto.payer);
+        assert!(result.is_ok(), "Commit + DrawLottery failed: {:?}", result);
+
+        let drawn_pot: Pot = get_account(&lotto.first_pot, &lotto.svm);
+        assert_eq!(drawn_pot.randomness_account, randomness);
+        assert_eq!(drawn_pot.wager, 100);
+    }
+
+    #[test]
+    fn test_keeper_lease() {
+        let mut lotto = TestLotto::initialized(100);
+        let user = lotto.funded_keypair();
+        lotto.enter_ticket(&user);
+
+        // Draws need the pot to have ended
+        lotto.warp(10, lotto.end_ts as i64 + 1);
+        let bounty_account = lotto.token_account(&lotto.payer.pubkey(), 0);
+
+        // Two keepers, neither of them the manager authority
+        let primary = lotto.funded_keypair();
+        let standby = lotto.funded_keypair();
+
+        let acquire = |lotto: &mut TestLotto, keeper: &Keypair, duration_slots: u64| {
+            let ix = Instruction::new_with_bytes(
+                lotto.program_id,
+                &AcquireKeeperLease { duration_slots }.data(),
+                vec![
+                    AccountMeta::new_readonly(lotto.pot_manager, false),
+                    AccountMeta::new(lotto.keeper_lease, false),
+                    AccountMeta::new_readonly(keeper.pubkey(), true),
+                    AccountMeta::new(keeper.pubkey(), true),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                ],
+            );
+            send(&mut lotto.svm, &[ix], keeper)
+        };
+        let set_keepers = |lotto: &mut TestLotto, keepers: Vec<Pubkey>| {
+            let ix = Instruction::new_with_bytes(
+                lotto.program_id,
+                &SetLeaseKeepers { keepers }.data(),
+                vec![
+                    AccountMeta::new_readonly(lotto.pot_manager, false),
+                    AccountMeta::new(lotto.keeper_lease, false),
+                    AccountMeta::new(lotto.payer.pubkey(), true),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                ],
+            );
+            let payer = lotto.payer.insecure_clone();
+            send(&mut lotto.svm, &[ix], &payer)
+        };
+
+        // Nobody but the authority can acquire the lease until it allows them
+        assert_eq!(acquire(&mut lotto, &primary, 0), Err(program_error(0, ErrorCode::InvalidLeaseDuration)));
+        assert_eq!(acquire(&mut lotto, &primary, MAX_LEASE_SLOTS + 1), Err(program_error(0, ErrorCode::InvalidLeaseDuration)));
+        assert_eq!(acquire(&mut lotto, &primary, 100), Err(program_error(0, ErrorCode::KeeperNotAllowed)));
+        let too_many = (0..=MAX_LEASE_KEEPERS).map(|_| Pubkey::new_unique()).collect();
+        assert_eq!(set_keepers(&mut lotto, too_many), Err(program_error(0, ErrorCode::TooManyLeaseKeepers)));
+        assert!(set_keepers(&mut lotto, vec![primary.pubkey(), standby.pubkey()]).is_ok());
+        assert!(acquire(&mut lotto, &primary, 100).is_ok());
+        let lease: KeeperLease = get_account(&lotto.keeper_lease, &lotto.svm);
+        assert_eq!((lease.keeper, lease.expiry_slot), (primary.pubkey(), 110));
+
+        // The standby can neither take the live lease nor draw while it holds
+        assert_eq!(acquire(&mut lotto, &standby, 100), Err(program_error(0, ErrorCode::KeeperLeaseHeld)));
+
+        let randomness = lotto.randomness_account(0, 0);
+        let commit_and_draw = |lotto: &TestLotto, keeper: &Keypair| {
+            vec![lotto.commit_ix(&randomness), lotto.draw_ix(&keeper.pubkey(), &randomness, &bounty_account)]
+        };
+        let ixs = commit_and_draw(&lotto, &standby);
+        let r = send(&mut lotto.svm, &ixs, &standby).unwrap_err();
+        assert_eq!(r, program_error(1, ErrorCode::KeeperLeaseHeld));
+
+        // The holder renews from the current slot
+        lotto.warp(60, lotto.clock.unix_timestamp);
+        assert!(acquire(&mut lotto, &primary, 100).is_ok());
+        let lease: KeeperLease = get_account(&lotto.keeper_lease, &lotto.svm);
+        assert_eq!(lease.expiry_slot, 160);
+
+        // Once the lease lapses the standby takes over and draws
+        lotto.warp(161, lotto.clock.unix_timestamp);
+        assert!(acquire(&mut lotto, &standby, 100).is_ok());
+        let lease: KeeperLease = get_account(&lotto.keeper_lease, &lotto.svm);
+        assert_eq!(lease.keeper, standby.pubkey());
+
+        let ixs = commit_and_draw(&lotto, &standby);
+        let result = send(&mut lotto.svm, &ixs, &standby);
+        assert!(result.is_ok(), "Commit + DrawLottery failed: {:?}", result);
+
+        // Disallowing the holder revokes its lease, and it can't acquire it again
+        assert!(set_keepers(&mut lotto, vec![primary.pubkey()]).is_ok());
+        let lease: KeeperLease = get_account(&lotto.keeper_lease, &lotto.svm);
+        assert_eq!((lease.keeper, lease.expiry_slot), (Pubkey::default(), 0));
+        assert_eq!(acquire(&mut lotto, &standby, 100), Err(program_error(0, ErrorCode::KeeperNotAllowed)));
+        assert!(acquire(&mut lotto, &primary, 100).is_ok());
+
+        // The manager authority can always take the lease back
+        let payer = lotto.payer.insecure_clone();
+        assert!(acquire(&mut lotto, &payer, 100).is_ok());
+        let lease: KeeperLease = get_account(&lotto.keeper_lease, &lotto.svm);
+        assert_eq!(lease.keeper, payer.pubkey());
+    }
+
+    fn token_balance(pubkey: &Pubkey, svm: &LiteSVM) -> u64 {
+        TokenAccount::unpack(&svm.get_account(pubkey).unwrap().data).unwrap().amount
     }
 
     fn get_account<A: anchor_lang::AccountDeserialize>(pubkey: &Pubkey, svm: &LiteSVM) -> A {